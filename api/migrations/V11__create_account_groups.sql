@@ -0,0 +1,13 @@
+CREATE TABLE account_group (
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    CONSTRAINT PK_ACCOUNT_GROUP PRIMARY KEY (id)
+);
+
+CREATE TABLE account_group_member (
+    account_group_id TEXT NOT NULL,
+    account_id TEXT NOT NULL,
+    CONSTRAINT PK_ACCOUNT_GROUP_MEMBER PRIMARY KEY (account_group_id, account_id),
+    CONSTRAINT fk_account_group_member_account_group_id_to_account_group FOREIGN KEY (account_group_id) REFERENCES account_group(id),
+    CONSTRAINT fk_account_group_member_account_id_to_account FOREIGN KEY (account_id) REFERENCES account(id)
+);
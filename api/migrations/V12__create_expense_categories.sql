@@ -0,0 +1,36 @@
+CREATE TABLE expense_category (
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    parent_id TEXT,
+    colour TEXT,
+    hidden INTEGER NOT NULL DEFAULT 0,
+    CONSTRAINT PK_EXPENSE_CATEGORY PRIMARY KEY (id),
+    CONSTRAINT fk_expense_category_parent_id_to_expense_category FOREIGN KEY (parent_id) REFERENCES expense_category(id)
+);
+
+-- The previously compiled in categories keep their stored value as their id so existing expenses still reference them
+INSERT INTO expense_category (id, name) VALUES
+    ('UNKNOWN', 'Unknown'),
+    ('OTHER', 'Other'),
+    ('AI', 'AI'),
+    ('BILLS', 'Bills'),
+    ('CAR', 'Car'),
+    ('CLOTHING', 'Clothing'),
+    ('COFFEE', 'Coffee'),
+    ('ELECTRONICS', 'Electronics'),
+    ('ENTERTAINMENT', 'Entertainment'),
+    ('FITNESS', 'Fitness'),
+    ('GIFTS', 'Gifts'),
+    ('GROCERIES', 'Groceries'),
+    ('HOUSE', 'House'),
+    ('MEDICAL', 'Medical'),
+    ('PETS', 'Pets'),
+    ('RESTAURANTS', 'Restaurants'),
+    ('SMART_HOME', 'Smart Home'),
+    ('SUBSCRIPTIONS', 'Subscriptions'),
+    ('VACATIONS', 'Vacations'),
+    ('WORK', 'Work');
+
+-- Anything stored that wasn't in the compiled in list
+INSERT INTO expense_category (id, name)
+SELECT DISTINCT category, category FROM expense WHERE category NOT IN (SELECT id FROM expense_category);
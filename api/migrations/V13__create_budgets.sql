@@ -0,0 +1,10 @@
+CREATE TABLE budget (
+    id TEXT NOT NULL,
+    category_id TEXT NOT NULL,
+    value TEXT NOT NULL,
+    period TEXT NOT NULL,
+    rollover INTEGER NOT NULL,
+    effective_from TEXT NOT NULL,
+    CONSTRAINT PK_BUDGET PRIMARY KEY (id),
+    CONSTRAINT fk_budget_category_id_to_expense_category FOREIGN KEY (category_id) REFERENCES expense_category(id)
+);
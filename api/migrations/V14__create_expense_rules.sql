@@ -0,0 +1,13 @@
+CREATE TABLE expense_rule (
+    id TEXT NOT NULL,
+    field TEXT NOT NULL,
+    match_type TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    min_value TEXT,
+    max_value TEXT,
+    category_id TEXT NOT NULL,
+    description TEXT,
+    priority INTEGER NOT NULL,
+    CONSTRAINT PK_EXPENSE_RULE PRIMARY KEY (id),
+    CONSTRAINT fk_expense_rule_category_id_to_expense_category FOREIGN KEY (category_id) REFERENCES expense_category(id)
+);
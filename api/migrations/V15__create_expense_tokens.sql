@@ -0,0 +1,6 @@
+CREATE TABLE expense_token (
+    category_id TEXT NOT NULL,
+    token TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    CONSTRAINT PK_EXPENSE_TOKEN PRIMARY KEY (category_id, token)
+);
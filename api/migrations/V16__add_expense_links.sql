@@ -0,0 +1,2 @@
+ALTER TABLE expense ADD COLUMN account_id TEXT REFERENCES account(id);
+ALTER TABLE expense ADD COLUMN transaction_id TEXT REFERENCES account_transaction(id);
@@ -0,0 +1,39 @@
+CREATE TABLE person (
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    me INTEGER NOT NULL,
+    account_id TEXT,
+    CONSTRAINT PK_PERSON PRIMARY KEY (id),
+    CONSTRAINT fk_person_account_id_to_account FOREIGN KEY (account_id) REFERENCES account(id)
+);
+
+CREATE TABLE expense_split (
+    expense_id TEXT NOT NULL,
+    type TEXT NOT NULL,
+    paid_by TEXT NOT NULL,
+    CONSTRAINT PK_EXPENSE_SPLIT PRIMARY KEY (expense_id),
+    CONSTRAINT fk_expense_split_expense_id_to_expense FOREIGN KEY (expense_id) REFERENCES expense(id),
+    CONSTRAINT fk_expense_split_paid_by_to_person FOREIGN KEY (paid_by) REFERENCES person(id)
+);
+
+CREATE TABLE expense_split_share (
+    expense_id TEXT NOT NULL,
+    person_id TEXT NOT NULL,
+    value TEXT,
+    CONSTRAINT PK_EXPENSE_SPLIT_SHARE PRIMARY KEY (expense_id, person_id),
+    CONSTRAINT fk_expense_split_share_expense_id_to_expense_split FOREIGN KEY (expense_id) REFERENCES expense_split(expense_id),
+    CONSTRAINT fk_expense_split_share_person_id_to_person FOREIGN KEY (person_id) REFERENCES person(id)
+);
+
+CREATE TABLE settlement (
+    id TEXT NOT NULL,
+    from_person_id TEXT NOT NULL,
+    to_person_id TEXT NOT NULL,
+    date TEXT NOT NULL,
+    value TEXT NOT NULL,
+    transaction_id TEXT,
+    CONSTRAINT PK_SETTLEMENT PRIMARY KEY (id),
+    CONSTRAINT fk_settlement_from_person_id_to_person FOREIGN KEY (from_person_id) REFERENCES person(id),
+    CONSTRAINT fk_settlement_to_person_id_to_person FOREIGN KEY (to_person_id) REFERENCES person(id),
+    CONSTRAINT fk_settlement_transaction_id_to_account_transaction FOREIGN KEY (transaction_id) REFERENCES account_transaction(id)
+);
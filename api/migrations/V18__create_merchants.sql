@@ -0,0 +1,15 @@
+CREATE TABLE merchant (
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    CONSTRAINT PK_MERCHANT PRIMARY KEY (id)
+);
+
+CREATE TABLE merchant_match (
+    merchant_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    value TEXT NOT NULL,
+    CONSTRAINT PK_MERCHANT_MATCH PRIMARY KEY (merchant_id, kind, value),
+    CONSTRAINT fk_merchant_match_merchant_id_to_merchant FOREIGN KEY (merchant_id) REFERENCES merchant(id)
+);
+
+ALTER TABLE expense ADD COLUMN merchant_id TEXT REFERENCES merchant(id);
@@ -0,0 +1,9 @@
+CREATE TABLE subscription (
+    id TEXT NOT NULL,
+    merchant_id TEXT NOT NULL,
+    cadence TEXT NOT NULL,
+    value TEXT NOT NULL,
+    start TEXT NOT NULL,
+    CONSTRAINT PK_SUBSCRIPTION PRIMARY KEY (id),
+    CONSTRAINT fk_subscription_merchant_id_to_merchant FOREIGN KEY (merchant_id) REFERENCES merchant(id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE expense ADD COLUMN kind TEXT NOT NULL DEFAULT 'PURCHASE';
+ALTER TABLE expense ADD COLUMN original_expense_id TEXT REFERENCES expense(id);
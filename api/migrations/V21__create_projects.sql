@@ -0,0 +1,10 @@
+CREATE TABLE project (
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    start TEXT NOT NULL,
+    end TEXT NOT NULL,
+    budget TEXT,
+    CONSTRAINT PK_PROJECT PRIMARY KEY (id)
+);
+
+ALTER TABLE expense ADD COLUMN project_id TEXT REFERENCES project(id);
@@ -0,0 +1,18 @@
+-- Imported statements repeat the same merchant so external is no longer unique, duplicates are caught on import instead
+DROP INDEX UNQ_EXTERNAL;
+CREATE INDEX IDX_EXPENSE_EXTERNAL ON expense(external);
+
+CREATE TABLE import_profile (
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    delimiter TEXT NOT NULL,
+    header INTEGER NOT NULL,
+    date_column INTEGER NOT NULL,
+    date_format TEXT NOT NULL,
+    external_column INTEGER NOT NULL,
+    description_column INTEGER,
+    value_column INTEGER NOT NULL,
+    negate INTEGER NOT NULL,
+    duplicate_days INTEGER NOT NULL,
+    CONSTRAINT PK_IMPORT_PROFILE PRIMARY KEY (id)
+);
@@ -0,0 +1,22 @@
+CREATE TABLE tag (
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    CONSTRAINT PK_TAG PRIMARY KEY (id)
+);
+CREATE UNIQUE INDEX UNQ_TAG_NAME ON tag(name);
+
+CREATE TABLE expense_tag (
+    expense_id TEXT NOT NULL,
+    tag_id TEXT NOT NULL,
+    CONSTRAINT PK_EXPENSE_TAG PRIMARY KEY (expense_id, tag_id),
+    CONSTRAINT fk_expense_tag_expense_id_to_expense FOREIGN KEY (expense_id) REFERENCES expense(id),
+    CONSTRAINT fk_expense_tag_tag_id_to_tag FOREIGN KEY (tag_id) REFERENCES tag(id)
+);
+
+CREATE TABLE transaction_tag (
+    transaction_id TEXT NOT NULL,
+    tag_id TEXT NOT NULL,
+    CONSTRAINT PK_TRANSACTION_TAG PRIMARY KEY (transaction_id, tag_id),
+    CONSTRAINT fk_transaction_tag_transaction_id_to_account_transaction FOREIGN KEY (transaction_id) REFERENCES account_transaction(id),
+    CONSTRAINT fk_transaction_tag_tag_id_to_tag FOREIGN KEY (tag_id) REFERENCES tag(id)
+);
@@ -0,0 +1,13 @@
+CREATE TABLE attachment (
+    id TEXT NOT NULL,
+    owner_type TEXT NOT NULL,
+    owner_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    hash TEXT NOT NULL,
+    content BLOB,
+    path TEXT,
+    CONSTRAINT PK_ATTACHMENT PRIMARY KEY (id)
+);
+CREATE INDEX IDX_ATTACHMENT_OWNER ON attachment(owner_type, owner_id);
@@ -0,0 +1,12 @@
+CREATE TABLE audit (
+    id TEXT NOT NULL,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    operation TEXT NOT NULL,
+    before TEXT,
+    after TEXT,
+    timestamp TEXT NOT NULL,
+    CONSTRAINT PK_AUDIT PRIMARY KEY (id)
+);
+CREATE INDEX IDX_AUDIT_ENTITY ON audit(entity_type, entity_id);
+CREATE INDEX IDX_AUDIT_TIMESTAMP ON audit(timestamp);
@@ -0,0 +1,16 @@
+ALTER TABLE account ADD COLUMN deleted_at TEXT;
+ALTER TABLE account ADD COLUMN deletion_id TEXT;
+ALTER TABLE account_transaction ADD COLUMN deleted_at TEXT;
+ALTER TABLE account_transaction ADD COLUMN deletion_id TEXT;
+ALTER TABLE expense ADD COLUMN deleted_at TEXT;
+ALTER TABLE expense ADD COLUMN deletion_id TEXT;
+
+CREATE TABLE trash (
+    id TEXT NOT NULL,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    entity TEXT NOT NULL,
+    settings TEXT NOT NULL,
+    deleted_at TEXT NOT NULL,
+    CONSTRAINT PK_TRASH PRIMARY KEY (id)
+);
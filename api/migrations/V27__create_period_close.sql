@@ -0,0 +1,10 @@
+CREATE TABLE period_close (
+    id TEXT NOT NULL,
+    account_id TEXT,
+    closed_through TEXT NOT NULL,
+    closed_by TEXT NOT NULL,
+    closed_at TEXT NOT NULL,
+    CONSTRAINT PK_PERIOD_CLOSE PRIMARY KEY (id),
+    CONSTRAINT fk_period_close_account_id_to_account FOREIGN KEY (account_id) REFERENCES account(id)
+);
+CREATE INDEX IDX_PERIOD_CLOSE_ACCOUNT ON period_close(account_id);
@@ -0,0 +1,10 @@
+CREATE TABLE loan (
+    account_id TEXT NOT NULL,
+    principal TEXT NOT NULL,
+    interest_rate TEXT NOT NULL,
+    term_months INTEGER NOT NULL,
+    first_payment_date TEXT NOT NULL,
+    payment TEXT,
+    CONSTRAINT PK_LOAN PRIMARY KEY (account_id),
+    CONSTRAINT fk_loan_account_id_to_account FOREIGN KEY (account_id) REFERENCES account(id)
+);
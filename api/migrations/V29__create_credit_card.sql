@@ -0,0 +1,8 @@
+CREATE TABLE credit_card (
+    account_id TEXT NOT NULL,
+    credit_limit TEXT NOT NULL,
+    statement_day INTEGER NOT NULL,
+    due_day INTEGER NOT NULL,
+    CONSTRAINT PK_CREDIT_CARD PRIMARY KEY (account_id),
+    CONSTRAINT fk_credit_card_account_id_to_account FOREIGN KEY (account_id) REFERENCES account(id)
+);
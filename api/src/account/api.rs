@@ -48,6 +48,21 @@ pub async fn delete_account(db: web::Data<Pool>, path: web::Path<String>) -> Res
         })
 }
 
+#[post("/api/account/{id}/merge-into/{target}/")]
+pub async fn merge_account(db: web::Data<Pool>, path: web::Path<(String, String)>, query: web::Query<schema::AccountMergeQuery>) -> Result<HttpResponse, Error> {
+    let (id, target) = path.into_inner();
+    let dry_run = query.dry_run;
+    info!("HTTP merge_account id:[{}] target:[{}] dry_run:[{}]", id, target, dry_run);
+    let result = do_in_transaction(&db, move |transaction| db::merge_account(transaction, id, target, dry_run)).await;
+    crate::attachment::api::remove_unreferenced_files(&db).await;
+    return result
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP merge_account: [{err}]");
+            return error::ErrorInternalServerError(err)
+        });
+}
+
 #[get("/api/account/{id}/")]
 pub async fn get_account(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
     let id = path.clone();
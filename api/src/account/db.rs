@@ -2,43 +2,145 @@ use anyhow::anyhow;
 use const_format::formatcp;
 use rusqlite::Transaction;
 use uuid::Uuid;
-use crate::account::schema::{Account, NewAccount};
-use crate::db::{list, single};
+use crate::account::schema::{Account, AccountMerge, NewAccount};
+use crate::audit::db::{record_create, record_delete, record_purge, record_restore, record_update};
+use crate::audit::schema::AuditEntityType;
+use crate::db::{format_timestamp, list, single};
+use crate::setting::db::list_settings;
+use crate::setting::schema::Setting;
+use crate::trash::db::create_trash_entry;
+use crate::trash::schema::{TrashEntityType, TrashEntry};
 
 const ACCOUNT_COLUMNS: &str = "id, name, type, hide_new_transactions, transfer_without_balance_ignored, no_regular_balance, hidden";
 const ACCOUNT_SELECT: &str = formatcp!("SELECT {ACCOUNT_COLUMNS} FROM account");
 const ACCOUNT_RETURNING: &str = formatcp!("RETURNING {ACCOUNT_COLUMNS}");
 const ACCOUNT_ORDERING: &str = "ORDER BY type, name ASC";
+// Accounts in the trash are left out everywhere else
+const ACCOUNT_LIVE: &str = "deleted_at IS NULL";
 
 pub fn create_account(transaction: &Transaction, new_account: NewAccount) -> anyhow::Result<Option<Account>> {
-    return single(
+    let account: Option<Account> = single(
         transaction,
         formatcp!("INSERT INTO account ({ACCOUNT_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) {ACCOUNT_RETURNING}"),
         [Uuid::new_v4().to_string(), new_account.name.clone(), new_account.account_type.to_string(), (new_account.hide_new_transactions as i32).to_string(), (new_account.transfer_without_balance_ignored as i32).to_string(), (new_account.no_regular_balance as i32).to_string(), (new_account.hidden as i32).to_string()]
-    );
+    )?;
+    if let Some(account) = &account {
+        record_create(transaction, AuditEntityType::Account, &account.id, account)?;
+    }
+    return Ok(account);
 }
 
 pub fn update_account(transaction: &Transaction, updated_account: Account) -> anyhow::Result<Option<Account>> {
-    return single(
+    let previous_account = get_account(transaction, updated_account.id.clone())?;
+    let account: Option<Account> = single(
         transaction,
-        formatcp!("UPDATE account SET name = ?1, type = ?2, hide_new_transactions = ?3, transfer_without_balance_ignored = ?4, no_regular_balance = ?5, hidden = ?6 WHERE id = ?7 {ACCOUNT_RETURNING}"),
+        formatcp!("UPDATE account SET name = ?1, type = ?2, hide_new_transactions = ?3, transfer_without_balance_ignored = ?4, no_regular_balance = ?5, hidden = ?6 WHERE id = ?7 AND {ACCOUNT_LIVE} {ACCOUNT_RETURNING}"),
         [updated_account.name, updated_account.account_type.to_string(), (updated_account.hide_new_transactions as i32).to_string(), (updated_account.transfer_without_balance_ignored as i32).to_string(), (updated_account.no_regular_balance as i32).to_string(), (updated_account.hidden as i32).to_string(), updated_account.id]
-    );
+    )?;
+    if let Some(account) = &account {
+        record_update(transaction, AuditEntityType::Account, &account.id, previous_account.as_ref(), account)?;
+    }
+    return Ok(account);
 }
 
+// Deleted accounts go to the trash along with their transactions, settings referring to them are changed straight away
 pub fn delete_account(transaction: &Transaction, id: String) -> anyhow::Result<Option<Account>> {
+    let Some(previous_account) = get_account(transaction, id.clone())? else {
+        return Ok(None);
+    };
+    let settings: Vec<Setting> = list_settings(transaction)?.into_iter()
+        .filter(|setting| setting.value.contains(id.as_str()))
+        .collect();
+    crate::setting::db::cascade_delete_account(transaction, id.clone())?;
+    let trash_entry = create_trash_entry(transaction, TrashEntityType::Account, &id, &previous_account, settings)?;
+    crate::transaction::db::cascade_trash_account(transaction, id.clone(), &trash_entry)?;
+    let account: Option<Account> = single(
+        transaction,
+        formatcp!("UPDATE account SET deleted_at = ?1, deletion_id = ?2 WHERE id = ?3 {ACCOUNT_RETURNING}"),
+        [format_timestamp(&trash_entry.deleted_at), trash_entry.id, id]
+    )?;
+    if let Some(account) = &account {
+        record_delete(transaction, AuditEntityType::Account, &account.id, account)?;
+    }
+    return Ok(account);
+}
+
+pub fn restore_account(transaction: &Transaction, trash_entry: &TrashEntry) -> anyhow::Result<()> {
+    let accounts: Vec<Account> = list(
+        transaction,
+        formatcp!("UPDATE account SET deleted_at = NULL, deletion_id = NULL WHERE deletion_id = ?1 {ACCOUNT_RETURNING}"),
+        [trash_entry.id.clone()]
+    )?;
+    for account in &accounts {
+        record_restore(transaction, AuditEntityType::Account, &account.id, account)?;
+    }
+    crate::transaction::db::restore_transactions(transaction, trash_entry.id.clone())?;
+    crate::setting::db::restore_settings(transaction, trash_entry.settings.clone())?;
+    return Ok(());
+}
+
+pub fn purge_account(transaction: &Transaction, id: String) -> anyhow::Result<()> {
     cascade_delete(transaction, id.clone())?;
-    return single(
+    let account: Option<Account> = single(
         transaction,
         formatcp!("DELETE FROM account WHERE id = ?1 {ACCOUNT_RETURNING}"),
         [id]
-    );
+    )?;
+    if let Some(account) = &account {
+        record_purge(transaction, AuditEntityType::Account, &account.id, account)?;
+    }
+    return Ok(());
+}
+
+// Conflicting transactions of the merged account are dropped, nothing is written on a dry run
+pub fn merge_account(transaction: &Transaction, id: String, target_account_id: String, dry_run: bool) -> anyhow::Result<Option<AccountMerge>> {
+    if id == target_account_id {
+        return Err(anyhow!("Account {} cannot be merged into itself", id));
+    }
+    if get_account(transaction, id.clone())?.is_none() {
+        return Ok(None);
+    }
+    verify_account_id_exists(transaction, target_account_id.clone())?;
+    let (moved, conflicts) = crate::transaction::db::find_merge_conflicts(transaction, id.clone(), target_account_id.clone())?;
+    let dropped_loan = crate::loan::db::find_merge_conflict(transaction, id.clone(), target_account_id.clone())?;
+    let dropped_credit_card = crate::credit_card::db::find_merge_conflict(transaction, id.clone(), target_account_id.clone())?;
+    if !dry_run {
+        for conflict in &conflicts {
+            if let Some(kept) = &conflict.kept {
+                crate::expense::db::cascade_merge_transaction(transaction, conflict.dropped.id.clone(), kept.id.clone())?;
+                crate::household::db::cascade_merge_transaction(transaction, conflict.dropped.id.clone(), kept.id.clone())?;
+                crate::tag::db::cascade_merge_transaction(transaction, conflict.dropped.id.clone(), kept.id.clone())?;
+                crate::attachment::db::cascade_merge_transaction(transaction, conflict.dropped.id.clone(), kept.id.clone())?;
+            }
+            crate::transaction::db::purge_transaction(transaction, conflict.dropped.id.clone())?;
+        }
+        crate::transaction::db::cascade_merge_account(transaction, id.clone(), target_account_id.clone())?;
+        crate::trash::db::delete_orphaned_entries(transaction)?;
+        crate::expense::db::cascade_merge_account(transaction, id.clone(), target_account_id.clone())?;
+        crate::person::db::cascade_merge_account(transaction, id.clone(), target_account_id.clone())?;
+        crate::attachment::db::cascade_merge_account(transaction, id.clone(), target_account_id.clone())?;
+        crate::account_group::db::cascade_merge_account(transaction, id.clone(), target_account_id.clone())?;
+        crate::setting::db::cascade_merge_account(transaction, id.clone(), target_account_id.clone())?;
+        crate::loan::db::cascade_merge_account(transaction, id.clone(), target_account_id.clone())?;
+        crate::credit_card::db::cascade_merge_account(transaction, id.clone(), target_account_id.clone())?;
+        crate::period_close::db::cascade_merge_account(transaction, id.clone(), target_account_id.clone())?;
+        purge_account(transaction, id.clone())?;
+    }
+    return Ok(Some(AccountMerge {
+        source_account_id: id,
+        target_account_id,
+        dry_run,
+        moved: moved.len(),
+        conflicts,
+        dropped_loan,
+        dropped_credit_card,
+    }));
 }
 
 pub fn get_account(transaction: &Transaction, id: String) -> anyhow::Result<Option<Account>> {
     return single(
         transaction,
-        formatcp!("{ACCOUNT_SELECT} WHERE id = ?1"),
+        formatcp!("{ACCOUNT_SELECT} WHERE id = ?1 AND {ACCOUNT_LIVE}"),
         [id]
     );
 }
@@ -46,7 +148,7 @@ pub fn get_account(transaction: &Transaction, id: String) -> anyhow::Result<Opti
 pub fn list_accounts(transaction: &Transaction) -> anyhow::Result<Vec<Account>> {
     return list(
         transaction,
-        formatcp!("{ACCOUNT_SELECT} {ACCOUNT_ORDERING}"),
+        formatcp!("{ACCOUNT_SELECT} WHERE {ACCOUNT_LIVE} {ACCOUNT_ORDERING}"),
         []
     );
 }
@@ -60,7 +162,15 @@ pub fn verify_account_id_exists(transaction: &Transaction, id: String) -> anyhow
 }
 
 fn cascade_delete(transaction: &Transaction, id: String) -> anyhow::Result<()> {
-    crate::setting::db::cascade_delete_account(transaction, id.clone())?;
+    crate::expense::db::cascade_delete_account(transaction, id.clone())?;
+    crate::household::db::cascade_delete_account(transaction, id.clone())?;
+    crate::person::db::cascade_delete_account(transaction, id.clone())?;
+    crate::tag::db::cascade_delete_account(transaction, id.clone())?;
+    crate::attachment::db::cascade_delete_account(transaction, id.clone())?;
     crate::transaction::db::cascade_delete_account(transaction, id.clone())?;
+    crate::account_group::db::cascade_delete_account(transaction, id.clone())?;
+    crate::period_close::db::cascade_delete_account(transaction, id.clone())?;
+    crate::loan::db::cascade_delete_account(transaction, id.clone())?;
+    crate::credit_card::db::cascade_delete_account(transaction, id.clone())?;
     Ok(())
 }
\ No newline at end of file
@@ -5,7 +5,10 @@ use rusqlite::Row;
 use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
 use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumString};
+use crate::credit_card::schema::CreditCard;
 use crate::db::FromRow;
+use crate::loan::schema::Loan;
+use crate::transaction::schema::Transaction;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Account {
@@ -68,6 +71,53 @@ pub enum AccountType {
     External,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountMergeQuery {
+    // Report the conflicts without changing anything
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountMerge {
+    #[serde(rename = "sourceAccountId")]
+    pub source_account_id: String,
+    #[serde(rename = "targetAccountId")]
+    pub target_account_id: String,
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    // Transactions moved over to the target account, conflicts are not counted
+    pub moved: usize,
+    pub conflicts: Vec<AccountMergeConflict>,
+    // Loan and card details are moved unless the target can't take them
+    #[serde(rename = "droppedLoan")]
+    pub dropped_loan: Option<Loan>,
+    #[serde(rename = "droppedCreditCard")]
+    pub dropped_credit_card: Option<CreditCard>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountMergeConflict {
+    pub kind: AccountMergeConflictKind,
+    // The source account's transaction, removed by the merge
+    pub dropped: Transaction,
+    // The target account's transaction it collides with, missing for transfers between the two accounts
+    pub kept: Option<Transaction>,
+}
+
+#[derive(Debug, Clone, Display, EnumString, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AccountMergeConflictKind {
+    #[serde(rename = "BALANCE" )]
+    #[strum(serialize="BALANCE", to_string="BALANCE")]
+    Balance,
+    #[serde(rename = "TRANSFER" )]
+    #[strum(serialize="TRANSFER", to_string="TRANSFER")]
+    Transfer,
+    #[serde(rename = "SELF_TRANSFER" )]
+    #[strum(serialize="SELF_TRANSFER", to_string="SELF_TRANSFER")]
+    SelfTransfer,
+}
+
 impl FromRow for Account {
     fn from_row(row: &Row) -> rusqlite::Result<Self> {
         Ok(Account {
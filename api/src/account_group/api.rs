@@ -0,0 +1,73 @@
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use log::{error, info};
+use crate::account_group::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+
+#[post("/api/account-group/")]
+pub async fn create_account_group(db: web::Data<Pool>, new_account_group: web::Json<schema::NewAccountGroup>) -> Result<HttpResponse, Error> {
+    let new_account_group = new_account_group.into_inner();
+    info!("HTTP create_account_group new_account_group:[{:?}]", new_account_group);
+    do_in_transaction(&db, |transaction| db::create_account_group(transaction, new_account_group))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_account_group: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+
+#[post("/api/account-group/{id}/")]
+pub async fn update_account_group(db: web::Data<Pool>, path: web::Path<String>, updated_account_group: web::Json<schema::AccountGroup>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let updated_account_group = updated_account_group.into_inner();
+    info!("HTTP update_account_group id:[{}] updated_account_group:[{:?}]", id, updated_account_group);
+    let option = crate::api::compare_ids(&id, &updated_account_group.id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::update_account_group(transaction, updated_account_group))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP update_account_group: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/account-group/{id}/")]
+pub async fn delete_account_group(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_account_group id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_account_group(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_account_group: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/account-group/{id}/")]
+pub async fn get_account_group(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_account_group id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_account_group(transaction, id))
+        .await.map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_account_group: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/account-group/")]
+pub async fn list_account_groups(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_account_groups");
+    do_in_transaction(&db, |transaction| db::list_account_groups(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_account_groups: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
\ No newline at end of file
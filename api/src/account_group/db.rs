@@ -0,0 +1,97 @@
+use anyhow::anyhow;
+use const_format::formatcp;
+use rusqlite::Transaction;
+use uuid::Uuid;
+use crate::account::db::verify_account_id_exists;
+use crate::account_group::schema::{AccountGroup, NewAccountGroup};
+use crate::db::{list, single};
+
+// Members are folded into a single column so a group can be read in one row, accounts in the trash are left out
+const ACCOUNT_GROUP_SELECT: &str = "SELECT account_group.id, account_group.name, GROUP_CONCAT(account_group_member.account_id) AS account_ids FROM account_group LEFT JOIN account_group_member ON account_group_member.account_group_id = account_group.id AND account_group_member.account_id NOT IN (SELECT id FROM account WHERE deleted_at IS NOT NULL)";
+const ACCOUNT_GROUP_GROUPING: &str = "GROUP BY account_group.id, account_group.name";
+const ACCOUNT_GROUP_ORDERING: &str = "ORDER BY account_group.name ASC";
+
+pub fn create_account_group(transaction: &Transaction, new_account_group: NewAccountGroup) -> anyhow::Result<Option<AccountGroup>> {
+    verify_account_ids(transaction, &new_account_group.account_ids)?;
+    let id = Uuid::new_v4().to_string();
+    transaction.execute(
+        "INSERT INTO account_group (id, name) VALUES (?1, ?2)",
+        [id.clone(), new_account_group.name],
+    )?;
+    insert_members(transaction, &id, new_account_group.account_ids)?;
+    return get_account_group(transaction, id);
+}
+
+pub fn update_account_group(transaction: &Transaction, updated_account_group: AccountGroup) -> anyhow::Result<Option<AccountGroup>> {
+    verify_account_ids(transaction, &updated_account_group.account_ids)?;
+    let updated = transaction.execute(
+        "UPDATE account_group SET name = ?1 WHERE id = ?2",
+        [updated_account_group.name, updated_account_group.id.clone()],
+    )?;
+    if updated == 0 {
+        return Ok(None);
+    }
+    transaction.execute("DELETE FROM account_group_member WHERE account_group_id = ?1", [updated_account_group.id.clone()])?;
+    insert_members(transaction, &updated_account_group.id, updated_account_group.account_ids)?;
+    return get_account_group(transaction, updated_account_group.id);
+}
+
+pub fn delete_account_group(transaction: &Transaction, id: String) -> anyhow::Result<Option<AccountGroup>> {
+    let account_group = get_account_group(transaction, id.clone())?;
+    if account_group.is_some() {
+        transaction.execute("DELETE FROM account_group_member WHERE account_group_id = ?1", [id.clone()])?;
+        transaction.execute("DELETE FROM account_group WHERE id = ?1", [id])?;
+    }
+    return Ok(account_group);
+}
+
+pub fn get_account_group(transaction: &Transaction, id: String) -> anyhow::Result<Option<AccountGroup>> {
+    return single(
+        transaction,
+        formatcp!("{ACCOUNT_GROUP_SELECT} WHERE account_group.id = ?1 {ACCOUNT_GROUP_GROUPING}"),
+        [id]
+    );
+}
+
+pub fn list_account_groups(transaction: &Transaction) -> anyhow::Result<Vec<AccountGroup>> {
+    return list(
+        transaction,
+        formatcp!("{ACCOUNT_GROUP_SELECT} {ACCOUNT_GROUP_GROUPING} {ACCOUNT_GROUP_ORDERING}"),
+        []
+    );
+}
+
+pub fn cascade_delete_account(transaction: &Transaction, account_id: String) -> anyhow::Result<()> {
+    transaction.execute("DELETE FROM account_group_member WHERE account_id = ?1", [account_id])?;
+    return Ok(());
+}
+
+// Groups that already have the target account just lose the merged one
+pub fn cascade_merge_account(transaction: &Transaction, account_id: String, target_account_id: String) -> anyhow::Result<()> {
+    transaction.execute(
+        "INSERT OR IGNORE INTO account_group_member (account_group_id, account_id) SELECT account_group_id, ?2 FROM account_group_member WHERE account_id = ?1",
+        [account_id.clone(), target_account_id],
+    )?;
+    transaction.execute("DELETE FROM account_group_member WHERE account_id = ?1", [account_id])?;
+    return Ok(());
+}
+
+fn insert_members(transaction: &Transaction, account_group_id: &str, account_ids: Vec<String>) -> anyhow::Result<()> {
+    for account_id in account_ids {
+        transaction.execute(
+            "INSERT OR IGNORE INTO account_group_member (account_group_id, account_id) VALUES (?1, ?2)",
+            [account_group_id.to_string(), account_id],
+        )?;
+    }
+    return Ok(());
+}
+
+fn verify_account_ids(transaction: &Transaction, account_ids: &[String]) -> anyhow::Result<()> {
+    if account_ids.is_empty() {
+        return Err(anyhow!("Account group must have at least one account"));
+    }
+    for account_id in account_ids {
+        verify_account_id_exists(transaction, account_id.clone())?;
+    }
+    return Ok(());
+}
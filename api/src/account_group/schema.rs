@@ -0,0 +1,33 @@
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use crate::db::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountGroup {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "accountIds")]
+    pub account_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewAccountGroup {
+    pub name: String,
+    #[serde(rename = "accountIds")]
+    pub account_ids: Vec<String>,
+}
+
+impl FromRow for AccountGroup {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let account_ids: Option<String> = row.get("account_ids")?;
+        let mut account_ids: Vec<String> = account_ids
+            .map(|account_ids| account_ids.split(',').map(|account_id| account_id.to_string()).collect())
+            .unwrap_or_default();
+        account_ids.sort();
+        Ok(AccountGroup {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            account_ids,
+        })
+    }
+}
@@ -0,0 +1,89 @@
+use actix_web::{delete, Error, error, get, HttpRequest, HttpResponse, post, web};
+use actix_web::http::header::{self, ContentDisposition};
+use log::{error, info};
+use crate::attachment::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+
+// The file is the raw request body, anything without a content type is stored as binary
+#[post("/api/attachment/")]
+pub async fn create_attachment(db: web::Data<Pool>, request: HttpRequest, new_attachment: web::Query<schema::NewAttachment>, content: web::Bytes) -> Result<HttpResponse, Error> {
+    let new_attachment = new_attachment.into_inner();
+    let content_type = request.headers().get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    info!("HTTP create_attachment new_attachment:[{:?}] content_type:[{}] size:[{}]", new_attachment, content_type, content.len());
+    do_in_transaction(&db, move |transaction| db::create_attachment(transaction, new_attachment, content_type, content.to_vec()))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_attachment: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/attachment/{id}/")]
+pub async fn delete_attachment(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_attachment id:[{}]", id);
+    let result = do_in_transaction(&db, |transaction| db::delete_attachment(transaction, id)).await;
+    remove_unreferenced_files(&db).await;
+    return result
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_attachment: [{err}]");
+            return error::ErrorInternalServerError(err)
+        });
+}
+
+#[get("/api/attachment/{id}/content/")]
+pub async fn download_attachment(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP download_attachment id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_attachment_content(transaction, id))
+        .await
+        .map(|option| match option {
+            None => HttpResponse::NotFound().finish(),
+            Some((attachment, content)) => HttpResponse::Ok()
+                .content_type(attachment.content_type)
+                .insert_header(ContentDisposition::attachment(attachment.name))
+                .body(content)
+        })
+        .map_err(|err| {
+            error!("HTTP download_attachment: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/attachment/{id}/")]
+pub async fn get_attachment(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_attachment id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_attachment(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_attachment: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/attachment/")]
+pub async fn list_attachments(db: web::Data<Pool>, query: web::Query<schema::AttachmentQuery>) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    info!("HTTP list_attachments query:[{:?}]", query);
+    do_in_transaction(&db, |transaction| db::list_attachments(transaction, query))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_attachments: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+// Files are removed separately once the delete that left them unreferenced is committed
+pub async fn remove_unreferenced_files(db: &Pool) {
+    if let Err(err) = do_in_transaction(db, db::remove_unreferenced_files).await {
+        error!("Could not remove unreferenced attachment files: [{err}]");
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use anyhow::anyhow;
+use const_format::formatcp;
+use log::info;
+use rusqlite::{params, Transaction};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::account::db::verify_account_id_exists;
+use crate::attachment::schema::{Attachment, AttachmentContent, AttachmentOwnerType, AttachmentQuery, NewAttachment};
+use crate::db::{list, single};
+use crate::setting::db::get_setting_by_key;
+use crate::setting::schema::SettingKey;
+
+// Uploads larger than this are rejected before they reach the handler
+pub const MAX_ATTACHMENT_SIZE: usize = 10 * 1024 * 1024;
+
+// Files younger than this may belong to an upload that hasn't committed yet, so cleanup leaves them alone
+const UNREFERENCED_FILE_GRACE: Duration = Duration::from_secs(60 * 60);
+
+const ATTACHMENT_COLUMNS: &str = "id, owner_type, owner_id, name, content_type, size, hash";
+const ATTACHMENT_SELECT: &str = formatcp!("SELECT {ATTACHMENT_COLUMNS} FROM attachment");
+const ATTACHMENT_RETURNING: &str = formatcp!("RETURNING {ATTACHMENT_COLUMNS}");
+const ATTACHMENT_ORDERING: &str = "ORDER BY name, id ASC";
+
+pub fn create_attachment(transaction: &Transaction, new_attachment: NewAttachment, content_type: String, content: Vec<u8>) -> anyhow::Result<Option<Attachment>> {
+    verify_owner_exists(transaction, new_attachment.owner_type, new_attachment.owner_id.clone())?;
+    if new_attachment.name.trim().is_empty() {
+        return Err(anyhow!("Attachment name cannot be empty"));
+    }
+    if content.is_empty() {
+        return Err(anyhow!("Attachment cannot be empty"));
+    }
+    if content.len() > MAX_ATTACHMENT_SIZE {
+        return Err(anyhow!("Attachment of {} bytes is larger than the limit of {} bytes", content.len(), MAX_ATTACHMENT_SIZE));
+    }
+    let hash = hash(&content);
+    let size = content.len() as i64;
+    // Files are named after their hash so the same file attached twice is only written once,
+    // one left behind by a rolled back upload is removed by a cleanup once the grace period has passed
+    let (content, path) = match get_setting_by_key(transaction, SettingKey::AttachmentDirectory)? {
+        Some(setting) => {
+            let path = Path::new(&setting.value).join(&hash);
+            if path.exists() {
+                // Restart the grace period so a cleanup can't remove it before this upload commits
+                fs::File::options().write(true).open(&path)?.set_modified(SystemTime::now())?;
+            } else {
+                fs::write(&path, &content)?;
+            }
+            (None, Some(path.to_string_lossy().to_string()))
+        }
+        None => (Some(content), None),
+    };
+    return single(
+        transaction,
+        formatcp!("INSERT INTO attachment ({ATTACHMENT_COLUMNS}, content, path) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9) {ATTACHMENT_RETURNING}"),
+        params![Uuid::new_v4().to_string(), new_attachment.owner_type.to_string(), new_attachment.owner_id, new_attachment.name, content_type, size, hash, content, path],
+    );
+}
+
+// The file stays until remove_unreferenced_files runs after the delete is committed
+pub fn delete_attachment(transaction: &Transaction, id: String) -> anyhow::Result<Option<Attachment>> {
+    return single(
+        transaction,
+        formatcp!("DELETE FROM attachment WHERE id = ?1 {ATTACHMENT_RETURNING}"),
+        [id],
+    );
+}
+
+pub fn get_attachment(transaction: &Transaction, id: String) -> anyhow::Result<Option<Attachment>> {
+    return single(
+        transaction,
+        formatcp!("{ATTACHMENT_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn get_attachment_content(transaction: &Transaction, id: String) -> anyhow::Result<Option<(Attachment, Vec<u8>)>> {
+    let Some(attachment) = get_attachment(transaction, id.clone())? else {
+        return Ok(None);
+    };
+    let stored: AttachmentContent = single(transaction, "SELECT content, path FROM attachment WHERE id = ?1", [id.clone()])?
+        .ok_or(anyhow!("Attachment {} does not exist", id))?;
+    let content = match (stored.content, stored.path) {
+        (Some(content), _) => content,
+        (None, Some(path)) => fs::read(&path).map_err(|err| anyhow!("Could not read attachment file {}: {}", path, err))?,
+        (None, None) => return Err(anyhow!("Attachment {} has no content", id)),
+    };
+    return Ok(Some((attachment, content)));
+}
+
+pub fn list_attachments(transaction: &Transaction, query: AttachmentQuery) -> anyhow::Result<Vec<Attachment>> {
+    return list(
+        transaction,
+        formatcp!("{ATTACHMENT_SELECT} WHERE (?1 IS NULL OR owner_type = ?1) AND (?2 IS NULL OR owner_id = ?2) {ATTACHMENT_ORDERING}"),
+        params![query.owner_type.map(|owner_type| owner_type.to_string()), query.owner_id],
+    );
+}
+
+// Includes the attachments of the account's transactions since those are deleted along with it
+pub fn cascade_delete_account(transaction: &Transaction, account_id: String) -> anyhow::Result<()> {
+    let attachments: Vec<Attachment> = list(
+        transaction,
+        formatcp!("{ATTACHMENT_SELECT} WHERE (owner_type = ?1 AND owner_id = ?2) OR (owner_type = ?3 AND owner_id IN (SELECT id FROM account_transaction WHERE account_id = ?2 OR from_account_id = ?2))"),
+        [AttachmentOwnerType::Account.to_string(), account_id, AttachmentOwnerType::Transaction.to_string()],
+    )?;
+    return delete_attachments(transaction, attachments);
+}
+
+pub fn cascade_merge_account(transaction: &Transaction, account_id: String, target_account_id: String) -> anyhow::Result<()> {
+    transaction.execute(
+        "UPDATE attachment SET owner_id = ?3 WHERE owner_type = ?1 AND owner_id = ?2",
+        [AttachmentOwnerType::Account.to_string(), account_id, target_account_id],
+    )?;
+    return Ok(());
+}
+
+pub fn cascade_merge_transaction(transaction: &Transaction, transaction_id: String, target_transaction_id: String) -> anyhow::Result<()> {
+    transaction.execute(
+        "UPDATE attachment SET owner_id = ?3 WHERE owner_type = ?1 AND owner_id = ?2",
+        [AttachmentOwnerType::Transaction.to_string(), transaction_id, target_transaction_id],
+    )?;
+    return Ok(());
+}
+
+pub fn cascade_delete_transaction(transaction: &Transaction, transaction_id: String) -> anyhow::Result<()> {
+    let attachments = list_attachments(transaction, AttachmentQuery { owner_type: Some(AttachmentOwnerType::Transaction), owner_id: Some(transaction_id) })?;
+    return delete_attachments(transaction, attachments);
+}
+
+pub fn cascade_delete_expense(transaction: &Transaction, expense_id: String) -> anyhow::Result<()> {
+    let attachments = list_attachments(transaction, AttachmentQuery { owner_type: Some(AttachmentOwnerType::Expense), owner_id: Some(expense_id) })?;
+    return delete_attachments(transaction, attachments);
+}
+
+// Only files named like a hash are touched so nothing else kept in the directory is removed
+pub fn remove_unreferenced_files(transaction: &Transaction) -> anyhow::Result<()> {
+    let Some(setting) = get_setting_by_key(transaction, SettingKey::AttachmentDirectory)? else {
+        return Ok(());
+    };
+    let paths: HashSet<String> = transaction.prepare("SELECT path FROM attachment WHERE path IS NOT NULL")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    for entry in fs::read_dir(&setting.value)? {
+        let path = entry?.path();
+        let hashed = path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.len() == 64 && name.chars().all(|character| character.is_ascii_hexdigit()));
+        let recent = fs::metadata(&path)?.modified()?.elapsed().map_or(true, |age| age < UNREFERENCED_FILE_GRACE);
+        if hashed && path.is_file() && !recent && !paths.contains(path.to_string_lossy().as_ref()) {
+            info!("Removing attachment file [{}]", path.display());
+            fs::remove_file(&path)?;
+        }
+    }
+    return Ok(());
+}
+
+fn delete_attachments(transaction: &Transaction, attachments: Vec<Attachment>) -> anyhow::Result<()> {
+    for attachment in attachments {
+        info!("Deleting attachment [{}] [{}] because its {} [{}] was deleted", attachment.id, attachment.name, attachment.owner_type, attachment.owner_id);
+        delete_attachment(transaction, attachment.id)?;
+    }
+    return Ok(());
+}
+
+fn verify_owner_exists(transaction: &Transaction, owner_type: AttachmentOwnerType, owner_id: String) -> anyhow::Result<()> {
+    match owner_type {
+        AttachmentOwnerType::Account => verify_account_id_exists(transaction, owner_id),
+        AttachmentOwnerType::Transaction => match crate::transaction::db::get_transaction(transaction, owner_id.clone())? {
+            Some(_) => Ok(()),
+            None => Err(anyhow!("Transaction {} does not exist", owner_id)),
+        },
+        AttachmentOwnerType::Expense => match crate::expense::db::get_expense(transaction, owner_id.clone())? {
+            Some(_) => Ok(()),
+            None => Err(anyhow!("Expense {} does not exist", owner_id)),
+        },
+    }
+}
+
+fn hash(content: &[u8]) -> String {
+    return Sha256::digest(content).iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash() {
+        assert_eq!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855", hash(b""));
+        assert_eq!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad", hash(b"abc"));
+    }
+}
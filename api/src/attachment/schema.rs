@@ -0,0 +1,96 @@
+#![allow(unreachable_patterns)]
+
+use std::str::FromStr;
+use rusqlite::Row;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use crate::db::FromRow;
+
+// The content itself is only returned by the download endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Attachment {
+    pub id: String,
+    #[serde(rename = "ownerType")]
+    pub owner_type: AttachmentOwnerType,
+    #[serde(rename = "ownerId")]
+    pub owner_id: String,
+    // The original file name
+    pub name: String,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    // In bytes
+    pub size: i64,
+    // Hex encoded SHA-256 of the content
+    pub hash: String,
+}
+
+// Sent as query parameters alongside the raw file in the request body
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewAttachment {
+    #[serde(rename = "ownerType")]
+    pub owner_type: AttachmentOwnerType,
+    #[serde(rename = "ownerId")]
+    pub owner_id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AttachmentQuery {
+    #[serde(rename = "ownerType")]
+    pub owner_type: Option<AttachmentOwnerType>,
+    #[serde(rename = "ownerId")]
+    pub owner_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Display, EnumString, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AttachmentOwnerType {
+    #[serde(rename = "ACCOUNT")]
+    #[strum(serialize = "ACCOUNT", to_string = "ACCOUNT")]
+    Account,
+    #[serde(rename = "TRANSACTION")]
+    #[strum(serialize = "TRANSACTION", to_string = "TRANSACTION")]
+    Transaction,
+    #[serde(rename = "EXPENSE")]
+    #[strum(serialize = "EXPENSE", to_string = "EXPENSE")]
+    Expense,
+}
+
+// Where the content lives, either inline in the database or as a file in the attachment directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentContent {
+    pub content: Option<Vec<u8>>,
+    pub path: Option<String>,
+}
+
+impl FromRow for Attachment {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Attachment {
+            id: row.get("id")?,
+            owner_type: row.get("owner_type")?,
+            owner_id: row.get("owner_id")?,
+            name: row.get("name")?,
+            content_type: row.get("content_type")?,
+            size: row.get("size")?,
+            hash: row.get("hash")?,
+        })
+    }
+}
+
+impl FromRow for AttachmentContent {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(AttachmentContent {
+            content: row.get("content")?,
+            path: row.get("path")?,
+        })
+    }
+}
+
+impl FromSql for AttachmentOwnerType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str() {
+            Ok(value) => AttachmentOwnerType::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
+            Err(err) => Err(err)
+        }
+    }
+}
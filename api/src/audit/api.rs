@@ -0,0 +1,17 @@
+use actix_web::{Error, error, get, HttpResponse, web};
+use log::{error, info};
+use crate::audit::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+
+#[get("/api/audit/")]
+pub async fn list_audit_entries(db: web::Data<Pool>, query: web::Query<schema::AuditQuery>) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    info!("HTTP list_audit_entries query:[{:?}]", query);
+    do_in_transaction(&db, |transaction| db::list_audit_entries(transaction, query))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_audit_entries: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
@@ -0,0 +1,61 @@
+use chrono::Days;
+use const_format::formatcp;
+use rusqlite::{params, Transaction};
+use serde::Serialize;
+use uuid::Uuid;
+use crate::audit::schema::{AuditEntityType, AuditEntry, AuditOperation, AuditQuery};
+use crate::db::{list, timestamp};
+
+const AUDIT_COLUMNS: &str = "id, entity_type, entity_id, operation, before, after, timestamp";
+const AUDIT_SELECT: &str = formatcp!("SELECT {AUDIT_COLUMNS} FROM audit");
+// Latest first, rowid keeps changes made within the same microsecond in the order they happened
+const AUDIT_ORDERING: &str = "ORDER BY timestamp DESC, rowid DESC";
+const DEFAULT_AUDIT_LIMIT: usize = 500;
+
+pub fn record_create<T: Serialize>(transaction: &Transaction, entity_type: AuditEntityType, entity_id: &str, after: &T) -> anyhow::Result<()> {
+    return record(transaction, entity_type, entity_id, AuditOperation::Create, None, Some(serde_json::to_string(after)?));
+}
+
+pub fn record_update<T: Serialize>(transaction: &Transaction, entity_type: AuditEntityType, entity_id: &str, before: Option<&T>, after: &T) -> anyhow::Result<()> {
+    let before = before.map(serde_json::to_string).transpose()?;
+    return record(transaction, entity_type, entity_id, AuditOperation::Update, before, Some(serde_json::to_string(after)?));
+}
+
+pub fn record_delete<T: Serialize>(transaction: &Transaction, entity_type: AuditEntityType, entity_id: &str, before: &T) -> anyhow::Result<()> {
+    return record(transaction, entity_type, entity_id, AuditOperation::Delete, Some(serde_json::to_string(before)?), None);
+}
+
+pub fn record_restore<T: Serialize>(transaction: &Transaction, entity_type: AuditEntityType, entity_id: &str, after: &T) -> anyhow::Result<()> {
+    return record(transaction, entity_type, entity_id, AuditOperation::Restore, None, Some(serde_json::to_string(after)?));
+}
+
+pub fn record_purge<T: Serialize>(transaction: &Transaction, entity_type: AuditEntityType, entity_id: &str, before: &T) -> anyhow::Result<()> {
+    return record(transaction, entity_type, entity_id, AuditOperation::Purge, Some(serde_json::to_string(before)?), None);
+}
+
+pub fn list_audit_entries(transaction: &Transaction, query: AuditQuery) -> anyhow::Result<Vec<AuditEntry>> {
+    // Timestamps are stored as RFC 3339 so a date compares as the start of that day
+    let end = query.end
+        .and_then(|end| end.checked_add_days(Days::new(1)))
+        .map(|end| end.to_string());
+    return list(
+        transaction,
+        formatcp!("{AUDIT_SELECT} WHERE (?1 IS NULL OR entity_type = ?1) AND (?2 IS NULL OR entity_id = ?2) AND (?3 IS NULL OR operation = ?3) AND (?4 IS NULL OR timestamp >= ?4) AND (?5 IS NULL OR timestamp < ?5) {AUDIT_ORDERING} LIMIT ?6"),
+        params![
+            query.entity_type.map(|entity_type| entity_type.to_string()),
+            query.entity_id,
+            query.operation.map(|operation| operation.to_string()),
+            query.start.map(|start| start.to_string()),
+            end,
+            query.limit.unwrap_or(DEFAULT_AUDIT_LIMIT) as i64,
+        ],
+    );
+}
+
+fn record(transaction: &Transaction, entity_type: AuditEntityType, entity_id: &str, operation: AuditOperation, before: Option<String>, after: Option<String>) -> anyhow::Result<()> {
+    transaction.execute(
+        formatcp!("INSERT INTO audit ({AUDIT_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"),
+        params![Uuid::new_v4().to_string(), entity_type.to_string(), entity_id, operation.to_string(), before, after, timestamp()],
+    )?;
+    return Ok(());
+}
@@ -0,0 +1,114 @@
+#![allow(unreachable_patterns)]
+
+use std::str::FromStr;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::Row;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum_macros::{Display, EnumString};
+use crate::db::{FromRow, get_date_time, get_optional_json};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+    pub id: String,
+    #[serde(rename = "entityType")]
+    pub entity_type: AuditEntityType,
+    #[serde(rename = "entityId")]
+    pub entity_id: String,
+    pub operation: AuditOperation,
+    // Missing for creates and restores
+    pub before: Option<Value>,
+    // Missing for deletes and purges
+    pub after: Option<Value>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuditQuery {
+    #[serde(rename = "entityType")]
+    pub entity_type: Option<AuditEntityType>,
+    #[serde(rename = "entityId")]
+    pub entity_id: Option<String>,
+    pub operation: Option<AuditOperation>,
+    // Both inclusive
+    pub start: Option<NaiveDate>,
+    pub end: Option<NaiveDate>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Display, EnumString, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AuditEntityType {
+    #[serde(rename = "ACCOUNT")]
+    #[strum(serialize = "ACCOUNT", to_string = "ACCOUNT")]
+    Account,
+    #[serde(rename = "TRANSACTION")]
+    #[strum(serialize = "TRANSACTION", to_string = "TRANSACTION")]
+    Transaction,
+    #[serde(rename = "EXPENSE")]
+    #[strum(serialize = "EXPENSE", to_string = "EXPENSE")]
+    Expense,
+    #[serde(rename = "SETTING")]
+    #[strum(serialize = "SETTING", to_string = "SETTING")]
+    Setting,
+    #[serde(rename = "PERIOD_CLOSE")]
+    #[strum(serialize = "PERIOD_CLOSE", to_string = "PERIOD_CLOSE")]
+    PeriodClose,
+    // Keyed by the expense the split belongs to
+    #[serde(rename = "EXPENSE_SPLIT")]
+    #[strum(serialize = "EXPENSE_SPLIT", to_string = "EXPENSE_SPLIT")]
+    ExpenseSplit,
+}
+
+#[derive(Debug, Clone, Copy, Display, EnumString, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AuditOperation {
+    #[serde(rename = "CREATE")]
+    #[strum(serialize = "CREATE", to_string = "CREATE")]
+    Create,
+    #[serde(rename = "UPDATE")]
+    #[strum(serialize = "UPDATE", to_string = "UPDATE")]
+    Update,
+    #[serde(rename = "DELETE")]
+    #[strum(serialize = "DELETE", to_string = "DELETE")]
+    Delete,
+    // Taken back out of the trash
+    #[serde(rename = "RESTORE")]
+    #[strum(serialize = "RESTORE", to_string = "RESTORE")]
+    Restore,
+    // Permanently removed from the trash
+    #[serde(rename = "PURGE")]
+    #[strum(serialize = "PURGE", to_string = "PURGE")]
+    Purge,
+}
+
+impl FromRow for AuditEntry {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(AuditEntry {
+            id: row.get("id")?,
+            entity_type: row.get("entity_type")?,
+            entity_id: row.get("entity_id")?,
+            operation: row.get("operation")?,
+            before: get_optional_json(row, "before")?,
+            after: get_optional_json(row, "after")?,
+            timestamp: get_date_time(row, "timestamp")?,
+        })
+    }
+}
+
+impl FromSql for AuditEntityType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str() {
+            Ok(value) => AuditEntityType::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
+            Err(err) => Err(err)
+        }
+    }
+}
+
+impl FromSql for AuditOperation {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str() {
+            Ok(value) => AuditOperation::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
+            Err(err) => Err(err)
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use chrono::{Datelike, Local, Months, NaiveDate};
+use log::{error, info};
+use rust_decimal::Decimal;
+use crate::budget::{db, schema};
+use crate::budget::schema::{Budget, BudgetPeriod, BudgetVariance};
+use crate::db::{do_in_transaction, Pool};
+use crate::expense::api::spending;
+use crate::expense::db::list_expenses;
+use crate::expense::schema::Expense;
+use crate::expense_category::db::{ancestors_by_expense_category_id, list_expense_categories};
+use crate::expense_category::schema::ExpenseCategory;
+
+#[post("/api/budget/")]
+pub async fn create_budget(db: web::Data<Pool>, new_budget: web::Json<schema::NewBudget>) -> Result<HttpResponse, Error> {
+    let new_budget = new_budget.into_inner();
+    info!("HTTP create_budget new_budget:[{:?}]", new_budget);
+    do_in_transaction(&db, |transaction| db::create_budget(transaction, new_budget))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_budget: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+
+#[post("/api/budget/{id}/")]
+pub async fn update_budget(db: web::Data<Pool>, path: web::Path<String>, updated_budget: web::Json<schema::Budget>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let updated_budget = updated_budget.into_inner();
+    info!("HTTP update_budget id:[{}] updated_budget:[{:?}]", id, updated_budget);
+    let option = crate::api::compare_ids(&id, &updated_budget.id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::update_budget(transaction, updated_budget))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP update_budget: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/budget/{id}/")]
+pub async fn delete_budget(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_budget id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_budget(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_budget: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/budget/report/")]
+pub async fn list_budget_variances(db: web::Data<Pool>, query: web::Query<schema::BudgetVarianceQuery>) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    info!("HTTP list_budget_variances [{query:?}]");
+    do_in_transaction(&db, |transaction| {
+        let budgets = db::list_budgets(transaction)?;
+        let expenses = list_expenses(transaction)?;
+        let expense_categories = list_expense_categories(transaction)?;
+        return Ok((budgets, expenses, expense_categories));
+    })
+        .await
+        .map(|(budgets, expenses, expense_categories)| {
+            let end = query.end.unwrap_or(Local::now().date_naive());
+            let budget_variances: Vec<BudgetVariance> = calculate_budget_variances(&budgets, &expenses, &expense_categories, end).into_iter()
+                .filter(|budget_variance| query.start.is_none_or(|start| start <= budget_variance.end))
+                .collect();
+            return HttpResponse::Ok().json(budget_variances);
+        })
+        .map_err(|err| {
+            error!("HTTP list_budget_variances: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/budget/{id}/")]
+pub async fn get_budget(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_budget id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_budget(transaction, id))
+        .await.map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_budget: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/budget/")]
+pub async fn list_budgets(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_budgets");
+    do_in_transaction(&db, |transaction| db::list_budgets(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_budgets: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+// Every period of every budget that starts on or before the end, spending in subcategories counts against their parents
+pub fn calculate_budget_variances(budgets: &[Budget], expenses: &[Expense], expense_categories: &[ExpenseCategory], end: NaiveDate) -> Vec<BudgetVariance> {
+    let expenses = spending(expenses.to_vec(), false);
+    let ancestors_by_category = ancestors_by_expense_category_id(expense_categories);
+    let mut budgets_by_category: BTreeMap<String, Vec<&Budget>> = BTreeMap::new();
+    for budget in budgets {
+        budgets_by_category.entry(budget.category_id.clone()).or_default().push(budget);
+    }
+
+    let mut budget_variances: Vec<BudgetVariance> = vec![];
+    for (category_id, mut budgets) in budgets_by_category {
+        budgets.sort_by_key(|budget| budget.effective_from);
+        // A later budget with the same period takes over from the start of its own first period,
+        // one with a different period can't line up with the periods before it so it takes over on the day itself
+        let takeovers: Vec<NaiveDate> = budgets.iter().enumerate()
+            .map(|(index, budget)| match index.checked_sub(1).map(|previous| budgets[previous]) {
+                Some(previous) if previous.period != budget.period => budget.effective_from,
+                _ => period_start(budget.effective_from, &budget.period),
+            })
+            .collect();
+        for (index, budget) in budgets.iter().enumerate() {
+            let from = takeovers[index];
+            let until = takeovers.get(index + 1).copied();
+            let mut start = period_start(from, &budget.period);
+            let mut carried = Decimal::ZERO;
+            while start <= end && until.is_none_or(|until| start.max(from) < until) {
+                let next_start = next_period_start(start, &budget.period);
+                let covered_start = start.max(from);
+                let covered_until = until.map_or(next_start, |until| until.min(next_start));
+                let period_end = covered_until.pred_opt().unwrap();
+                // Periods cut short by a takeover only get their share of the amount
+                let budgeted = if covered_start == start && covered_until == next_start {
+                    budget.value
+                } else {
+                    let covered_days = Decimal::from((covered_until - covered_start).num_days());
+                    let period_days = Decimal::from((next_start - start).num_days());
+                    (budget.value * covered_days / period_days).round_dp(2)
+                };
+                let actual: Decimal = expenses.iter()
+                    .filter(|expense| covered_start <= expense.date && expense.date <= period_end)
+                    .filter(|expense| ancestors_by_category.get(&expense.category).is_some_and(|ancestors| ancestors.contains(&category_id)))
+                    .map(|expense| expense.value)
+                    .sum();
+                let remaining = budgeted + carried - actual;
+                budget_variances.push(BudgetVariance {
+                    budget_id: budget.id.clone(),
+                    category_id: category_id.clone(),
+                    start: covered_start,
+                    end: period_end,
+                    budgeted,
+                    carried,
+                    actual,
+                    remaining,
+                });
+                carried = if budget.rollover && remaining > Decimal::ZERO { remaining } else { Decimal::ZERO };
+                start = next_start;
+            }
+        }
+    }
+    budget_variances.sort_by(|one, two| one.start.cmp(&two.start).then(one.category_id.cmp(&two.category_id)));
+    return budget_variances;
+}
+
+fn period_start(date: NaiveDate, period: &BudgetPeriod) -> NaiveDate {
+    return match period {
+        BudgetPeriod::Monthly => date.with_day(1).unwrap(),
+        BudgetPeriod::Yearly => date.with_day(1).unwrap().with_month(1).unwrap(),
+    };
+}
+
+fn next_period_start(start: NaiveDate, period: &BudgetPeriod) -> NaiveDate {
+    return match period {
+        BudgetPeriod::Monthly => start.checked_add_months(Months::new(1)).unwrap(),
+        BudgetPeriod::Yearly => start.checked_add_months(Months::new(12)).unwrap(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(id: &str, value: i64, period: BudgetPeriod, rollover: bool, effective_from: NaiveDate) -> Budget {
+        return Budget {
+            id: id.to_string(),
+            category_id: "HOME".to_string(),
+            value: Decimal::new(value, 0),
+            period,
+            rollover,
+            effective_from,
+        };
+    }
+
+    fn expense_category(id: &str, parent_id: Option<&str>) -> ExpenseCategory {
+        return ExpenseCategory {
+            id: id.to_string(),
+            name: id.to_string(),
+            parent_id: parent_id.map(|parent_id| parent_id.to_string()),
+            colour: None,
+            hidden: false,
+        };
+    }
+
+    #[test]
+    fn test_calculate_budget_variances() {
+        let expense_categories = vec![expense_category("HOME", None), expense_category("FURNITURE", Some("HOME"))];
+        let budgets = vec![
+            budget("first", 100, BudgetPeriod::Monthly, true, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()),
+            budget("second", 50, BudgetPeriod::Monthly, false, NaiveDate::from_ymd_opt(2024, 3, 10).unwrap()),
+        ];
+        let expenses = vec![
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(), ..Expense::for_test("home-1-3", "HOME", Decimal::new(60, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 2, 20).unwrap(), ..Expense::for_test("furniture-2-20", "FURNITURE", Decimal::new(130, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(), ..Expense::for_test("furniture-3-5", "FURNITURE", Decimal::new(70, 0)) },
+        ];
+
+        let budget_variances = calculate_budget_variances(&budgets, &expenses, &expense_categories, NaiveDate::from_ymd_opt(2024, 4, 5).unwrap());
+        let summary: Vec<(&str, NaiveDate, NaiveDate, Decimal, Decimal, Decimal)> = budget_variances.iter()
+            .map(|budget_variance| (budget_variance.budget_id.as_str(), budget_variance.start, budget_variance.end, budget_variance.carried, budget_variance.actual, budget_variance.remaining))
+            .collect();
+        assert_eq!(vec![
+            ("first", NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(), Decimal::ZERO, Decimal::new(60, 0), Decimal::new(40, 0)),
+            // Unused amounts roll over into the next month
+            ("first", NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(), Decimal::new(40, 0), Decimal::new(130, 0), Decimal::new(10, 0)),
+            // The replacement takes over for the whole month it starts in and doesn't inherit the rollover
+            ("second", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(), Decimal::ZERO, Decimal::new(70, 0), Decimal::new(-20, 0)),
+            ("second", NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 4, 30).unwrap(), Decimal::ZERO, Decimal::ZERO, Decimal::new(50, 0)),
+        ], summary);
+    }
+
+    #[test]
+    fn test_calculate_budget_variances_with_mixed_periods() {
+        let expense_categories = vec![expense_category("HOME", None)];
+        let expenses = vec![
+            Expense { date: NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(), ..Expense::for_test("home-6-3", "HOME", Decimal::new(40, 0)) },
+        ];
+        let summary = |budgets: &[Budget]| calculate_budget_variances(budgets, &expenses, &expense_categories, NaiveDate::from_ymd_opt(2024, 7, 15).unwrap()).iter()
+            .map(|budget_variance| (budget_variance.budget_id.clone(), budget_variance.start, budget_variance.end, budget_variance.budgeted, budget_variance.actual))
+            .collect::<Vec<(String, NaiveDate, NaiveDate, Decimal, Decimal)>>();
+
+        // A yearly budget replaced by a monthly one in June only gets the share of the year it covered
+        assert_eq!(vec![
+            ("year".to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 5, 31).unwrap(), Decimal::new(49836, 2), Decimal::ZERO),
+            ("month".to_string(), NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(), Decimal::new(100, 0), Decimal::new(40, 0)),
+            ("month".to_string(), NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 7, 31).unwrap(), Decimal::new(100, 0), Decimal::ZERO),
+        ], summary(&[
+            budget("year", 1200, BudgetPeriod::Yearly, false, NaiveDate::from_ymd_opt(2023, 1, 1).unwrap()),
+            budget("month", 100, BudgetPeriod::Monthly, false, NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()),
+        ])[1..]);
+
+        // A monthly budget replaced by a yearly one keeps its months, the yearly one starts on its own date
+        assert_eq!(vec![
+            ("month".to_string(), NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(), Decimal::new(100, 0), Decimal::ZERO),
+            ("month".to_string(), NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 4, 30).unwrap(), Decimal::new(100, 0), Decimal::ZERO),
+            ("month".to_string(), NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 5, 31).unwrap(), Decimal::new(100, 0), Decimal::ZERO),
+            ("year".to_string(), NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(), Decimal::new(70164, 2), Decimal::new(40, 0)),
+        ], summary(&[
+            budget("month", 100, BudgetPeriod::Monthly, false, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()),
+            budget("year", 1200, BudgetPeriod::Yearly, false, NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()),
+        ]));
+    }
+}
@@ -0,0 +1,58 @@
+use const_format::formatcp;
+use rusqlite::Transaction;
+use uuid::Uuid;
+use crate::budget::schema::{Budget, NewBudget};
+use crate::db::{list, single};
+use crate::expense_category::db::verify_expense_category_id_exists;
+
+const BUDGET_COLUMNS: &str = "id, category_id, value, period, rollover, effective_from";
+const BUDGET_SELECT: &str = formatcp!("SELECT {BUDGET_COLUMNS} FROM budget");
+const BUDGET_RETURNING: &str = formatcp!("RETURNING {BUDGET_COLUMNS}");
+const BUDGET_ORDERING: &str = "ORDER BY category_id, effective_from, id ASC";
+
+pub fn create_budget(transaction: &Transaction, new_budget: NewBudget) -> anyhow::Result<Option<Budget>> {
+    verify_expense_category_id_exists(transaction, new_budget.category_id.clone())?;
+    return single(
+        transaction,
+        formatcp!("INSERT INTO budget ({BUDGET_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6) {BUDGET_RETURNING}"),
+        [Uuid::new_v4().to_string(), new_budget.category_id, new_budget.value.round_dp(2).to_string(), new_budget.period.to_string(), (new_budget.rollover as i32).to_string(), new_budget.effective_from.to_string()],
+    );
+}
+
+pub fn update_budget(transaction: &Transaction, updated_budget: Budget) -> anyhow::Result<Option<Budget>> {
+    verify_expense_category_id_exists(transaction, updated_budget.category_id.clone())?;
+    return single(
+        transaction,
+        formatcp!("UPDATE budget SET category_id = ?1, value = ?2, period = ?3, rollover = ?4, effective_from = ?5 WHERE id = ?6 {BUDGET_RETURNING}"),
+        [updated_budget.category_id, updated_budget.value.round_dp(2).to_string(), updated_budget.period.to_string(), (updated_budget.rollover as i32).to_string(), updated_budget.effective_from.to_string(), updated_budget.id],
+    );
+}
+
+pub fn delete_budget(transaction: &Transaction, id: String) -> anyhow::Result<Option<Budget>> {
+    return single(
+        transaction,
+        formatcp!("DELETE FROM budget WHERE id = ?1 {BUDGET_RETURNING}"),
+        [id],
+    );
+}
+
+pub fn get_budget(transaction: &Transaction, id: String) -> anyhow::Result<Option<Budget>> {
+    return single(
+        transaction,
+        formatcp!("{BUDGET_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn list_budgets(transaction: &Transaction) -> anyhow::Result<Vec<Budget>> {
+    return list(
+        transaction,
+        formatcp!("{BUDGET_SELECT} {BUDGET_ORDERING}"),
+        [],
+    );
+}
+
+pub fn cascade_delete_expense_category(transaction: &Transaction, category_id: String) -> anyhow::Result<()> {
+    transaction.execute("DELETE FROM budget WHERE category_id = ?1", [category_id])?;
+    return Ok(());
+}
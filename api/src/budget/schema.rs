@@ -0,0 +1,94 @@
+#![allow(unreachable_patterns)]
+
+use std::str::FromStr;
+use chrono::NaiveDate;
+use rusqlite::Row;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use crate::db::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Budget {
+    pub id: String,
+    #[serde(rename = "categoryId")]
+    pub category_id: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    pub period: BudgetPeriod,
+    // Unused amounts are carried into the next period of the same budget
+    pub rollover: bool,
+    // Replaces any earlier budget for the category from the period containing this date,
+    // or from this date itself when the earlier budget has a different period
+    #[serde(rename = "effectiveFrom")]
+    pub effective_from: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewBudget {
+    #[serde(rename = "categoryId")]
+    pub category_id: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    pub period: BudgetPeriod,
+    pub rollover: bool,
+    #[serde(rename = "effectiveFrom")]
+    pub effective_from: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BudgetVariance {
+    #[serde(rename = "budgetId")]
+    pub budget_id: String,
+    #[serde(rename = "categoryId")]
+    pub category_id: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub budgeted: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub carried: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub actual: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub remaining: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BudgetVarianceQuery {
+    pub start: Option<NaiveDate>,
+    pub end: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Display, EnumString, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+pub enum BudgetPeriod {
+    #[serde(rename = "MONTHLY" )]
+    #[strum(serialize="MONTHLY", to_string="MONTHLY")]
+    Monthly,
+    #[serde(rename = "YEARLY" )]
+    #[strum(serialize="YEARLY", to_string="YEARLY")]
+    Yearly
+}
+
+impl FromRow for Budget {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Budget {
+            id: row.get("id")?,
+            category_id: row.get("category_id")?,
+            value: crate::db::get_decimal(row, "value")?,
+            period: row.get("period")?,
+            rollover: row.get::<_, i32>("rollover")? != 0,
+            effective_from: crate::db::get_naive_date(row, "effective_from")?,
+        })
+    }
+}
+
+impl FromSql for BudgetPeriod {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str() {
+            Ok(value) => BudgetPeriod::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
+            Err(err) => Err(err)
+        }
+    }
+}
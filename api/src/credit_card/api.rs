@@ -0,0 +1,195 @@
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use chrono::{Datelike, Months, NaiveDate};
+use log::{error, info};
+use rust_decimal::Decimal;
+use crate::credit_card::{db, schema};
+use crate::credit_card::schema::CreditCard;
+use crate::db::{do_in_transaction, Pool};
+use crate::issue::schema::{Issue, IssueType};
+use crate::transaction::schema::{Transaction, TransactionType};
+
+// Used when the CREDIT_UTILISATION_THRESHOLD setting is missing
+pub const DEFAULT_CREDIT_UTILISATION_THRESHOLD: Decimal = Decimal::from_parts(30, 0, 0, false, 0);
+
+#[post("/api/account/{id}/credit-card/")]
+pub async fn set_credit_card(db: web::Data<Pool>, path: web::Path<String>, credit_card: web::Json<schema::CreditCard>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let credit_card = credit_card.into_inner();
+    info!("HTTP set_credit_card id:[{}] credit_card:[{:?}]", id, credit_card);
+    let option = crate::api::compare_ids(&id, &credit_card.account_id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::set_credit_card(transaction, credit_card))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP set_credit_card: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/account/{id}/credit-card/")]
+pub async fn delete_credit_card(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_credit_card id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_credit_card(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_credit_card: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/account/{id}/credit-card/")]
+pub async fn get_credit_card(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_credit_card id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_credit_card(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_credit_card: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+// Percentage of the limit in use, card balances are negative while money is owed
+pub fn credit_utilisation(credit_card: &CreditCard, balance: Decimal) -> Decimal {
+    let owed = (-balance).max(Decimal::ZERO);
+    return (owed * Decimal::ONE_HUNDRED / credit_card.credit_limit).round_dp(2);
+}
+
+// Only the latest statement whose due date has passed is checked, what was paid in by the due date has to cover what the statement owed
+pub fn calculate_credit_card_issues(credit_cards: &[CreditCard], transactions: &[Transaction], threshold: Decimal, today: NaiveDate) -> Vec<Issue> {
+    let mut issues: Vec<Issue> = vec![];
+    for credit_card in credit_cards {
+        let balances: Vec<&Transaction> = transactions.iter()
+            .filter(|transaction| transaction.transaction_type == TransactionType::Balance && transaction.account_id == credit_card.account_id && transaction.date <= today)
+            .collect();
+        let (statement_date, due_date) = latest_due_statement(credit_card, today);
+        // A balance from an earlier cycle says nothing about what this statement owes
+        let previous_statement_date = day_in_month(statement_date.with_day(1).unwrap() - Months::new(1), credit_card.statement_day);
+        let statement_balance = balances.iter()
+            .filter(|balance| previous_statement_date < balance.date && balance.date <= statement_date)
+            .max_by_key(|balance| balance.date);
+        let owed = statement_balance.map(|balance| -balance.value).unwrap_or(Decimal::ZERO);
+        let paid: Decimal = transactions.iter()
+            .filter(|transaction| {
+                return transaction.transaction_type == TransactionType::Transfer
+                    && transaction.account_id == credit_card.account_id
+                    && statement_date < transaction.date
+                    && transaction.date <= due_date;
+            })
+            .map(|transaction| transaction.value)
+            .sum();
+        if owed > Decimal::ZERO && paid < owed {
+            issues.push(Issue {
+                issue_type: IssueType::CreditCardPaymentMissing,
+                date: Some(due_date),
+                account_id: Some(credit_card.account_id.clone()),
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
+            });
+        }
+        if let Some(balance) = balances.iter().max_by_key(|balance| balance.date) {
+            if credit_utilisation(credit_card, balance.value) > threshold {
+                issues.push(Issue {
+                    issue_type: IssueType::CreditUtilisationHigh,
+                    date: Some(balance.date),
+                    account_id: Some(credit_card.account_id.clone()),
+                    from_account_id: None,
+                    category_id: None,
+                    subscription_id: None
+                });
+            }
+        }
+    }
+    return issues;
+}
+
+fn latest_due_statement(credit_card: &CreditCard, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let mut month = today.with_day(1).unwrap();
+    loop {
+        let statement_date = day_in_month(month, credit_card.statement_day);
+        let due_date = if credit_card.due_day > credit_card.statement_day {
+            day_in_month(month, credit_card.due_day)
+        } else {
+            day_in_month(month + Months::new(1), credit_card.due_day)
+        };
+        if due_date < today {
+            return (statement_date, due_date);
+        }
+        month = month - Months::new(1);
+    }
+}
+
+fn day_in_month(month: NaiveDate, day: u32) -> NaiveDate {
+    let mut day = day;
+    loop {
+        if let Some(date) = month.with_day(day) {
+            return date;
+        }
+        day -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use super::*;
+
+    fn credit_card(statement_day: u32, due_day: u32) -> CreditCard {
+        return CreditCard {
+            account_id: "card".to_string(),
+            credit_limit: Decimal::new(1000, 0),
+            statement_day,
+            due_day,
+        };
+    }
+
+    #[test]
+    fn test_latest_due_statement() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        assert_eq!((NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 5).unwrap()), latest_due_statement(&credit_card(31, 5), today));
+        assert_eq!((NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(), NaiveDate::from_ymd_opt(2024, 2, 25).unwrap()), latest_due_statement(&credit_card(31, 25), today));
+        assert_eq!((NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 9).unwrap()), latest_due_statement(&credit_card(1, 9), today));
+    }
+
+    #[test]
+    fn test_calculate_credit_card_issues() {
+        let transaction = |transaction_type: TransactionType, day: u32, value: i64| Transaction {
+            id: day.to_string(),
+            description: "".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 2, day).unwrap(),
+            value: Decimal::new(value, 0),
+            from_account_id: if transaction_type == TransactionType::Transfer { Some("checking".to_string()) } else { None },
+            transaction_type,
+            account_id: "card".to_string(),
+        };
+        let today = NaiveDate::from_ymd_opt(2024, 2, 25).unwrap();
+        let issue_types = |transactions: &[Transaction]| calculate_credit_card_issues(&[credit_card(1, 20)], transactions, Decimal::ONE_HUNDRED, today).into_iter()
+            .map(|issue| issue.issue_type)
+            .collect::<Vec<IssueType>>();
+
+        // A token payment doesn't cover the statement, paying it off in parts does
+        let statement = transaction(TransactionType::Balance, 1, -300);
+        assert_eq!(vec![IssueType::CreditCardPaymentMissing], issue_types(&[statement.clone(), transaction(TransactionType::Transfer, 10, 1)]));
+        assert_eq!(Vec::<IssueType>::new(), issue_types(&[statement.clone(), transaction(TransactionType::Transfer, 10, 100), transaction(TransactionType::Transfer, 15, 200)]));
+        assert_eq!(vec![IssueType::CreditCardPaymentMissing], issue_types(&[statement.clone(), transaction(TransactionType::Transfer, 22, 300)]));
+
+        // The previous statement's balance was for the last cycle, nothing recorded since means nothing is known to be owed
+        let previous_statement = Transaction { date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), ..statement.clone() };
+        assert_eq!(Vec::<IssueType>::new(), issue_types(&[previous_statement.clone(), transaction(TransactionType::Transfer, 10, 1)]));
+        let during_cycle = Transaction { date: NaiveDate::from_ymd_opt(2024, 1, 20).unwrap(), ..statement.clone() };
+        assert_eq!(vec![IssueType::CreditCardPaymentMissing], issue_types(&[previous_statement, during_cycle]));
+    }
+
+    #[test]
+    fn test_credit_utilisation() {
+        assert_eq!(Decimal::new(2550, 2), credit_utilisation(&credit_card(1, 20), Decimal::new(-255, 0)));
+        assert_eq!(Decimal::ZERO, credit_utilisation(&credit_card(1, 20), Decimal::new(40, 0)));
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::anyhow;
+use const_format::formatcp;
+use rusqlite::{params, Transaction};
+use rust_decimal::Decimal;
+use crate::account::db::get_account;
+use crate::account::schema::AccountType;
+use crate::credit_card::schema::CreditCard;
+use crate::db::{list, single};
+
+const CREDIT_CARD_COLUMNS: &str = "account_id, credit_limit, statement_day, due_day";
+const CREDIT_CARD_SELECT: &str = formatcp!("SELECT {CREDIT_CARD_COLUMNS} FROM credit_card");
+const CREDIT_CARD_RETURNING: &str = formatcp!("RETURNING {CREDIT_CARD_COLUMNS}");
+
+// Creates or replaces the card details of a credit card account
+pub fn set_credit_card(transaction: &Transaction, credit_card: CreditCard) -> anyhow::Result<Option<CreditCard>> {
+    let Some(account) = get_account(transaction, credit_card.account_id.clone())? else {
+        return Ok(None);
+    };
+    if account.account_type != AccountType::CreditCard {
+        return Err(anyhow!("Account {} is not a credit card", account.id));
+    }
+    verify(&credit_card)?;
+    return single(
+        transaction,
+        formatcp!("INSERT INTO credit_card ({CREDIT_CARD_COLUMNS}) VALUES (?1, ?2, ?3, ?4) ON CONFLICT (account_id) DO UPDATE SET credit_limit = ?2, statement_day = ?3, due_day = ?4 {CREDIT_CARD_RETURNING}"),
+        params![credit_card.account_id, credit_card.credit_limit.to_string(), credit_card.statement_day, credit_card.due_day],
+    );
+}
+
+pub fn delete_credit_card(transaction: &Transaction, account_id: String) -> anyhow::Result<Option<CreditCard>> {
+    return single(
+        transaction,
+        formatcp!("DELETE FROM credit_card WHERE account_id = ?1 {CREDIT_CARD_RETURNING}"),
+        [account_id],
+    );
+}
+
+pub fn get_credit_card(transaction: &Transaction, account_id: String) -> anyhow::Result<Option<CreditCard>> {
+    if get_account(transaction, account_id.clone())?.is_none() {
+        return Ok(None);
+    }
+    return single(
+        transaction,
+        formatcp!("{CREDIT_CARD_SELECT} WHERE account_id = ?1"),
+        [account_id],
+    );
+}
+
+// Cards whose account is in the trash are left out
+pub fn list_credit_cards(transaction: &Transaction) -> anyhow::Result<Vec<CreditCard>> {
+    return list(
+        transaction,
+        formatcp!("{CREDIT_CARD_SELECT} WHERE account_id IN (SELECT id FROM account WHERE deleted_at IS NULL) ORDER BY account_id"),
+        [],
+    );
+}
+
+// The card can't be moved when the target already has its own or is not a credit card account, so it is dropped
+pub fn find_merge_conflict(transaction: &Transaction, account_id: String, target_account_id: String) -> anyhow::Result<Option<CreditCard>> {
+    let credit_card: Option<CreditCard> = single(transaction, formatcp!("{CREDIT_CARD_SELECT} WHERE account_id = ?1"), [account_id])?;
+    let target_account = get_account(transaction, target_account_id.clone())?;
+    let target_credit_card: Option<CreditCard> = single(transaction, formatcp!("{CREDIT_CARD_SELECT} WHERE account_id = ?1"), [target_account_id])?;
+    if target_credit_card.is_none() && target_account.is_some_and(|target_account| target_account.account_type == AccountType::CreditCard) {
+        return Ok(None);
+    }
+    return Ok(credit_card);
+}
+
+pub fn cascade_merge_account(transaction: &Transaction, account_id: String, target_account_id: String) -> anyhow::Result<()> {
+    if find_merge_conflict(transaction, account_id.clone(), target_account_id.clone())?.is_none() {
+        transaction.execute("UPDATE credit_card SET account_id = ?2 WHERE account_id = ?1", [account_id, target_account_id])?;
+    }
+    return Ok(());
+}
+
+pub fn cascade_delete_account(transaction: &Transaction, account_id: String) -> anyhow::Result<()> {
+    transaction.execute("DELETE FROM credit_card WHERE account_id = ?1", [account_id])?;
+    return Ok(());
+}
+
+fn verify(credit_card: &CreditCard) -> anyhow::Result<()> {
+    if credit_card.credit_limit <= Decimal::ZERO {
+        return Err(anyhow!("Credit limit must be positive"));
+    }
+    if !(1..=31).contains(&credit_card.statement_day) {
+        return Err(anyhow!("Statement day must be between 1 and 31"));
+    }
+    if !(1..=31).contains(&credit_card.due_day) {
+        return Err(anyhow!("Due day must be between 1 and 31"));
+    }
+    return Ok(());
+}
@@ -0,0 +1,30 @@
+use rusqlite::Row;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use crate::db::{FromRow, get_decimal};
+
+// Days past the end of a shorter month fall on its last day
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CreditCard {
+    #[serde(rename = "accountId")]
+    pub account_id: String,
+    #[serde(rename = "creditLimit", with = "rust_decimal::serde::float")]
+    pub credit_limit: Decimal,
+    // Day of the month the statement closes
+    #[serde(rename = "statementDay")]
+    pub statement_day: u32,
+    // Day of the month payment is due, in the month after the statement when it is not after the statement day
+    #[serde(rename = "dueDay")]
+    pub due_day: u32,
+}
+
+impl FromRow for CreditCard {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(CreditCard {
+            account_id: row.get("account_id")?,
+            credit_limit: get_decimal(row, "credit_limit")?,
+            statement_day: row.get("statement_day")?,
+            due_day: row.get("due_day")?,
+        })
+    }
+}
@@ -2,11 +2,12 @@ use std::fmt::Debug;
 use std::str::FromStr;
 use actix_web::{web};
 use anyhow::{anyhow};
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
 use rusqlite::{Params, Row, Transaction};
 use log::{*};
 use rusqlite::types::{Type};
 use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
 
 pub type Pool = r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>;
 
@@ -55,12 +56,43 @@ pub fn get_decimal(row: &Row, value: &str) -> rusqlite::Result<Decimal> {
         .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Real, Box::from(err)));
 }
 
+pub fn get_optional_decimal(row: &Row, value: &str) -> rusqlite::Result<Option<Decimal>> {
+    let string: Option<String> = row.get(value)?;
+    if string.is_none() {
+        return Ok(None);
+    }
+    return get_decimal(row, value).map(Some);
+}
+
 pub fn get_naive_date(row: &Row, value: &str) -> rusqlite::Result<NaiveDate> {
     let string: String = row.get(value)?;
     return NaiveDate::from_str(string.as_str())
         .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Real, Box::from(err)))
 }
 
+pub fn get_date_time(row: &Row, value: &str) -> rusqlite::Result<DateTime<Utc>> {
+    let string: String = row.get(value)?;
+    return DateTime::parse_from_rfc3339(&string)
+        .map(|date_time| date_time.with_timezone(&Utc))
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::from(err)));
+}
+
+pub fn get_optional_json<T: DeserializeOwned>(row: &Row, value: &str) -> rusqlite::Result<Option<T>> {
+    let string: Option<String> = row.get(value)?;
+    return string.map(|string| serde_json::from_str(&string))
+        .transpose()
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::from(err)));
+}
+
+pub fn timestamp() -> String {
+    return format_timestamp(&Utc::now());
+}
+
+// Stored timestamps have a fixed width so they sort correctly as text
+pub fn format_timestamp(date_time: &DateTime<Utc>) -> String {
+    return date_time.to_rfc3339_opts(SecondsFormat::Micros, true);
+}
+
 pub trait FromRow: Sized + Send {
 
     fn from_row(row: &Row) -> rusqlite::Result<Self>;
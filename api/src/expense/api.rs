@@ -1,9 +1,18 @@
+use std::collections::HashMap;
 use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use anyhow::anyhow;
+use chrono::NaiveDate;
 use log::{error, info};
-use strum::IntoEnumIterator;
+use rust_decimal::Decimal;
+use crate::account::db::list_accounts;
+use crate::account::schema::{Account, AccountType};
 use crate::db::{do_in_transaction, Pool};
 use crate::expense::db;
-use crate::expense::schema::{Expense, ExpenseCategory, NewExpense};
+use crate::expense::schema::{AccountExpenseReport, AccountExpenseReportQuery, Expense, ExpenseKind, ExpenseSearchQuery, NewExpense, OutstandingReimbursement};
+use crate::expense_category::schema::UNKNOWN_EXPENSE_CATEGORY_ID;
+use crate::sankey::api::calculate_flow_by_account;
+use crate::transaction::db::list_transactions;
+use crate::transaction::schema::Transaction;
 
 #[post("/api/expense/")]
 pub async fn create_expense(db: web::Data<Pool>, new_expense: web::Json<NewExpense>) -> Result<HttpResponse, Error> {
@@ -49,6 +58,40 @@ pub async fn delete_expense(db: web::Data<Pool>, path: web::Path<String>) -> Res
         })
 }
 
+#[get("/api/expense/account-report/")]
+pub async fn list_account_expense_reports(db: web::Data<Pool>, query: web::Query<AccountExpenseReportQuery>) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    info!("HTTP list_account_expense_reports [{query:?}]");
+    do_in_transaction(&db, move |transaction| {
+        if query.end < query.start {
+            return Err(anyhow!("End {} cannot be before start {}", query.end, query.start));
+        }
+        let transactions = list_transactions(transaction)?;
+        let accounts = list_accounts(transaction)?;
+        let expenses = db::list_expenses(transaction)?;
+        return Ok((query, transactions, accounts, expenses));
+    })
+        .await
+        .map(|(query, transactions, accounts, expenses)| calculate_account_expense_reports(&transactions, &accounts, &expenses, query.start, query.end))
+        .map(|account_expense_reports| HttpResponse::Ok().json(account_expense_reports))
+        .map_err(|err| {
+            error!("HTTP list_account_expense_reports: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/expense/reimbursement/")]
+pub async fn list_outstanding_reimbursements(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_outstanding_reimbursements");
+    do_in_transaction(&db, |transaction| db::list_expenses(transaction))
+        .await
+        .map(|expenses| HttpResponse::Ok().json(calculate_outstanding_reimbursements(&expenses)))
+        .map_err(|err| {
+            error!("HTTP list_outstanding_reimbursements: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
 #[get("/api/expense/{id}/")]
 pub async fn get_expense(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
     let id = path.clone();
@@ -63,9 +106,10 @@ pub async fn get_expense(db: web::Data<Pool>, path: web::Path<String>) -> Result
 }
 
 #[get("/api/expense/")]
-pub async fn list_expenses(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
-    info!("HTTP list_expenses");
-    do_in_transaction(&db, |transaction| db::list_expenses(transaction))
+pub async fn list_expenses(db: web::Data<Pool>, query: web::Query<ExpenseSearchQuery>) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    info!("HTTP list_expenses query:[{query:?}]");
+    do_in_transaction(&db, |transaction| db::search_expenses(transaction, query))
         .await
         .map(|value| HttpResponse::Ok().json(value))
         .map_err(|err| {
@@ -74,9 +118,114 @@ pub async fn list_expenses(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
         })
 }
 
-#[get("/api/expense-category/")]
-pub async fn list_expense_categories() -> Result<HttpResponse, Error> {
-    info!("HTTP list_expense_categories");
-    let categories: Vec<ExpenseCategory> = ExpenseCategory::iter().collect();
-    Ok(HttpResponse::Ok().json(categories))
+// Net spending takes refunds and reimbursements off the original expense's category, gross spending ignores them
+pub fn spending(expenses: Vec<Expense>, gross: bool) -> Vec<Expense> {
+    let category_by_id: HashMap<String, String> = expenses.iter()
+        .map(|expense| (expense.id.clone(), expense.category.clone()))
+        .collect();
+    return expenses.into_iter()
+        .filter_map(|expense| match expense.kind {
+            ExpenseKind::Purchase | ExpenseKind::Reimbursable => Some(expense),
+            ExpenseKind::Refund | ExpenseKind::Reimbursement => {
+                if gross {
+                    return None;
+                }
+                let category = expense.original_expense_id.as_ref()
+                    .and_then(|original_expense_id| category_by_id.get(original_expense_id))
+                    .cloned()
+                    .unwrap_or(expense.category.clone());
+                Some(Expense { value: -expense.value, category, ..expense })
+            }
+        })
+        .collect();
+}
+
+fn calculate_outstanding_reimbursements(expenses: &[Expense]) -> Vec<OutstandingReimbursement> {
+    let mut reimbursed_by_expense_id: HashMap<&str, Decimal> = HashMap::new();
+    for expense in expenses {
+        if let (ExpenseKind::Reimbursement, Some(original_expense_id)) = (expense.kind, &expense.original_expense_id) {
+            *reimbursed_by_expense_id.entry(original_expense_id.as_str()).or_insert(Decimal::ZERO) += expense.value;
+        }
+    }
+    return expenses.iter()
+        .filter(|expense| expense.kind == ExpenseKind::Reimbursable)
+        .map(|expense| {
+            let reimbursed = reimbursed_by_expense_id.get(expense.id.as_str()).cloned().unwrap_or(Decimal::ZERO);
+            return OutstandingReimbursement {
+                expense_id: expense.id.clone(),
+                date: expense.date,
+                category: expense.category.clone(),
+                value: expense.value,
+                reimbursed,
+                outstanding: expense.value - reimbursed,
+            };
+        })
+        .filter(|outstanding_reimbursement| outstanding_reimbursement.outstanding > Decimal::ZERO)
+        .collect();
+}
+
+// Expenses dated within (start, end] are compared with the outflow over the same period
+fn calculate_account_expense_reports(transactions: &[Transaction], accounts: &[Account], expenses: &[Expense], start: NaiveDate, end: NaiveDate) -> Vec<AccountExpenseReport> {
+    let flow_by_account_id = calculate_flow_by_account(transactions, start, end);
+    let mut linked_by_account_id: HashMap<String, (Decimal, Decimal)> = HashMap::new();
+    for expense in expenses {
+        if expense.date <= start || end < expense.date {
+            continue;
+        }
+        if let Some(account_id) = &expense.account_id {
+            let (categorised, uncategorised) = linked_by_account_id.entry(account_id.clone()).or_insert((Decimal::ZERO, Decimal::ZERO));
+            if expense.category == UNKNOWN_EXPENSE_CATEGORY_ID {
+                *uncategorised += expense.value;
+            } else {
+                *categorised += expense.value;
+            }
+        }
+    }
+
+    return accounts.iter()
+        .filter(|account| account.account_type != AccountType::External)
+        .filter_map(|account| {
+            let flow = flow_by_account_id.get(&account.id).cloned().unwrap_or(Decimal::ZERO);
+            let outflow = if flow < Decimal::ZERO { flow.abs() } else { Decimal::ZERO };
+            let (categorised, uncategorised) = linked_by_account_id.get(&account.id).cloned().unwrap_or((Decimal::ZERO, Decimal::ZERO));
+            if outflow == Decimal::ZERO && categorised == Decimal::ZERO && uncategorised == Decimal::ZERO {
+                return None;
+            }
+            return Some(AccountExpenseReport {
+                account_id: account.id.clone(),
+                outflow,
+                categorised,
+                uncategorised,
+                unitemised: outflow - categorised - uncategorised,
+            });
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spending() {
+        let expenses = vec![
+            Expense { kind: ExpenseKind::Purchase, ..Expense::for_test("shoes", "CLOTHING", Decimal::new(80, 0)) },
+            Expense { kind: ExpenseKind::Refund, original_expense_id: Some("shoes".to_string()), ..Expense::for_test("refund", "OTHER", Decimal::new(30, 0)) },
+            Expense { kind: ExpenseKind::Reimbursable, ..Expense::for_test("laptop", "WORK", Decimal::new(1000, 0)) },
+            Expense { kind: ExpenseKind::Reimbursement, original_expense_id: Some("laptop".to_string()), ..Expense::for_test("expenses", "OTHER", Decimal::new(1000, 0)) },
+        ];
+
+        let values = |expenses: Vec<Expense>| expenses.into_iter().map(|expense| (expense.category, expense.value)).collect::<Vec<_>>();
+        assert_eq!(vec![
+            ("CLOTHING".to_string(), Decimal::new(80, 0)),
+            ("CLOTHING".to_string(), Decimal::new(-30, 0)),
+            ("WORK".to_string(), Decimal::new(1000, 0)),
+            ("WORK".to_string(), Decimal::new(-1000, 0)),
+        ], values(spending(expenses.clone(), false)));
+        assert_eq!(vec![
+            ("CLOTHING".to_string(), Decimal::new(80, 0)),
+            ("WORK".to_string(), Decimal::new(1000, 0)),
+        ], values(spending(expenses.clone(), true)));
+        assert_eq!(0, calculate_outstanding_reimbursements(&expenses).len());
+    }
 }
@@ -1,43 +1,158 @@
+use anyhow::anyhow;
+use chrono::NaiveDate;
 use const_format::formatcp;
-use rusqlite::{Transaction};
+use rusqlite::{params, params_from_iter, Params, Transaction};
 use rust_decimal::Decimal;
 use uuid::Uuid;
-use crate::db::{list, single};
-use crate::expense::schema::{NewExpense, Expense};
+use crate::account::db::verify_account_id_exists;
+use crate::audit::db::{record_create, record_delete, record_purge, record_restore, record_update};
+use crate::audit::schema::AuditEntityType;
+use crate::db::{format_timestamp, get_decimal, list, single};
+use crate::expense::schema::{NewExpense, Expense, ExpenseKind, ExpenseSearch, ExpenseSearchQuery, ExpenseSort, ExpenseSummary, SortDirection};
+use crate::expense_category::db::{ancestors_by_expense_category_id, list_expense_categories, verify_expense_category_id_exists};
+use crate::expense_classifier::db::learn_expense;
+use crate::expense_rule::db::categorise_new_expense;
+use crate::merchant::db::{resolve_merchant_id, verify_merchant_id_exists};
+use crate::project::db::verify_project_id_exists;
+use crate::transaction::db::get_transaction;
+use crate::transaction::schema::TransactionType;
+use crate::trash::db::create_trash_entry;
+use crate::trash::schema::TrashEntityType;
 
-const EXPENSE_COLUMNS: &str = "id, description, external, category, date, value";
+const EXPENSE_COLUMNS: &str = "id, description, external, category, date, value, account_id, transaction_id, merchant_id, kind, original_expense_id, project_id";
 const EXPENSE_SELECT: &str = formatcp!("SELECT {EXPENSE_COLUMNS} FROM expense");
 const EXPENSE_RETURNING: &str = formatcp!("RETURNING {EXPENSE_COLUMNS}");
 const EXPENSE_ORDERING: &str = "ORDER BY date, category, description, id ASC";
+// Expenses in the trash are left out everywhere else
+const EXPENSE_LIVE: &str = "deleted_at IS NULL";
 
 pub fn create_expense(transaction: &Transaction, new_expense: NewExpense) -> anyhow::Result<Option<Expense>> {
-    return single(
+    let new_expense = categorise_new_expense(transaction, new_expense)?;
+    if new_expense.merchant_id.is_some() {
+        return insert_expense(transaction, new_expense);
+    }
+    let merchant_id = resolve_merchant_id(transaction, &new_expense.external)?;
+    return insert_expense(transaction, NewExpense { merchant_id, ..new_expense });
+}
+
+// Saves the expense as given, the rules and merchants have to have been applied already
+pub fn insert_expense(transaction: &Transaction, new_expense: NewExpense) -> anyhow::Result<Option<Expense>> {
+    verify_open(transaction, new_expense.date, new_expense.account_id.clone())?;
+    verify_expense_category_id_exists(transaction, new_expense.category.clone())?;
+    verify_links(transaction, new_expense.account_id.clone(), new_expense.transaction_id.clone())?;
+    if let Some(merchant_id) = new_expense.merchant_id.clone() {
+        verify_merchant_id_exists(transaction, merchant_id)?;
+    }
+    verify_original(transaction, None, new_expense.kind, new_expense.original_expense_id.clone())?;
+    if let Some(project_id) = new_expense.project_id.clone() {
+        verify_project_id_exists(transaction, project_id)?;
+    }
+    let expense: Option<Expense> = single(
         transaction,
-        formatcp!("INSERT INTO expense ({EXPENSE_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6) {EXPENSE_RETURNING}"),
-        [Uuid::new_v4().to_string(), new_expense.description, new_expense.external, new_expense.category.to_string(), new_expense.date.to_string(), normalize_decimal(&new_expense.value).to_string()],
-    );
+        formatcp!("INSERT INTO expense ({EXPENSE_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12) {EXPENSE_RETURNING}"),
+        params![Uuid::new_v4().to_string(), new_expense.description, new_expense.external, new_expense.category, new_expense.date.to_string(), normalize_decimal(&new_expense.value).to_string(), new_expense.account_id, new_expense.transaction_id, new_expense.merchant_id, new_expense.kind.to_string(), new_expense.original_expense_id, new_expense.project_id],
+    )?;
+    if let Some(expense) = &expense {
+        learn_expense(transaction, expense, 1)?;
+        record_create(transaction, AuditEntityType::Expense, &expense.id, expense)?;
+    }
+    return Ok(expense);
 }
 
 pub fn update_expense(transaction: &Transaction, updated_expense: Expense) -> anyhow::Result<Option<Expense>> {
-    return single(
+    verify_expense_category_id_exists(transaction, updated_expense.category.clone())?;
+    verify_links(transaction, updated_expense.account_id.clone(), updated_expense.transaction_id.clone())?;
+    let merchant_id = merchant(transaction, updated_expense.merchant_id.clone(), &updated_expense.external)?;
+    verify_original(transaction, Some(updated_expense.id.clone()), updated_expense.kind, updated_expense.original_expense_id.clone())?;
+    if let Some(project_id) = updated_expense.project_id.clone() {
+        verify_project_id_exists(transaction, project_id)?;
+    }
+    let previous_expense = get_expense(transaction, updated_expense.id.clone())?;
+    if let Some(previous_expense) = &previous_expense {
+        verify_open(transaction, previous_expense.date, previous_expense.account_id.clone())?;
+        if previous_expense.value != updated_expense.value {
+            crate::household::db::verify_split_value(transaction, updated_expense.id.clone(), updated_expense.value)?;
+        }
+    }
+    verify_open(transaction, updated_expense.date, updated_expense.account_id.clone())?;
+    let expense: Option<Expense> = single(
         transaction,
-        formatcp!("UPDATE expense SET description = ?1, external = ?2, category = ?3, date = ?4, value = ?5 WHERE id = ?6 {EXPENSE_RETURNING}"),
-        [updated_expense.description, updated_expense.external, updated_expense.category.to_string(), updated_expense.date.to_string(), normalize_decimal(&updated_expense.value).to_string(), updated_expense.id],
-    );
+        formatcp!("UPDATE expense SET description = ?1, external = ?2, category = ?3, date = ?4, value = ?5, account_id = ?6, transaction_id = ?7, merchant_id = ?8, kind = ?9, original_expense_id = ?10, project_id = ?11 WHERE id = ?12 AND {EXPENSE_LIVE} {EXPENSE_RETURNING}"),
+        params![updated_expense.description, updated_expense.external, updated_expense.category, updated_expense.date.to_string(), normalize_decimal(&updated_expense.value).to_string(), updated_expense.account_id, updated_expense.transaction_id, merchant_id, updated_expense.kind.to_string(), updated_expense.original_expense_id, updated_expense.project_id, updated_expense.id],
+    )?;
+    if let (Some(previous_expense), Some(expense)) = (&previous_expense, &expense) {
+        learn_expense(transaction, previous_expense, -1)?;
+        learn_expense(transaction, expense, 1)?;
+    }
+    if let Some(expense) = &expense {
+        record_update(transaction, AuditEntityType::Expense, &expense.id, previous_expense.as_ref(), expense)?;
+    }
+    return Ok(expense);
 }
 
 pub fn delete_expense(transaction: &Transaction, id: String) -> anyhow::Result<Option<Expense>> {
-    return single(
+    let linked: i64 = transaction.query_row(formatcp!("SELECT COUNT(*) FROM expense WHERE original_expense_id = ?1 AND {EXPENSE_LIVE}"), [id.clone()], |row| row.get(0))?;
+    if linked != 0 {
+        return Err(anyhow!("Expense {} has {} refunds or reimbursements", id, linked));
+    }
+    let Some(previous_expense) = get_expense(transaction, id.clone())? else {
+        return Ok(None);
+    };
+    verify_open(transaction, previous_expense.date, previous_expense.account_id.clone())?;
+    let trash_entry = create_trash_entry(transaction, TrashEntityType::Expense, &id, &previous_expense, vec![])?;
+    let expense: Option<Expense> = single(
+        transaction,
+        formatcp!("UPDATE expense SET deleted_at = ?1, deletion_id = ?2 WHERE id = ?3 {EXPENSE_RETURNING}"),
+        [format_timestamp(&trash_entry.deleted_at), trash_entry.id, id],
+    )?;
+    if let Some(expense) = &expense {
+        learn_expense(transaction, expense, -1)?;
+        record_delete(transaction, AuditEntityType::Expense, &expense.id, expense)?;
+    }
+    return Ok(expense);
+}
+
+// Categories can be deleted while their expenses are in the trash so the category is checked again
+pub fn restore_expense(transaction: &Transaction, deletion_id: String) -> anyhow::Result<()> {
+    let expenses: Vec<Expense> = list(
+        transaction,
+        formatcp!("{EXPENSE_SELECT} WHERE deletion_id = ?1"),
+        [deletion_id],
+    )?;
+    for expense in &expenses {
+        verify_expense_category_id_exists(transaction, expense.category.clone())?;
+        verify_open(transaction, expense.date, expense.account_id.clone())?;
+        transaction.execute("UPDATE expense SET deleted_at = NULL, deletion_id = NULL WHERE id = ?1", [expense.id.clone()])?;
+        learn_expense(transaction, expense, 1)?;
+        record_restore(transaction, AuditEntityType::Expense, &expense.id, expense)?;
+    }
+    return Ok(());
+}
+
+// Refunds and reimbursements in the trash still count since they would be left pointing at nothing
+pub fn purge_expense(transaction: &Transaction, id: String) -> anyhow::Result<()> {
+    let linked: i64 = transaction.query_row("SELECT COUNT(*) FROM expense WHERE original_expense_id = ?1", [id.clone()], |row| row.get(0))?;
+    if linked != 0 {
+        return Err(anyhow!("Expense {} has {} refunds or reimbursements", id, linked));
+    }
+    crate::household::db::cascade_delete_expense(transaction, id.clone())?;
+    crate::tag::db::cascade_delete_expense(transaction, id.clone())?;
+    crate::attachment::db::cascade_delete_expense(transaction, id.clone())?;
+    let expense: Option<Expense> = single(
         transaction,
         formatcp!("DELETE FROM expense WHERE id = ?1 {EXPENSE_RETURNING}"),
         [id],
-    );
+    )?;
+    if let Some(expense) = &expense {
+        record_purge(transaction, AuditEntityType::Expense, &expense.id, expense)?;
+    }
+    return Ok(());
 }
 
 pub fn get_expense(transaction: &Transaction, id: String) -> anyhow::Result<Option<Expense>> {
     return single(
         transaction,
-        formatcp!("{EXPENSE_SELECT} WHERE id = ?1"),
+        formatcp!("{EXPENSE_SELECT} WHERE id = ?1 AND {EXPENSE_LIVE}"),
         [id],
     );
 }
@@ -45,11 +160,243 @@ pub fn get_expense(transaction: &Transaction, id: String) -> anyhow::Result<Opti
 pub fn list_expenses(transaction: &Transaction) -> anyhow::Result<Vec<Expense>> {
     return list(
         transaction,
-        formatcp!("{EXPENSE_SELECT} {EXPENSE_ORDERING}"),
+        formatcp!("{EXPENSE_SELECT} WHERE {EXPENSE_LIVE} {EXPENSE_ORDERING}"),
         [],
     );
 }
 
+pub fn search_expenses(transaction: &Transaction, query: ExpenseSearchQuery) -> anyhow::Result<ExpenseSearch> {
+    let mut conditions: Vec<String> = vec![EXPENSE_LIVE.to_string()];
+    let mut values: Vec<String> = vec![];
+    if let Some(start) = query.start {
+        values.push(start.to_string());
+        conditions.push(format!("date >= ?{}", values.len()));
+    }
+    if let Some(end) = query.end {
+        values.push(end.to_string());
+        conditions.push(format!("date <= ?{}", values.len()));
+    }
+    if let Some(categories) = query.categories {
+        let requested: Vec<String> = categories.split(',').map(|category| category.trim().to_string()).collect();
+        let ancestors_by_category = ancestors_by_expense_category_id(&list_expense_categories(transaction)?);
+        let mut placeholders: Vec<String> = vec![];
+        for (category, ancestors) in ancestors_by_category {
+            if ancestors.iter().any(|ancestor| requested.contains(ancestor)) {
+                values.push(category);
+                placeholders.push(format!("?{}", values.len()));
+            }
+        }
+        conditions.push(if placeholders.is_empty() { "0".to_string() } else { format!("category IN ({})", placeholders.join(", ")) });
+    }
+    if let Some(external) = query.external {
+        values.push(like_pattern(&external));
+        conditions.push(format!("LOWER(external) LIKE ?{} ESCAPE '\\'", values.len()));
+    }
+    if let Some(merchants) = query.merchants {
+        let mut placeholders: Vec<String> = vec![];
+        for merchant_id in merchants.split(',') {
+            values.push(merchant_id.trim().to_string());
+            placeholders.push(format!("?{}", values.len()));
+        }
+        conditions.push(format!("merchant_id IN ({})", placeholders.join(", ")));
+    }
+    if let Some(project_id) = query.project_id {
+        values.push(project_id);
+        conditions.push(format!("project_id = ?{}", values.len()));
+    }
+    if let Some(tags) = query.tags {
+        let mut placeholders: Vec<String> = vec![];
+        for tag_id in tags.split(',') {
+            values.push(tag_id.trim().to_string());
+            placeholders.push(format!("?{}", values.len()));
+        }
+        conditions.push(format!("id IN (SELECT expense_id FROM expense_tag WHERE tag_id IN ({}))", placeholders.join(", ")));
+    }
+    if let Some(search) = query.search {
+        values.push(like_pattern(&search));
+        conditions.push(format!("LOWER(description) LIKE ?{} ESCAPE '\\'", values.len()));
+    }
+    if let Some(min_value) = query.min_value {
+        values.push(min_value.to_string());
+        conditions.push(format!("CAST(value AS REAL) >= CAST(?{} AS REAL)", values.len()));
+    }
+    if let Some(max_value) = query.max_value {
+        values.push(max_value.to_string());
+        conditions.push(format!("CAST(value AS REAL) <= CAST(?{} AS REAL)", values.len()));
+    }
+    let filter = format!("WHERE {}", conditions.join(" AND "));
+    let direction = match query.direction.unwrap_or(SortDirection::Ascending) {
+        SortDirection::Ascending => "ASC",
+        SortDirection::Descending => "DESC",
+    };
+    let ordering = match query.sort {
+        None => EXPENSE_ORDERING.to_string(),
+        Some(sort) => {
+            let column = match sort {
+                ExpenseSort::Date => "date",
+                ExpenseSort::Value => "CAST(value AS REAL)",
+                ExpenseSort::Description => "description",
+                ExpenseSort::Category => "category",
+            };
+            format!("ORDER BY {column} {direction}, date {direction}, id ASC")
+        }
+    };
+
+    // Summed here rather than in SQL so the totals stay exact
+    let matching_values: Vec<Decimal> = transaction
+        .prepare(&format!("SELECT value FROM expense {filter}"))?
+        .query_map(params_from_iter(values.iter()), |row| get_decimal(row, "value"))?
+        .collect::<Result<Vec<Decimal>, _>>()?;
+    let count = matching_values.len();
+    let sum: Decimal = matching_values.iter().sum();
+    let average = if count == 0 { Decimal::ZERO } else { (sum / Decimal::from(count)).round_dp(2) };
+    let page = query.page.unwrap_or(0);
+    let page_size = query.page_size.unwrap_or(0);
+    let limit = if page_size == 0 {
+        String::new()
+    } else {
+        let offset = page.checked_mul(page_size)
+            .filter(|offset| *offset <= i64::MAX as usize && page_size <= i64::MAX as usize)
+            .ok_or(anyhow!("Page {} of size {} is too large", page, page_size))?;
+        format!("LIMIT {page_size} OFFSET {offset}")
+    };
+    let expenses = list(transaction, &format!("{EXPENSE_SELECT} {filter} {ordering} {limit}"), params_from_iter(values.iter()))?;
+    return Ok(ExpenseSearch {
+        expenses,
+        page,
+        page_size,
+        summary: ExpenseSummary { count, sum, average },
+    });
+}
+
+// Lower cased and matched anywhere, with LIKE wildcards in the text taken literally
+fn like_pattern(text: &str) -> String {
+    let escaped = text.to_lowercase().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    return format!("%{escaped}%");
+}
+
+// Unlinks rather than deletes, the expense still happened
+pub fn cascade_delete_account(transaction: &Transaction, account_id: String) -> anyhow::Result<()> {
+    relink_expenses(
+        transaction,
+        "transaction_id IN (SELECT id FROM account_transaction WHERE account_id = ?1 OR from_account_id = ?1)",
+        [account_id.clone()],
+        |expense| Expense { transaction_id: None, ..expense },
+    )?;
+    relink_expenses(transaction, "account_id = ?1", [account_id], |expense| Expense { account_id: None, ..expense })?;
+    return Ok(());
+}
+
+pub fn cascade_delete_transaction(transaction: &Transaction, transaction_id: String) -> anyhow::Result<()> {
+    relink_expenses(transaction, "transaction_id = ?1", [transaction_id], |expense| Expense { transaction_id: None, ..expense })?;
+    return Ok(());
+}
+
+pub fn cascade_merge_account(transaction: &Transaction, account_id: String, target_account_id: String) -> anyhow::Result<()> {
+    relink_expenses(transaction, "account_id = ?1", [account_id], |expense| Expense { account_id: Some(target_account_id.clone()), ..expense })?;
+    return Ok(());
+}
+
+// Expenses follow a transaction that was dropped in favour of another on the same date
+pub fn cascade_merge_transaction(transaction: &Transaction, transaction_id: String, target_transaction_id: String) -> anyhow::Result<()> {
+    relink_expenses(transaction, "transaction_id = ?1", [transaction_id], |expense| Expense { transaction_id: Some(target_transaction_id.clone()), ..expense })?;
+    return Ok(());
+}
+
+// Changes which account, transaction, merchant or project the matching expenses belong to, each one is audited
+pub fn relink_expenses<P: Params>(transaction: &Transaction, condition: &str, params: P, relink: impl Fn(Expense) -> Expense) -> anyhow::Result<()> {
+    let expenses: Vec<Expense> = list(transaction, &format!("{EXPENSE_SELECT} WHERE {condition}"), params)?;
+    for previous_expense in expenses {
+        let relinked = relink(previous_expense.clone());
+        let expense: Option<Expense> = single(
+            transaction,
+            formatcp!("UPDATE expense SET account_id = ?1, transaction_id = ?2, merchant_id = ?3, project_id = ?4 WHERE id = ?5 {EXPENSE_RETURNING}"),
+            params![relinked.account_id, relinked.transaction_id, relinked.merchant_id, relinked.project_id, relinked.id],
+        )?;
+        if let Some(expense) = &expense {
+            record_update(transaction, AuditEntityType::Expense, &expense.id, Some(&previous_expense), expense)?;
+        }
+    }
+    return Ok(());
+}
+
+// Expenses without an account are only held back by closes for every account
+fn verify_open(transaction: &Transaction, date: NaiveDate, account_id: Option<String>) -> anyhow::Result<()> {
+    let account_ids: Vec<String> = account_id.into_iter().collect();
+    return crate::period_close::db::verify_open(transaction, date, &account_ids);
+}
+
+// An explicit merchant is kept as long as it exists, otherwise external is matched against the merchants
+fn merchant(transaction: &Transaction, merchant_id: Option<String>, external: &str) -> anyhow::Result<Option<String>> {
+    if let Some(merchant_id) = merchant_id {
+        verify_merchant_id_exists(transaction, merchant_id.clone())?;
+        return Ok(Some(merchant_id));
+    }
+    return resolve_merchant_id(transaction, external);
+}
+
+// Refunds can pay back any purchase but reimbursements only pay back reimbursable expenses
+fn verify_original(transaction: &Transaction, id: Option<String>, kind: ExpenseKind, original_expense_id: Option<String>) -> anyhow::Result<()> {
+    match (kind, original_expense_id) {
+        (ExpenseKind::Purchase | ExpenseKind::Reimbursable, None) => {}
+        (ExpenseKind::Purchase | ExpenseKind::Reimbursable, Some(_)) => {
+            return Err(anyhow!("Only refunds and reimbursements can have an original expense"));
+        }
+        (ExpenseKind::Refund | ExpenseKind::Reimbursement, None) => {
+            return Err(anyhow!("A {} needs an original expense", kind));
+        }
+        (ExpenseKind::Refund | ExpenseKind::Reimbursement, Some(original_expense_id)) => {
+            if id.as_ref() == Some(&original_expense_id) {
+                return Err(anyhow!("Expense cannot be its own original expense"));
+            }
+            let original = get_expense(transaction, original_expense_id.clone())?
+                .ok_or(anyhow!("Expense {} does not exist", original_expense_id))?;
+            let allowed = match kind {
+                ExpenseKind::Refund => original.kind == ExpenseKind::Purchase || original.kind == ExpenseKind::Reimbursable,
+                _ => original.kind == ExpenseKind::Reimbursable,
+            };
+            if !allowed {
+                return Err(anyhow!("A {} cannot pay back a {} expense", kind, original.kind));
+            }
+        }
+    }
+    // Anything already paid back has to stay something that can be paid back
+    if let Some(id) = id {
+        let mut statement = transaction.prepare("SELECT kind FROM expense WHERE original_expense_id = ?1")?;
+        let linked_kinds: Vec<ExpenseKind> = statement.query_map([id.clone()], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        for linked_kind in linked_kinds {
+            let allowed = match linked_kind {
+                ExpenseKind::Refund => kind == ExpenseKind::Purchase || kind == ExpenseKind::Reimbursable,
+                _ => kind == ExpenseKind::Reimbursable,
+            };
+            if !allowed {
+                return Err(anyhow!("Expense {} has a {} so cannot be a {} expense", id, linked_kind, kind));
+            }
+        }
+    }
+    return Ok(());
+}
+
+fn verify_links(transaction: &Transaction, account_id: Option<String>, transaction_id: Option<String>) -> anyhow::Result<()> {
+    if let Some(account_id) = account_id.clone() {
+        verify_account_id_exists(transaction, account_id)?;
+    }
+    if let Some(transaction_id) = transaction_id {
+        let linked = get_transaction(transaction, transaction_id.clone())?
+            .ok_or(anyhow!("Transaction {} does not exist", transaction_id))?;
+        if linked.transaction_type != TransactionType::Transfer {
+            return Err(anyhow!("Transaction {} is not a transfer", transaction_id));
+        }
+        if let Some(account_id) = account_id {
+            if linked.account_id != account_id && linked.from_account_id != Some(account_id.clone()) {
+                return Err(anyhow!("Transaction {} does not involve account {}", transaction_id, account_id));
+            }
+        }
+    }
+    return Ok(());
+}
+
 fn normalize_decimal(decimal: &Decimal) -> Decimal {
     let mut cloned = decimal.clone();
     cloned.rescale(2);
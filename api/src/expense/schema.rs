@@ -6,7 +6,7 @@ use rusqlite::Row;
 use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use strum_macros::{Display, EnumIter, EnumString};
+use strum_macros::{Display, EnumString};
 use crate::db::FromRow;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -14,85 +14,172 @@ pub struct Expense {
     pub id: String,
     pub description: String,
     pub external: String,
-    pub category: ExpenseCategory,
+    // Id of an expense category
+    pub category: String,
     pub date: NaiveDate, 
     #[serde(with = "rust_decimal::serde::float")]
     pub value: Decimal,
+    // Account the expense was paid from
+    #[serde(rename = "accountId")]
+    pub account_id: Option<String>,
+    // Transfer the expense is part of
+    #[serde(rename = "transactionId")]
+    pub transaction_id: Option<String>,
+    // Canonical merchant, worked out from external when not given
+    #[serde(rename = "merchantId")]
+    pub merchant_id: Option<String>,
+    #[serde(default)]
+    pub kind: ExpenseKind,
+    // The purchase a refund or the reimbursable expense a reimbursement pays back
+    #[serde(rename = "originalExpenseId", default)]
+    pub original_expense_id: Option<String>,
+    #[serde(rename = "projectId", default)]
+    pub project_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct NewExpense {
     pub description: String,
     pub external: String,
-    pub category: ExpenseCategory,
+    pub category: String,
     pub date: NaiveDate, 
     #[serde(with = "rust_decimal::serde::float")]
     pub value: Decimal,
+    #[serde(rename = "accountId")]
+    pub account_id: Option<String>,
+    #[serde(rename = "transactionId")]
+    pub transaction_id: Option<String>,
+    #[serde(rename = "merchantId")]
+    pub merchant_id: Option<String>,
+    #[serde(default)]
+    pub kind: ExpenseKind,
+    #[serde(rename = "originalExpenseId", default)]
+    pub original_expense_id: Option<String>,
+    #[serde(rename = "projectId", default)]
+    pub project_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Display, EnumString, Serialize, Deserialize, PartialEq, Eq, EnumIter, Hash)]
-pub enum ExpenseCategory {
-    #[serde(rename = "UNKNOWN" )]
-    #[strum(serialize="UNKNOWN", to_string="UNKNOWN")]
-    Unknown,
-    #[serde(rename = "OTHER" )]
-    #[strum(serialize="OTHER", to_string="OTHER")]
-    Other,
+// Values are always what changed hands, refunds and reimbursements are taken off spending rather than being negative
+#[derive(Debug, Clone, Copy, Default, Display, EnumString, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+pub enum ExpenseKind {
+    #[default]
+    #[serde(rename = "PURCHASE" )]
+    #[strum(serialize="PURCHASE", to_string="PURCHASE")]
+    Purchase,
+    #[serde(rename = "REFUND" )]
+    #[strum(serialize="REFUND", to_string="REFUND")]
+    Refund,
+    // Paid for now and expected to be paid back, usually by an employer
+    #[serde(rename = "REIMBURSABLE" )]
+    #[strum(serialize="REIMBURSABLE", to_string="REIMBURSABLE")]
+    Reimbursable,
+    #[serde(rename = "REIMBURSEMENT" )]
+    #[strum(serialize="REIMBURSEMENT", to_string="REIMBURSEMENT")]
+    Reimbursement
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OutstandingReimbursement {
+    #[serde(rename = "expenseId")]
+    pub expense_id: String,
+    pub date: NaiveDate,
+    pub category: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub reimbursed: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub outstanding: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountExpenseReport {
+    #[serde(rename = "accountId")]
+    pub account_id: String,
+    // Spending worked out from balances and transfers
+    #[serde(with = "rust_decimal::serde::float")]
+    pub outflow: Decimal,
+    // Linked expenses with a category
+    #[serde(with = "rust_decimal::serde::float")]
+    pub categorised: Decimal,
+    // Linked expenses that are still UNKNOWN
+    #[serde(with = "rust_decimal::serde::float")]
+    pub uncategorised: Decimal,
+    // Outflow without any linked expense, negative when more has been itemised than was spent
+    #[serde(with = "rust_decimal::serde::float")]
+    pub unitemised: Decimal,
+}
 
-    #[serde(rename = "AI" )]
-    #[strum(serialize="AI", to_string="AI")]
-    AI,
-    #[serde(rename = "BILLS" )]
-    #[strum(serialize="BILLS", to_string="BILLS")]
-    Bills,
-    #[serde(rename = "CLOTHING" )]
-    #[strum(serialize="CLOTHING", to_string="CLOTHING")]
-    Car,
-    #[serde(rename = "CAR" )]
-    #[strum(serialize="CAR", to_string="CAR")]
-    Clothing,
-    #[serde(rename = "COFFEE" )]
-    #[strum(serialize="COFFEE", to_string="COFFEE")]
-    Coffee,
-    #[serde(rename = "ELECTRONICS" )]
-    #[strum(serialize="ELECTRONICS", to_string="ELECTRONICS")]
-    Electronics,
-    #[serde(rename = "ENTERTAINMENT" )]
-    #[strum(serialize="ENTERTAINMENT", to_string="ENTERTAINMENT")]
-    Entertainment,
-    #[serde(rename = "FITNESS" )]
-    #[strum(serialize="FITNESS", to_string="FITNESS")]
-    Fitness,
-    #[serde(rename = "GIFTS" )]
-    #[strum(serialize="GIFTS", to_string="GIFTS")]
-    Gifts,
-    #[serde(rename = "GROCERIES" )]
-    #[strum(serialize="GROCERIES", to_string="GROCERIES")]
-    Groceries,
-    #[serde(rename = "HOUSE" )]
-    #[strum(serialize="HOUSE", to_string="HOUSE")]
-    House,
-    #[serde(rename = "MEDICAL" )]
-    #[strum(serialize="MEDICAL", to_string="MEDICAL")]
-    Medical,
-    #[serde(rename = "PETS" )]
-    #[strum(serialize="PETS", to_string="PETS")]
-    Pets,
-    #[serde(rename = "RESTAURANTS" )]
-    #[strum(serialize="RESTAURANTS", to_string="RESTAURANTS")]
-    Restaurants,
-    #[serde(rename = "SMART_HOME" )]
-    #[strum(serialize="SMART_HOME", to_string="SMART_HOME")]
-    SmartHome,
-    #[serde(rename = "SUBSCRIPTIONS" )]
-    #[strum(serialize="SUBSCRIPTIONS", to_string="SUBSCRIPTIONS")]
-    Subscriptions,
-    #[serde(rename = "VACATIONS" )]
-    #[strum(serialize="VACATIONS", to_string="VACATIONS")]
-    Vacations,
-    #[serde(rename = "WORK" )]
-    #[strum(serialize="WORK", to_string="WORK")]
-    Work,
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountExpenseReportQuery {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpenseSearchQuery {
+    // Inclusive on both ends
+    pub start: Option<NaiveDate>,
+    pub end: Option<NaiveDate>,
+    // Comma separated category ids, subcategories are included
+    pub categories: Option<String>,
+    // Case insensitive substring of external
+    pub external: Option<String>,
+    #[serde(with = "rust_decimal::serde::float_option", default)]
+    pub min_value: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::float_option", default)]
+    pub max_value: Option<Decimal>,
+    // Comma separated merchant ids
+    pub merchants: Option<String>,
+    pub project_id: Option<String>,
+    // Comma separated tag ids, expenses with any of them match
+    pub tags: Option<String>,
+    // Case insensitive substring of description
+    pub search: Option<String>,
+    pub sort: Option<ExpenseSort>,
+    pub direction: Option<SortDirection>,
+    // Pages start at 0, without a page size (or with 0) everything is returned
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExpenseSort {
+    #[serde(rename = "date")]
+    Date,
+    #[serde(rename = "value")]
+    Value,
+    #[serde(rename = "description")]
+    Description,
+    #[serde(rename = "category")]
+    Category,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SortDirection {
+    #[serde(rename = "asc")]
+    Ascending,
+    #[serde(rename = "desc")]
+    Descending,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpenseSearch {
+    pub expenses: Vec<Expense>,
+    pub page: usize,
+    #[serde(rename = "pageSize")]
+    pub page_size: usize,
+    // Covers every matching expense, not just the page
+    pub summary: ExpenseSummary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpenseSummary {
+    pub count: usize,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub sum: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub average: Decimal,
 }
 
 impl FromRow for Expense {
@@ -104,16 +191,42 @@ impl FromRow for Expense {
             category: row.get("category")?,
             date: crate::db::get_naive_date(row, "date")?,
             value: crate::db::get_decimal(row, "value")?,
+            account_id: row.get("account_id")?,
+            transaction_id: row.get("transaction_id")?,
+            merchant_id: row.get("merchant_id")?,
+            kind: row.get("kind")?,
+            original_expense_id: row.get("original_expense_id")?,
+            project_id: row.get("project_id")?,
         })
     }
 }
 
-impl FromSql for ExpenseCategory {
+// Shared by the unit tests, a plain purchase on 2024-01-01 with nothing linked
+#[cfg(test)]
+impl Expense {
+    pub fn for_test(id: &str, category: &str, value: Decimal) -> Expense {
+        return Expense {
+            id: id.to_string(),
+            description: "".to_string(),
+            external: "".to_string(),
+            category: category.to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            value,
+            account_id: None,
+            transaction_id: None,
+            merchant_id: None,
+            kind: ExpenseKind::Purchase,
+            original_expense_id: None,
+            project_id: None,
+        };
+    }
+}
+
+impl FromSql for ExpenseKind {
     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
         match value.as_str() {
-            Ok(value) => ExpenseCategory::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
+            Ok(value) => ExpenseKind::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
             Err(err) => Err(err)
         }
     }
 }
-
@@ -0,0 +1,73 @@
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use log::{error, info};
+use crate::expense_category::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+
+#[post("/api/expense-category/")]
+pub async fn create_expense_category(db: web::Data<Pool>, new_expense_category: web::Json<schema::NewExpenseCategory>) -> Result<HttpResponse, Error> {
+    let new_expense_category = new_expense_category.into_inner();
+    info!("HTTP create_expense_category new_expense_category:[{:?}]", new_expense_category);
+    do_in_transaction(&db, |transaction| db::create_expense_category(transaction, new_expense_category))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_expense_category: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+
+#[post("/api/expense-category/{id}/")]
+pub async fn update_expense_category(db: web::Data<Pool>, path: web::Path<String>, updated_expense_category: web::Json<schema::ExpenseCategory>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let updated_expense_category = updated_expense_category.into_inner();
+    info!("HTTP update_expense_category id:[{}] updated_expense_category:[{:?}]", id, updated_expense_category);
+    let option = crate::api::compare_ids(&id, &updated_expense_category.id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::update_expense_category(transaction, updated_expense_category))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP update_expense_category: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/expense-category/{id}/")]
+pub async fn delete_expense_category(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_expense_category id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_expense_category(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_expense_category: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/expense-category/{id}/")]
+pub async fn get_expense_category(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_expense_category id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_expense_category(transaction, id))
+        .await.map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_expense_category: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/expense-category/")]
+pub async fn list_expense_categories(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_expense_categories");
+    do_in_transaction(&db, |transaction| db::list_expense_categories(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_expense_categories: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
\ No newline at end of file
@@ -0,0 +1,128 @@
+use std::collections::{HashMap, HashSet};
+use anyhow::anyhow;
+use const_format::formatcp;
+use rusqlite::{params, Transaction};
+use uuid::Uuid;
+use crate::db::{list, single};
+use crate::expense_category::schema::{ExpenseCategory, NewExpenseCategory, UNKNOWN_EXPENSE_CATEGORY_ID};
+
+const EXPENSE_CATEGORY_COLUMNS: &str = "id, name, parent_id, colour, hidden";
+const EXPENSE_CATEGORY_SELECT: &str = formatcp!("SELECT {EXPENSE_CATEGORY_COLUMNS} FROM expense_category");
+const EXPENSE_CATEGORY_RETURNING: &str = formatcp!("RETURNING {EXPENSE_CATEGORY_COLUMNS}");
+const EXPENSE_CATEGORY_ORDERING: &str = "ORDER BY name, id ASC";
+
+pub fn create_expense_category(transaction: &Transaction, new_expense_category: NewExpenseCategory) -> anyhow::Result<Option<ExpenseCategory>> {
+    let id = Uuid::new_v4().to_string();
+    verify_parent(transaction, &id, new_expense_category.parent_id.clone())?;
+    return single(
+        transaction,
+        formatcp!("INSERT INTO expense_category ({EXPENSE_CATEGORY_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5) {EXPENSE_CATEGORY_RETURNING}"),
+        params![id, new_expense_category.name, new_expense_category.parent_id, new_expense_category.colour, new_expense_category.hidden as i32],
+    );
+}
+
+pub fn update_expense_category(transaction: &Transaction, updated_expense_category: ExpenseCategory) -> anyhow::Result<Option<ExpenseCategory>> {
+    verify_parent(transaction, &updated_expense_category.id, updated_expense_category.parent_id.clone())?;
+    return single(
+        transaction,
+        formatcp!("UPDATE expense_category SET name = ?1, parent_id = ?2, colour = ?3, hidden = ?4 WHERE id = ?5 {EXPENSE_CATEGORY_RETURNING}"),
+        params![updated_expense_category.name, updated_expense_category.parent_id, updated_expense_category.colour, updated_expense_category.hidden as i32, updated_expense_category.id],
+    );
+}
+
+pub fn delete_expense_category(transaction: &Transaction, id: String) -> anyhow::Result<Option<ExpenseCategory>> {
+    verify_deletable(transaction, id.clone())?;
+    crate::budget::db::cascade_delete_expense_category(transaction, id.clone())?;
+    crate::expense_rule::db::cascade_delete_expense_category(transaction, id.clone())?;
+    crate::expense_classifier::db::cascade_delete_expense_category(transaction, id.clone())?;
+    return single(
+        transaction,
+        formatcp!("DELETE FROM expense_category WHERE id = ?1 {EXPENSE_CATEGORY_RETURNING}"),
+        [id],
+    );
+}
+
+pub fn get_expense_category(transaction: &Transaction, id: String) -> anyhow::Result<Option<ExpenseCategory>> {
+    return single(
+        transaction,
+        formatcp!("{EXPENSE_CATEGORY_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn list_expense_categories(transaction: &Transaction) -> anyhow::Result<Vec<ExpenseCategory>> {
+    return list(
+        transaction,
+        formatcp!("{EXPENSE_CATEGORY_SELECT} {EXPENSE_CATEGORY_ORDERING}"),
+        [],
+    );
+}
+
+pub fn verify_expense_category_id_exists(transaction: &Transaction, id: String) -> anyhow::Result<()> {
+    let result = get_expense_category(transaction, id.clone())?;
+    if result.is_none() {
+        return Err(anyhow!("Expense category {} does not exist", id));
+    }
+    return Ok(())
+}
+
+// Each category along with every category above it, so totals can be rolled up into parents
+pub fn ancestors_by_expense_category_id(expense_categories: &[ExpenseCategory]) -> HashMap<String, Vec<String>> {
+    let parent_by_id: HashMap<String, Option<String>> = expense_categories.iter()
+        .map(|expense_category| (expense_category.id.clone(), expense_category.parent_id.clone()))
+        .collect();
+    return expense_categories.iter()
+        .map(|expense_category| {
+            let mut ancestors = vec![expense_category.id.clone()];
+            let mut current = expense_category.parent_id.clone();
+            while let Some(parent_id) = current {
+                if ancestors.contains(&parent_id) {
+                    break;
+                }
+                ancestors.push(parent_id.clone());
+                current = parent_by_id.get(&parent_id).cloned().flatten();
+            }
+            return (expense_category.id.clone(), ancestors);
+        })
+        .collect();
+}
+
+fn verify_parent(transaction: &Transaction, id: &str, parent_id: Option<String>) -> anyhow::Result<()> {
+    if parent_id.is_none() {
+        return Ok(());
+    }
+    let expense_categories = list_expense_categories(transaction)?;
+    let parent_by_id: HashMap<String, Option<String>> = expense_categories.iter()
+        .map(|expense_category| (expense_category.id.clone(), expense_category.parent_id.clone()))
+        .collect();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut current = parent_id;
+    while let Some(parent_id) = current {
+        if parent_id == id {
+            return Err(anyhow!("Expense category cannot be its own parent"));
+        }
+        if !parent_by_id.contains_key(&parent_id) {
+            return Err(anyhow!("Expense category {} does not exist", parent_id));
+        }
+        if !seen.insert(parent_id.clone()) {
+            break;
+        }
+        current = parent_by_id.get(&parent_id).cloned().flatten();
+    }
+    return Ok(());
+}
+
+fn verify_deletable(transaction: &Transaction, id: String) -> anyhow::Result<()> {
+    if id == UNKNOWN_EXPENSE_CATEGORY_ID {
+        return Err(anyhow!("Expense category {} cannot be deleted", id));
+    }
+    let children: i64 = transaction.query_row("SELECT COUNT(*) FROM expense_category WHERE parent_id = ?1", [id.clone()], |row| row.get(0))?;
+    if children != 0 {
+        return Err(anyhow!("Expense category {} has subcategories", id));
+    }
+    let expenses: i64 = transaction.query_row("SELECT COUNT(*) FROM expense WHERE category = ?1 AND deleted_at IS NULL", [id.clone()], |row| row.get(0))?;
+    if expenses != 0 {
+        return Err(anyhow!("Expense category {} is used by {} expenses", id, expenses));
+    }
+    return Ok(());
+}
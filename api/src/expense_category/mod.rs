@@ -0,0 +1,3 @@
+pub mod api;
+pub mod db;
+pub mod schema;
\ No newline at end of file
@@ -0,0 +1,38 @@
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use crate::db::FromRow;
+
+// Seeded by the migration and used as the fallback for expenses that haven't been categorised
+pub const UNKNOWN_EXPENSE_CATEGORY_ID: &str = "UNKNOWN";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpenseCategory {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<String>,
+    pub colour: Option<String>,
+    // Hides retired categories from selection while keeping them for existing expenses
+    pub hidden: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewExpenseCategory {
+    pub name: String,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<String>,
+    pub colour: Option<String>,
+    pub hidden: bool,
+}
+
+impl FromRow for ExpenseCategory {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ExpenseCategory {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            parent_id: row.get("parent_id")?,
+            colour: row.get("colour")?,
+            hidden: row.get::<_, i32>("hidden")? != 0,
+        })
+    }
+}
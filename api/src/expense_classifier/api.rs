@@ -0,0 +1,18 @@
+use actix_web::{Error, error, HttpResponse, post, web};
+use log::{error, info};
+use crate::db::{do_in_transaction, Pool};
+use crate::expense_classifier::db;
+use crate::expense_classifier::schema::SuggestCategory;
+
+#[post("/api/expense/suggest-category/")]
+pub async fn suggest_categories(db: web::Data<Pool>, suggest_category: web::Json<SuggestCategory>) -> Result<HttpResponse, Error> {
+    let suggest_category = suggest_category.into_inner();
+    info!("HTTP suggest_categories suggest_category:[{:?}]", suggest_category);
+    do_in_transaction(&db, |transaction| db::suggest_categories(transaction, suggest_category))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP suggest_categories: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
@@ -0,0 +1,154 @@
+use std::collections::{BTreeSet, HashMap};
+use rusqlite::Transaction;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use crate::db::list;
+use crate::expense::db::list_expenses;
+use crate::expense::schema::Expense;
+use crate::expense_category::db::list_expense_categories;
+use crate::expense_category::schema::UNKNOWN_EXPENSE_CATEGORY_ID;
+use crate::expense_classifier::schema::{CategorySuggestion, ExpenseToken, SuggestCategory};
+
+const DEFAULT_LIMIT: usize = 3;
+const VALUE_TOKEN_PREFIX: &str = "#value:";
+
+// Called with 1 when an expense is saved and -1 when it's removed so the counts always follow the expense table
+pub fn learn_expense(transaction: &Transaction, expense: &Expense, delta: i64) -> anyhow::Result<()> {
+    if expense.category == UNKNOWN_EXPENSE_CATEGORY_ID {
+        return Ok(());
+    }
+    for token in tokenize(&expense.description, &expense.external, expense.value) {
+        transaction.execute(
+            "INSERT INTO expense_token (category_id, token, count) VALUES (?1, ?2, ?3) ON CONFLICT (category_id, token) DO UPDATE SET count = count + ?3",
+            (expense.category.clone(), token, delta),
+        )?;
+    }
+    transaction.execute("DELETE FROM expense_token WHERE count <= 0", [])?;
+    return Ok(());
+}
+
+pub fn suggest_categories(transaction: &Transaction, suggest_category: SuggestCategory) -> anyhow::Result<Vec<CategorySuggestion>> {
+    let mut expense_tokens = list_expense_tokens(transaction)?;
+    // Every learnt expense has exactly one value token, so a mismatch means expenses from before the classifier existed
+    let expenses: i64 = transaction.query_row(
+        "SELECT COUNT(*) FROM expense WHERE deleted_at IS NULL AND category != ?1",
+        [UNKNOWN_EXPENSE_CATEGORY_ID],
+        |row| row.get(0),
+    )?;
+    if expenses != documents_by_category(&expense_tokens).values().sum::<i64>() {
+        transaction.execute("DELETE FROM expense_token", [])?;
+        for expense in list_expenses(transaction)? {
+            learn_expense(transaction, &expense, 1)?;
+        }
+        expense_tokens = list_expense_tokens(transaction)?;
+    }
+
+    let scores = score(&documents_by_category(&expense_tokens), &expense_tokens, &tokenize(&suggest_category.description, &suggest_category.external, suggest_category.value));
+    let expense_categories = list_expense_categories(transaction)?;
+    return Ok(scores.into_iter()
+        .filter_map(|(category_id, confidence)| {
+            let expense_category = expense_categories.iter().find(|expense_category| expense_category.id == category_id)?;
+            return Some(CategorySuggestion {
+                category: expense_category.clone(),
+                confidence: Decimal::from_f64(confidence).unwrap_or(Decimal::ZERO).round_dp(4),
+            });
+        })
+        .take(suggest_category.limit.unwrap_or(DEFAULT_LIMIT))
+        .collect());
+}
+
+pub fn cascade_delete_expense_category(transaction: &Transaction, category_id: String) -> anyhow::Result<()> {
+    transaction.execute("DELETE FROM expense_token WHERE category_id = ?1", [category_id])?;
+    return Ok(());
+}
+
+fn list_expense_tokens(transaction: &Transaction) -> anyhow::Result<Vec<ExpenseToken>> {
+    return list(
+        transaction,
+        "SELECT category_id, token, count FROM expense_token ORDER BY category_id, token ASC",
+        [],
+    );
+}
+
+// The value token counts one per learnt expense, so they double as the number of expenses in each category
+fn documents_by_category(expense_tokens: &[ExpenseToken]) -> HashMap<String, i64> {
+    let mut documents_by_category: HashMap<String, i64> = HashMap::new();
+    for expense_token in expense_tokens {
+        if expense_token.token.starts_with(VALUE_TOKEN_PREFIX) {
+            *documents_by_category.entry(expense_token.category_id.clone()).or_insert(0) += expense_token.count;
+        }
+    }
+    return documents_by_category;
+}
+
+// Multinomial naive Bayes with add one smoothing, normalised so the confidences sum to one
+fn score(documents_by_category: &HashMap<String, i64>, expense_tokens: &[ExpenseToken], tokens: &BTreeSet<String>) -> Vec<(String, f64)> {
+    let documents: i64 = documents_by_category.values().sum();
+    let vocabulary = expense_tokens.iter().map(|expense_token| &expense_token.token).collect::<BTreeSet<_>>().len() as f64;
+    let mut totals_by_category: HashMap<&str, i64> = HashMap::new();
+    let mut counts: HashMap<(&str, &str), i64> = HashMap::new();
+    for expense_token in expense_tokens {
+        *totals_by_category.entry(&expense_token.category_id).or_insert(0) += expense_token.count;
+        counts.insert((&expense_token.category_id, &expense_token.token), expense_token.count);
+    }
+
+    let mut log_scores: Vec<(String, f64)> = documents_by_category.iter()
+        .map(|(category_id, category_documents)| {
+            let total = *totals_by_category.get(category_id.as_str()).unwrap_or(&0) as f64;
+            let mut log_score = (*category_documents as f64 / documents as f64).ln();
+            for token in tokens {
+                let count = *counts.get(&(category_id.as_str(), token.as_str())).unwrap_or(&0) as f64;
+                log_score += ((count + 1.0) / (total + vocabulary + 1.0)).ln();
+            }
+            return (category_id.clone(), log_score);
+        })
+        .collect();
+    let max = log_scores.iter().map(|(_, log_score)| *log_score).fold(f64::NEG_INFINITY, f64::max);
+    let sum: f64 = log_scores.iter().map(|(_, log_score)| (log_score - max).exp()).sum();
+    for (_, log_score) in log_scores.iter_mut() {
+        *log_score = (*log_score - max).exp() / sum;
+    }
+    log_scores.sort_by(|one, two| two.1.total_cmp(&one.1).then(one.0.cmp(&two.0)));
+    return log_scores;
+}
+
+// Words from the description and external id plus the order of magnitude of the value
+fn tokenize(description: &str, external: &str, value: Decimal) -> BTreeSet<String> {
+    let mut tokens: BTreeSet<String> = format!("{description} {external}")
+        .to_lowercase()
+        .split(|character: char| !character.is_alphanumeric())
+        .filter(|token| token.len() > 1 && !token.chars().all(|character| character.is_ascii_digit()))
+        .map(|token| token.to_string())
+        .collect();
+    tokens.insert(format!("{VALUE_TOKEN_PREFIX}{}", value.abs().trunc().to_string().len()));
+    return tokens;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            BTreeSet::from(["#value:2".to_string(), "card".to_string(), "payment".to_string(), "stores".to_string(), "tesco".to_string()]),
+            tokenize("Card payment: TESCO", "tesco-stores-4021 a", Decimal::new(2550, 2)),
+        );
+    }
+
+    #[test]
+    fn test_score() {
+        let expense_tokens = vec![
+            ExpenseToken { category_id: "GROCERIES".to_string(), token: "tesco".to_string(), count: 2 },
+            ExpenseToken { category_id: "GROCERIES".to_string(), token: "#value:2".to_string(), count: 2 },
+            ExpenseToken { category_id: "CAR".to_string(), token: "shell".to_string(), count: 1 },
+            ExpenseToken { category_id: "CAR".to_string(), token: "#value:2".to_string(), count: 1 },
+        ];
+        assert_eq!(HashMap::from([("GROCERIES".to_string(), 2), ("CAR".to_string(), 1)]), documents_by_category(&expense_tokens));
+
+        let scores = score(&documents_by_category(&expense_tokens), &expense_tokens, &tokenize("Shell", "", Decimal::new(40, 0)));
+        assert_eq!(vec!["CAR", "GROCERIES"], scores.iter().map(|(category_id, _)| category_id.as_str()).collect::<Vec<&str>>());
+        assert!(scores[0].1 > 0.5);
+        assert!((scores.iter().map(|(_, confidence)| confidence).sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,40 @@
+use rusqlite::Row;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use crate::db::FromRow;
+use crate::expense_category::schema::ExpenseCategory;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SuggestCategory {
+    pub description: String,
+    pub external: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    // Defaults to 3
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CategorySuggestion {
+    pub category: ExpenseCategory,
+    // Between 0 and 1, summed across every category that has been seen
+    #[serde(with = "rust_decimal::serde::float")]
+    pub confidence: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpenseToken {
+    pub category_id: String,
+    pub token: String,
+    pub count: i64,
+}
+
+impl FromRow for ExpenseToken {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ExpenseToken {
+            category_id: row.get("category_id")?,
+            token: row.get("token")?,
+            count: row.get("count")?,
+        })
+    }
+}
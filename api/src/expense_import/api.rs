@@ -0,0 +1,339 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use log::{error, info};
+use rust_decimal::Decimal;
+use crate::account::db::verify_account_id_exists;
+use crate::expense_import::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+use crate::expense::db::{insert_expense, list_expenses};
+use crate::expense::schema::{Expense, ExpenseKind, NewExpense};
+use crate::expense_category::schema::UNKNOWN_EXPENSE_CATEGORY_ID;
+use crate::expense_import::schema::{ExpenseImport, ImportedRow, ImportProfile, ImportStatus};
+use crate::expense_rule::db::{categorise_with_rules, list_expense_rules};
+use crate::merchant::db::{list_merchants, match_merchant};
+use crate::period_close::db::verify_open;
+
+#[post("/api/expense/import/")]
+pub async fn import_expenses(db: web::Data<Pool>, expense_import: web::Json<ExpenseImport>) -> Result<HttpResponse, Error> {
+    let expense_import = expense_import.into_inner();
+    info!("HTTP import_expenses profile_id:[{}] account_id:[{:?}] dry_run:[{}]", expense_import.profile_id, expense_import.account_id, expense_import.dry_run);
+    do_in_transaction(&db, move |transaction| {
+        let import_profile = db::get_import_profile(transaction, expense_import.profile_id.clone())?
+            .ok_or(anyhow!("Import profile {} does not exist", expense_import.profile_id))?;
+        if let Some(account_id) = expense_import.account_id.clone() {
+            verify_account_id_exists(transaction, account_id)?;
+        }
+        let existing_expenses = list_expenses(transaction)?;
+        // Rules and merchants are loaded once for the whole statement rather than once per row
+        let expense_rules = list_expense_rules(transaction)?;
+        let expense_rule_patterns = crate::expense_rule::db::compile_patterns(&expense_rules);
+        let merchants = list_merchants(transaction)?;
+        let merchant_patterns = crate::merchant::db::compile_patterns(&merchants);
+        let mut matched_expense_ids: HashSet<String> = HashSet::new();
+        let mut imported: Vec<(usize, Option<String>, NewExpense)> = vec![];
+        let mut imported_rows: Vec<ImportedRow> = vec![];
+        for (line, row) in read_statement(&import_profile, &expense_import.content, expense_import.account_id.clone()) {
+            let new_expense = match row {
+                Ok(new_expense) => new_expense,
+                Err(message) => {
+                    imported_rows.push(ImportedRow { line, status: ImportStatus::Invalid, expense: None, expense_id: None, duplicate_of: None, message: Some(message) });
+                    continue;
+                }
+            };
+            let mut new_expense = categorise_with_rules(&expense_rules, &expense_rule_patterns, new_expense);
+            new_expense.merchant_id = match_merchant(&merchants, &merchant_patterns, &new_expense.external);
+            if new_expense.value <= Decimal::ZERO {
+                imported_rows.push(ImportedRow { line, status: ImportStatus::Skipped, expense: Some(new_expense), expense_id: None, duplicate_of: None, message: Some("Credits are not imported".to_string()) });
+                continue;
+            }
+            let account_ids: Vec<String> = new_expense.account_id.clone().into_iter().collect();
+            if let Err(err) = verify_open(transaction, new_expense.date, &account_ids) {
+                imported_rows.push(ImportedRow { line, status: ImportStatus::Skipped, expense: Some(new_expense), expense_id: None, duplicate_of: None, message: Some(err.to_string()) });
+                continue;
+            }
+            let duplicate = find_duplicate(&existing_expenses, &matched_expense_ids, &new_expense, import_profile.duplicate_days);
+            let (status, duplicate_of, message) = match duplicate {
+                Some((ImportStatus::Skipped, expense_id)) => {
+                    matched_expense_ids.insert(expense_id.clone());
+                    imported_rows.push(ImportedRow { line, status: ImportStatus::Skipped, expense: Some(new_expense), expense_id: None, duplicate_of: Some(expense_id), message: Some("Already imported".to_string()) });
+                    continue;
+                }
+                Some((status, expense_id)) => (status, Some(expense_id), None),
+                None => match find_imported_duplicate(&imported, &new_expense, import_profile.duplicate_days) {
+                    Some((imported_line, expense_id)) => (ImportStatus::Flagged, expense_id, Some(format!("Same as line {}", imported_line))),
+                    None => (ImportStatus::Created, None, None),
+                },
+            };
+            let expense_id = if expense_import.dry_run {
+                None
+            } else {
+                insert_expense(transaction, new_expense.clone())?.map(|expense| expense.id)
+            };
+            imported.push((line, expense_id.clone(), new_expense.clone()));
+            imported_rows.push(ImportedRow { line, status, expense: Some(new_expense), expense_id, duplicate_of, message });
+        }
+        return Ok(imported_rows);
+    })
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP import_expenses: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[post("/api/import-profile/")]
+pub async fn create_import_profile(db: web::Data<Pool>, new_import_profile: web::Json<schema::NewImportProfile>) -> Result<HttpResponse, Error> {
+    let new_import_profile = new_import_profile.into_inner();
+    info!("HTTP create_import_profile new_import_profile:[{:?}]", new_import_profile);
+    do_in_transaction(&db, |transaction| db::create_import_profile(transaction, new_import_profile))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_import_profile: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+
+#[post("/api/import-profile/{id}/")]
+pub async fn update_import_profile(db: web::Data<Pool>, path: web::Path<String>, updated_import_profile: web::Json<schema::ImportProfile>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let updated_import_profile = updated_import_profile.into_inner();
+    info!("HTTP update_import_profile id:[{}] updated_import_profile:[{:?}]", id, updated_import_profile);
+    let option = crate::api::compare_ids(&id, &updated_import_profile.id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::update_import_profile(transaction, updated_import_profile))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP update_import_profile: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/import-profile/{id}/")]
+pub async fn delete_import_profile(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_import_profile id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_import_profile(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_import_profile: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/import-profile/{id}/")]
+pub async fn get_import_profile(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_import_profile id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_import_profile(transaction, id))
+        .await.map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_import_profile: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/import-profile/")]
+pub async fn list_import_profiles(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_import_profiles");
+    do_in_transaction(&db, |transaction| db::list_import_profiles(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_import_profiles: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+// Each row along with its line, rows that can't be read have the reason instead
+fn read_statement(import_profile: &ImportProfile, content: &str, account_id: Option<String>) -> Vec<(usize, Result<NewExpense, String>)> {
+    let delimiter = import_profile.delimiter.chars().next().unwrap_or(',');
+    return parse_csv(content, delimiter).into_iter()
+        .enumerate()
+        .skip(if import_profile.header { 1 } else { 0 })
+        .filter(|(_, columns)| columns.iter().any(|column| !column.trim().is_empty()))
+        .map(|(index, columns)| (index + 1, read_row(import_profile, &columns, account_id.clone())))
+        .collect();
+}
+
+fn read_row(import_profile: &ImportProfile, columns: &[String], account_id: Option<String>) -> Result<NewExpense, String> {
+    let column = |index: usize| columns.get(index)
+        .map(|column| column.trim().to_string())
+        .ok_or(format!("Column {} is missing", index));
+    let raw_date = column(import_profile.date_column)?;
+    let date = NaiveDate::parse_from_str(&raw_date, &import_profile.date_format)
+        .map_err(|_| format!("Date {} does not match {}", raw_date, import_profile.date_format))?;
+    let raw_value = column(import_profile.value_column)?;
+    let mut value = parse_value(&raw_value).ok_or(format!("Value {} is not a number with . as the decimal point", raw_value))?;
+    if import_profile.negate {
+        value = -value;
+    }
+    let external = column(import_profile.external_column)?;
+    if external.is_empty() {
+        return Err("Merchant is empty".to_string());
+    }
+    let description = match import_profile.description_column {
+        Some(description_column) => column(description_column)?,
+        None => "".to_string(),
+    };
+    return Ok(NewExpense {
+        description,
+        external,
+        category: UNKNOWN_EXPENSE_CATEGORY_ID.to_string(),
+        date,
+        value,
+        account_id,
+        transaction_id: None,
+        merchant_id: None,
+        kind: ExpenseKind::Purchase,
+        original_expense_id: None,
+        project_id: None,
+    });
+}
+
+// Statements decorate amounts with currency symbols, thousands separators and brackets for negatives,
+// a comma is only read as a thousands separator so anything like 1.234,56 or 1,5 is refused rather than misread
+fn parse_value(raw: &str) -> Option<Decimal> {
+    let negative = raw.starts_with('(') && raw.ends_with(')');
+    let cleaned: String = raw.chars()
+        .filter(|character| character.is_ascii_digit() || *character == '.' || *character == ',' || *character == '-')
+        .collect();
+    let (integer, fraction) = cleaned.split_once('.').unwrap_or((&cleaned, ""));
+    if fraction.contains(['.', ',']) {
+        return None;
+    }
+    let mut groups = integer.split(',');
+    let first = groups.next().unwrap_or("");
+    if integer.contains(',') && (first.trim_start_matches('-').is_empty() || groups.any(|group| group.len() != 3)) {
+        return None;
+    }
+    let value = Decimal::from_str(&cleaned.replace(',', "")).ok()?;
+    return Some(if negative { -value } else { value });
+}
+
+// The same merchant and value close enough in time is already there, the same value alone is only worth a flag
+fn find_duplicate(expenses: &[Expense], matched_expense_ids: &HashSet<String>, new_expense: &NewExpense, duplicate_days: u32) -> Option<(ImportStatus, String)> {
+    let candidates: Vec<&Expense> = expenses.iter()
+        .filter(|expense| !matched_expense_ids.contains(&expense.id))
+        .filter(|expense| expense.value == new_expense.value)
+        .filter(|expense| (expense.date - new_expense.date).num_days().abs() <= duplicate_days as i64)
+        .collect();
+    let external = normalise_external(&new_expense.external);
+    if let Some(expense) = candidates.iter().find(|expense| normalise_external(&expense.external) == external) {
+        return Some((ImportStatus::Skipped, expense.id.clone()));
+    }
+    return candidates.first().map(|expense| (ImportStatus::Flagged, expense.id.clone()));
+}
+
+// Rows earlier in the same statement are only flagged since a statement can repeat a purchase
+fn find_imported_duplicate(imported: &[(usize, Option<String>, NewExpense)], new_expense: &NewExpense, duplicate_days: u32) -> Option<(usize, Option<String>)> {
+    return imported.iter()
+        .find(|(_, _, imported_expense)| {
+            return imported_expense.value == new_expense.value
+                && (imported_expense.date - new_expense.date).num_days().abs() <= duplicate_days as i64;
+        })
+        .map(|(line, expense_id, _)| (*line, expense_id.clone()));
+}
+
+fn normalise_external(external: &str) -> String {
+    return external.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase();
+}
+
+// Quoted fields can hold the delimiter, newlines and doubled quotes
+fn parse_csv(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows: Vec<Vec<String>> = vec![];
+    let mut row: Vec<String> = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut characters = content.chars().peekable();
+    while let Some(character) = characters.next() {
+        if quoted {
+            if character == '"' {
+                if characters.peek() == Some(&'"') {
+                    field.push('"');
+                    characters.next();
+                } else {
+                    quoted = false;
+                }
+            } else {
+                field.push(character);
+            }
+        } else if character == '"' {
+            quoted = true;
+        } else if character == delimiter {
+            row.push(std::mem::take(&mut field));
+        } else if character == '\n' {
+            row.push(std::mem::take(&mut field));
+            rows.push(std::mem::take(&mut row));
+        } else if character != '\r' {
+            field.push(character);
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    return rows;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let content = "Date,Merchant,Amount\r\n01/01/2024,\"Shop, \"\"The\"\" One\",\"£1,250.00\"\r\n02/01/2024,\"Multi\nLine\",-3";
+        assert_eq!(vec![
+            vec!["Date".to_string(), "Merchant".to_string(), "Amount".to_string()],
+            vec!["01/01/2024".to_string(), "Shop, \"The\" One".to_string(), "£1,250.00".to_string()],
+            vec!["02/01/2024".to_string(), "Multi\nLine".to_string(), "-3".to_string()],
+        ], parse_csv(content, ','));
+        assert_eq!(Some(Decimal::new(125000, 2)), parse_value("£1,250.00"));
+        assert_eq!(Some(Decimal::new(-45, 1)), parse_value("(4.50)"));
+        assert_eq!(None, parse_value("n/a"));
+        assert_eq!(Some(Decimal::new(1234567, 0)), parse_value("1,234,567"));
+        assert_eq!(None, parse_value("1.234,56"));
+        assert_eq!(None, parse_value("1,5"));
+        assert_eq!(None, parse_value("1.234.567"));
+    }
+
+    #[test]
+    fn test_find_duplicate() {
+        let new_expense = |external: &str, day: u32, value: i64| NewExpense {
+            description: "".to_string(),
+            external: external.to_string(),
+            category: "UNKNOWN".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            value: Decimal::new(value, 2),
+            account_id: None,
+            transaction_id: None,
+            merchant_id: None,
+            kind: ExpenseKind::Purchase,
+            original_expense_id: None,
+            project_id: None,
+        };
+        let expenses = vec![
+            Expense { external: "TESCO  STORES".to_string(), date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(), ..Expense::for_test("tesco", "GROCERIES", Decimal::new(1250, 2)) },
+            Expense { external: "ALDI".to_string(), date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(), ..Expense::for_test("aldi", "GROCERIES", Decimal::new(999, 2)) },
+        ];
+
+        assert_eq!(Some((ImportStatus::Skipped, "tesco".to_string())), find_duplicate(&expenses, &HashSet::new(), &new_expense("Tesco Stores", 12, 1250), 2));
+        assert_eq!(Some((ImportStatus::Flagged, "tesco".to_string())), find_duplicate(&expenses, &HashSet::new(), &new_expense("Sainsburys", 12, 1250), 2));
+        assert_eq!(None, find_duplicate(&expenses, &HashSet::new(), &new_expense("Tesco Stores", 13, 1250), 2));
+        // Each existing expense only accounts for one row of the statement
+        assert_eq!(None, find_duplicate(&expenses, &HashSet::from(["aldi".to_string()]), &new_expense("ALDI", 10, 999), 2));
+
+        let imported = vec![(2, None, new_expense("LIDL", 20, 300))];
+        assert_eq!(Some((2, None)), find_imported_duplicate(&imported, &new_expense("LIDL", 21, 300), 2));
+        assert_eq!(None, find_imported_duplicate(&imported, &new_expense("LIDL", 25, 300), 2));
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::anyhow;
+use const_format::formatcp;
+use rusqlite::{params, Transaction};
+use uuid::Uuid;
+use crate::db::{list, single};
+use crate::expense_import::schema::{ImportProfile, NewImportProfile};
+
+const IMPORT_PROFILE_COLUMNS: &str = "id, name, delimiter, header, date_column, date_format, external_column, description_column, value_column, negate, duplicate_days";
+const IMPORT_PROFILE_SELECT: &str = formatcp!("SELECT {IMPORT_PROFILE_COLUMNS} FROM import_profile");
+const IMPORT_PROFILE_RETURNING: &str = formatcp!("RETURNING {IMPORT_PROFILE_COLUMNS}");
+const IMPORT_PROFILE_ORDERING: &str = "ORDER BY name, id ASC";
+
+pub fn create_import_profile(transaction: &Transaction, new_import_profile: NewImportProfile) -> anyhow::Result<Option<ImportProfile>> {
+    verify_delimiter(&new_import_profile.delimiter)?;
+    return single(
+        transaction,
+        formatcp!("INSERT INTO import_profile ({IMPORT_PROFILE_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11) {IMPORT_PROFILE_RETURNING}"),
+        params![
+            Uuid::new_v4().to_string(),
+            new_import_profile.name,
+            new_import_profile.delimiter,
+            new_import_profile.header as i32,
+            new_import_profile.date_column,
+            new_import_profile.date_format,
+            new_import_profile.external_column,
+            new_import_profile.description_column,
+            new_import_profile.value_column,
+            new_import_profile.negate as i32,
+            new_import_profile.duplicate_days,
+        ],
+    );
+}
+
+pub fn update_import_profile(transaction: &Transaction, updated_import_profile: ImportProfile) -> anyhow::Result<Option<ImportProfile>> {
+    verify_delimiter(&updated_import_profile.delimiter)?;
+    return single(
+        transaction,
+        formatcp!("UPDATE import_profile SET name = ?1, delimiter = ?2, header = ?3, date_column = ?4, date_format = ?5, external_column = ?6, description_column = ?7, value_column = ?8, negate = ?9, duplicate_days = ?10 WHERE id = ?11 {IMPORT_PROFILE_RETURNING}"),
+        params![
+            updated_import_profile.name,
+            updated_import_profile.delimiter,
+            updated_import_profile.header as i32,
+            updated_import_profile.date_column,
+            updated_import_profile.date_format,
+            updated_import_profile.external_column,
+            updated_import_profile.description_column,
+            updated_import_profile.value_column,
+            updated_import_profile.negate as i32,
+            updated_import_profile.duplicate_days,
+            updated_import_profile.id,
+        ],
+    );
+}
+
+pub fn delete_import_profile(transaction: &Transaction, id: String) -> anyhow::Result<Option<ImportProfile>> {
+    return single(
+        transaction,
+        formatcp!("DELETE FROM import_profile WHERE id = ?1 {IMPORT_PROFILE_RETURNING}"),
+        [id],
+    );
+}
+
+pub fn get_import_profile(transaction: &Transaction, id: String) -> anyhow::Result<Option<ImportProfile>> {
+    return single(
+        transaction,
+        formatcp!("{IMPORT_PROFILE_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn list_import_profiles(transaction: &Transaction) -> anyhow::Result<Vec<ImportProfile>> {
+    return list(
+        transaction,
+        formatcp!("{IMPORT_PROFILE_SELECT} {IMPORT_PROFILE_ORDERING}"),
+        [],
+    );
+}
+
+fn verify_delimiter(delimiter: &str) -> anyhow::Result<()> {
+    if delimiter.chars().count() != 1 {
+        return Err(anyhow!("Delimiter {:?} must be a single character", delimiter));
+    }
+    return Ok(());
+}
@@ -0,0 +1,120 @@
+#![allow(unreachable_patterns)]
+
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use crate::db::FromRow;
+use crate::expense::schema::NewExpense;
+
+// How to read the statement of one bank, columns are counted from 0
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportProfile {
+    pub id: String,
+    pub name: String,
+    pub delimiter: String,
+    // Skip the first row
+    pub header: bool,
+    #[serde(rename = "dateColumn")]
+    pub date_column: usize,
+    // chrono format such as %d/%m/%Y
+    #[serde(rename = "dateFormat")]
+    pub date_format: String,
+    // Merchant column
+    #[serde(rename = "externalColumn")]
+    pub external_column: usize,
+    #[serde(rename = "descriptionColumn")]
+    pub description_column: Option<usize>,
+    #[serde(rename = "valueColumn")]
+    pub value_column: usize,
+    // For statements that show charges as negative
+    pub negate: bool,
+    // How many days apart an existing expense can be and still count as the same one
+    #[serde(rename = "duplicateDays")]
+    pub duplicate_days: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewImportProfile {
+    pub name: String,
+    pub delimiter: String,
+    pub header: bool,
+    #[serde(rename = "dateColumn")]
+    pub date_column: usize,
+    #[serde(rename = "dateFormat")]
+    pub date_format: String,
+    #[serde(rename = "externalColumn")]
+    pub external_column: usize,
+    #[serde(rename = "descriptionColumn")]
+    pub description_column: Option<usize>,
+    #[serde(rename = "valueColumn")]
+    pub value_column: usize,
+    pub negate: bool,
+    #[serde(rename = "duplicateDays")]
+    pub duplicate_days: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpenseImport {
+    #[serde(rename = "profileId")]
+    pub profile_id: String,
+    // The whole CSV file
+    pub content: String,
+    // Every imported expense is linked to this account
+    #[serde(rename = "accountId")]
+    pub account_id: Option<String>,
+    // Report what would happen without creating anything
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportedRow {
+    // Counted from 1 like a spreadsheet
+    pub line: usize,
+    pub status: ImportStatus,
+    // Categorised as it would be created, missing when the row couldn't be read
+    pub expense: Option<NewExpense>,
+    // Only set once created
+    #[serde(rename = "expenseId")]
+    pub expense_id: Option<String>,
+    // The existing expense this row skipped or was flagged against
+    #[serde(rename = "duplicateOf")]
+    pub duplicate_of: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Display, EnumString, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+pub enum ImportStatus {
+    #[serde(rename = "CREATED" )]
+    #[strum(serialize="CREATED", to_string="CREATED")]
+    Created,
+    // Created, but an existing expense has the same date and value under a different external
+    #[serde(rename = "FLAGGED" )]
+    #[strum(serialize="FLAGGED", to_string="FLAGGED")]
+    Flagged,
+    // Not created because it is already there or is a credit
+    #[serde(rename = "SKIPPED" )]
+    #[strum(serialize="SKIPPED", to_string="SKIPPED")]
+    Skipped,
+    #[serde(rename = "INVALID" )]
+    #[strum(serialize="INVALID", to_string="INVALID")]
+    Invalid
+}
+
+impl FromRow for ImportProfile {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ImportProfile {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            delimiter: row.get("delimiter")?,
+            header: row.get::<_, i32>("header")? != 0,
+            date_column: row.get("date_column")?,
+            date_format: row.get("date_format")?,
+            external_column: row.get("external_column")?,
+            description_column: row.get("description_column")?,
+            value_column: row.get("value_column")?,
+            negate: row.get::<_, i32>("negate")? != 0,
+            duplicate_days: row.get("duplicate_days")?,
+        })
+    }
+}
@@ -0,0 +1,85 @@
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use log::{error, info};
+use crate::expense_rule::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+
+#[post("/api/expense-rule/")]
+pub async fn create_expense_rule(db: web::Data<Pool>, new_expense_rule: web::Json<schema::NewExpenseRule>) -> Result<HttpResponse, Error> {
+    let new_expense_rule = new_expense_rule.into_inner();
+    info!("HTTP create_expense_rule new_expense_rule:[{:?}]", new_expense_rule);
+    do_in_transaction(&db, |transaction| db::create_expense_rule(transaction, new_expense_rule))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_expense_rule: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+
+#[post("/api/expense-rule/apply/")]
+pub async fn apply_expense_rules(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP apply_expense_rules");
+    do_in_transaction(&db, |transaction| db::apply_expense_rules(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP apply_expense_rules: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[post("/api/expense-rule/{id}/")]
+pub async fn update_expense_rule(db: web::Data<Pool>, path: web::Path<String>, updated_expense_rule: web::Json<schema::ExpenseRule>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let updated_expense_rule = updated_expense_rule.into_inner();
+    info!("HTTP update_expense_rule id:[{}] updated_expense_rule:[{:?}]", id, updated_expense_rule);
+    let option = crate::api::compare_ids(&id, &updated_expense_rule.id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::update_expense_rule(transaction, updated_expense_rule))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP update_expense_rule: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/expense-rule/{id}/")]
+pub async fn delete_expense_rule(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_expense_rule id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_expense_rule(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_expense_rule: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/expense-rule/{id}/")]
+pub async fn get_expense_rule(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_expense_rule id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_expense_rule(transaction, id))
+        .await.map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_expense_rule: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/expense-rule/")]
+pub async fn list_expense_rules(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_expense_rules");
+    do_in_transaction(&db, |transaction| db::list_expense_rules(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_expense_rules: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
\ No newline at end of file
@@ -0,0 +1,219 @@
+use anyhow::anyhow;
+use const_format::formatcp;
+use regex::Regex;
+use rusqlite::{params, Transaction};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+use crate::db::{list, single};
+use crate::expense::db::{list_expenses, update_expense};
+use crate::expense::schema::{Expense, NewExpense};
+use crate::expense_category::db::verify_expense_category_id_exists;
+use crate::expense_category::schema::UNKNOWN_EXPENSE_CATEGORY_ID;
+use crate::expense_rule::schema::{ExpenseRule, ExpenseRuleField, ExpenseRuleMatchType, NewExpenseRule};
+use crate::period_close::db::closed_through;
+
+const EXPENSE_RULE_COLUMNS: &str = "id, field, match_type, pattern, min_value, max_value, category_id, description, priority";
+const EXPENSE_RULE_SELECT: &str = formatcp!("SELECT {EXPENSE_RULE_COLUMNS} FROM expense_rule");
+const EXPENSE_RULE_RETURNING: &str = formatcp!("RETURNING {EXPENSE_RULE_COLUMNS}");
+const EXPENSE_RULE_ORDERING: &str = "ORDER BY priority, id ASC";
+
+pub fn create_expense_rule(transaction: &Transaction, new_expense_rule: NewExpenseRule) -> anyhow::Result<Option<ExpenseRule>> {
+    verify_expense_category_id_exists(transaction, new_expense_rule.category_id.clone())?;
+    verify_pattern(&new_expense_rule.match_type, &new_expense_rule.pattern)?;
+    return single(
+        transaction,
+        formatcp!("INSERT INTO expense_rule ({EXPENSE_RULE_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9) {EXPENSE_RULE_RETURNING}"),
+        params![
+            Uuid::new_v4().to_string(),
+            new_expense_rule.field.to_string(),
+            new_expense_rule.match_type.to_string(),
+            new_expense_rule.pattern,
+            new_expense_rule.min_value.map(|value| value.to_string()),
+            new_expense_rule.max_value.map(|value| value.to_string()),
+            new_expense_rule.category_id,
+            new_expense_rule.description,
+            new_expense_rule.priority,
+        ],
+    );
+}
+
+pub fn update_expense_rule(transaction: &Transaction, updated_expense_rule: ExpenseRule) -> anyhow::Result<Option<ExpenseRule>> {
+    verify_expense_category_id_exists(transaction, updated_expense_rule.category_id.clone())?;
+    verify_pattern(&updated_expense_rule.match_type, &updated_expense_rule.pattern)?;
+    return single(
+        transaction,
+        formatcp!("UPDATE expense_rule SET field = ?1, match_type = ?2, pattern = ?3, min_value = ?4, max_value = ?5, category_id = ?6, description = ?7, priority = ?8 WHERE id = ?9 {EXPENSE_RULE_RETURNING}"),
+        params![
+            updated_expense_rule.field.to_string(),
+            updated_expense_rule.match_type.to_string(),
+            updated_expense_rule.pattern,
+            updated_expense_rule.min_value.map(|value| value.to_string()),
+            updated_expense_rule.max_value.map(|value| value.to_string()),
+            updated_expense_rule.category_id,
+            updated_expense_rule.description,
+            updated_expense_rule.priority,
+            updated_expense_rule.id,
+        ],
+    );
+}
+
+pub fn delete_expense_rule(transaction: &Transaction, id: String) -> anyhow::Result<Option<ExpenseRule>> {
+    return single(
+        transaction,
+        formatcp!("DELETE FROM expense_rule WHERE id = ?1 {EXPENSE_RULE_RETURNING}"),
+        [id],
+    );
+}
+
+pub fn get_expense_rule(transaction: &Transaction, id: String) -> anyhow::Result<Option<ExpenseRule>> {
+    return single(
+        transaction,
+        formatcp!("{EXPENSE_RULE_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn list_expense_rules(transaction: &Transaction) -> anyhow::Result<Vec<ExpenseRule>> {
+    return list(
+        transaction,
+        formatcp!("{EXPENSE_RULE_SELECT} {EXPENSE_RULE_ORDERING}"),
+        [],
+    );
+}
+
+pub fn cascade_delete_expense_category(transaction: &Transaction, category_id: String) -> anyhow::Result<()> {
+    transaction.execute("DELETE FROM expense_rule WHERE category_id = ?1", [category_id])?;
+    return Ok(());
+}
+
+// Only expenses that haven't been categorised yet are touched so manual choices always win
+pub fn categorise_new_expense(transaction: &Transaction, new_expense: NewExpense) -> anyhow::Result<NewExpense> {
+    if new_expense.category != UNKNOWN_EXPENSE_CATEGORY_ID {
+        return Ok(new_expense);
+    }
+    let expense_rules = list_expense_rules(transaction)?;
+    return Ok(categorise_with_rules(&expense_rules, &compile_patterns(&expense_rules), new_expense));
+}
+
+// Lets callers categorising a batch of expenses load and compile the rules once
+pub fn categorise_with_rules(expense_rules: &[ExpenseRule], patterns: &[Option<Regex>], new_expense: NewExpense) -> NewExpense {
+    if new_expense.category != UNKNOWN_EXPENSE_CATEGORY_ID {
+        return new_expense;
+    }
+    return match find_matching_expense_rule(expense_rules, patterns, &new_expense.description, &new_expense.external, new_expense.value) {
+        Some((category_id, description)) => NewExpense { category: category_id, description, ..new_expense },
+        None => new_expense,
+    };
+}
+
+pub fn apply_expense_rules(transaction: &Transaction) -> anyhow::Result<Vec<Expense>> {
+    let expense_rules = list_expense_rules(transaction)?;
+    let patterns = compile_patterns(&expense_rules);
+    let mut updated_expenses: Vec<Expense> = vec![];
+    for expense in list_expenses(transaction)? {
+        if expense.category != UNKNOWN_EXPENSE_CATEGORY_ID {
+            continue;
+        }
+        // Expenses in closed periods are left uncategorised
+        let account_ids: Vec<String> = expense.account_id.clone().into_iter().collect();
+        if closed_through(transaction, &account_ids)?.is_some_and(|closed_through| expense.date <= closed_through) {
+            continue;
+        }
+        if let Some((category_id, description)) = find_matching_expense_rule(&expense_rules, &patterns, &expense.description, &expense.external, expense.value) {
+            let updated_expense = update_expense(transaction, Expense { category: category_id, description, ..expense })?;
+            updated_expenses.extend(updated_expense);
+        }
+    }
+    return Ok(updated_expenses);
+}
+
+// Compiled once per call rather than once per expense, Contains rules have no regex
+pub fn compile_patterns(expense_rules: &[ExpenseRule]) -> Vec<Option<Regex>> {
+    return expense_rules.iter()
+        .map(|expense_rule| match expense_rule.match_type {
+            ExpenseRuleMatchType::Contains => None,
+            ExpenseRuleMatchType::Regex => Regex::new(&expense_rule.pattern).ok(),
+        })
+        .collect();
+}
+
+// The category and description from the first rule that matches
+fn find_matching_expense_rule(expense_rules: &[ExpenseRule], patterns: &[Option<Regex>], description: &str, external: &str, value: Decimal) -> Option<(String, String)> {
+    for (expense_rule, pattern) in expense_rules.iter().zip(patterns) {
+        if expense_rule.min_value.is_some_and(|min_value| value < min_value) || expense_rule.max_value.is_some_and(|max_value| value > max_value) {
+            continue;
+        }
+        let field = match expense_rule.field {
+            ExpenseRuleField::Description => description,
+            ExpenseRuleField::External => external,
+        };
+        let rewritten = match expense_rule.match_type {
+            ExpenseRuleMatchType::Contains => {
+                if !field.to_lowercase().contains(&expense_rule.pattern.to_lowercase()) {
+                    continue;
+                }
+                expense_rule.description.clone()
+            }
+            ExpenseRuleMatchType::Regex => {
+                // Patterns are checked when saved so this only fails if the regex crate changes its mind
+                let Some(regex) = pattern else {
+                    continue;
+                };
+                let Some(captures) = regex.captures(field) else {
+                    continue;
+                };
+                expense_rule.description.as_ref().map(|replacement| {
+                    let mut rewritten = String::new();
+                    captures.expand(replacement, &mut rewritten);
+                    return rewritten;
+                })
+            }
+        };
+        return Some((expense_rule.category_id.clone(), rewritten.unwrap_or(description.to_string())));
+    }
+    return None;
+}
+
+fn verify_pattern(match_type: &ExpenseRuleMatchType, pattern: &str) -> anyhow::Result<()> {
+    if pattern.is_empty() {
+        return Err(anyhow!("Pattern cannot be empty"));
+    }
+    if *match_type == ExpenseRuleMatchType::Regex {
+        Regex::new(pattern).map_err(|err| anyhow!("Pattern {} is not a valid regex: {}", pattern, err))?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expense_rule(field: ExpenseRuleField, match_type: ExpenseRuleMatchType, pattern: &str, min_value: Option<i64>, category_id: &str, description: Option<&str>) -> ExpenseRule {
+        return ExpenseRule {
+            id: pattern.to_string(),
+            field,
+            match_type,
+            pattern: pattern.to_string(),
+            min_value: min_value.map(|min_value| Decimal::new(min_value, 0)),
+            max_value: None,
+            category_id: category_id.to_string(),
+            description: description.map(|description| description.to_string()),
+            priority: 0,
+        };
+    }
+
+    #[test]
+    fn test_find_matching_expense_rule() {
+        let expense_rules = vec![
+            expense_rule(ExpenseRuleField::External, ExpenseRuleMatchType::Contains, "tesco", Some(100), "HOUSEHOLD", None),
+            expense_rule(ExpenseRuleField::External, ExpenseRuleMatchType::Contains, "tesco", None, "GROCERIES", Some("Tesco")),
+            expense_rule(ExpenseRuleField::Description, ExpenseRuleMatchType::Regex, "^AMZN\\*(\\w+)", None, "SHOPPING", Some("Amazon $1")),
+        ];
+        let patterns = compile_patterns(&expense_rules);
+
+        assert_eq!(Some(("HOUSEHOLD".to_string(), "Card".to_string())), find_matching_expense_rule(&expense_rules, &patterns, "Card", "TESCO STORES 123", Decimal::new(150, 0)));
+        assert_eq!(Some(("GROCERIES".to_string(), "Tesco".to_string())), find_matching_expense_rule(&expense_rules, &patterns, "Card", "TESCO STORES 123", Decimal::new(20, 0)));
+        assert_eq!(Some(("SHOPPING".to_string(), "Amazon Books".to_string())), find_matching_expense_rule(&expense_rules, &patterns, "AMZN*Books 42", "", Decimal::new(20, 0)));
+        assert_eq!(None, find_matching_expense_rule(&expense_rules, &patterns, "Rent", "LANDLORD", Decimal::new(20, 0)));
+    }
+}
@@ -0,0 +1,100 @@
+#![allow(unreachable_patterns)]
+
+use std::str::FromStr;
+use rusqlite::Row;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use crate::db::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpenseRule {
+    pub id: String,
+    pub field: ExpenseRuleField,
+    #[serde(rename = "matchType")]
+    pub match_type: ExpenseRuleMatchType,
+    pub pattern: String,
+    // Inclusive bounds on the expense value, either can be left open
+    #[serde(rename = "minValue", with = "rust_decimal::serde::float_option", default)]
+    pub min_value: Option<Decimal>,
+    #[serde(rename = "maxValue", with = "rust_decimal::serde::float_option", default)]
+    pub max_value: Option<Decimal>,
+    #[serde(rename = "categoryId")]
+    pub category_id: String,
+    // Replaces the description when present, regex rules can refer to capture groups like $1
+    pub description: Option<String>,
+    // Lowest priority is tried first and the first matching rule wins
+    pub priority: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewExpenseRule {
+    pub field: ExpenseRuleField,
+    #[serde(rename = "matchType")]
+    pub match_type: ExpenseRuleMatchType,
+    pub pattern: String,
+    #[serde(rename = "minValue", with = "rust_decimal::serde::float_option", default)]
+    pub min_value: Option<Decimal>,
+    #[serde(rename = "maxValue", with = "rust_decimal::serde::float_option", default)]
+    pub max_value: Option<Decimal>,
+    #[serde(rename = "categoryId")]
+    pub category_id: String,
+    pub description: Option<String>,
+    pub priority: i32,
+}
+
+#[derive(Debug, Clone, Display, EnumString, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+pub enum ExpenseRuleField {
+    #[serde(rename = "DESCRIPTION" )]
+    #[strum(serialize="DESCRIPTION", to_string="DESCRIPTION")]
+    Description,
+    #[serde(rename = "EXTERNAL" )]
+    #[strum(serialize="EXTERNAL", to_string="EXTERNAL")]
+    External
+}
+
+#[derive(Debug, Clone, Display, EnumString, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+pub enum ExpenseRuleMatchType {
+    // Case insensitive substring
+    #[serde(rename = "CONTAINS" )]
+    #[strum(serialize="CONTAINS", to_string="CONTAINS")]
+    Contains,
+    #[serde(rename = "REGEX" )]
+    #[strum(serialize="REGEX", to_string="REGEX")]
+    Regex
+}
+
+impl FromRow for ExpenseRule {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ExpenseRule {
+            id: row.get("id")?,
+            field: row.get("field")?,
+            match_type: row.get("match_type")?,
+            pattern: row.get("pattern")?,
+            min_value: crate::db::get_optional_decimal(row, "min_value")?,
+            max_value: crate::db::get_optional_decimal(row, "max_value")?,
+            category_id: row.get("category_id")?,
+            description: row.get("description")?,
+            priority: row.get("priority")?,
+        })
+    }
+}
+
+impl FromSql for ExpenseRuleField {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str() {
+            Ok(value) => ExpenseRuleField::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
+            Err(err) => Err(err)
+        }
+    }
+}
+
+impl FromSql for ExpenseRuleMatchType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str() {
+            Ok(value) => ExpenseRuleMatchType::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
+            Err(err) => Err(err)
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use log::{error, info};
+use rust_decimal::Decimal;
+use crate::db::{do_in_transaction, Pool};
+use crate::expense::db::list_expenses;
+use crate::expense::schema::Expense;
+use crate::household::{db, schema};
+use crate::household::schema::{Debt, ExpenseSplit, HouseholdBalance, PersonBalance, Settlement, SplitType};
+use crate::person::db::list_persons;
+use crate::person::schema::Person;
+
+#[post("/api/expense/{id}/split/")]
+pub async fn set_expense_split(db: web::Data<Pool>, path: web::Path<String>, new_expense_split: web::Json<schema::NewExpenseSplit>) -> Result<HttpResponse, Error> {
+    let expense_id = path.clone();
+    let new_expense_split = new_expense_split.into_inner();
+    info!("HTTP set_expense_split expense_id:[{}] new_expense_split:[{:?}]", expense_id, new_expense_split);
+    do_in_transaction(&db, |transaction| db::set_expense_split(transaction, expense_id, new_expense_split))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP set_expense_split: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/expense/{id}/split/")]
+pub async fn delete_expense_split(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let expense_id = path.clone();
+    info!("HTTP delete_expense_split expense_id:[{}]", expense_id);
+    do_in_transaction(&db, |transaction| db::delete_expense_split(transaction, expense_id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_expense_split: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/expense/{id}/split/")]
+pub async fn get_expense_split(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let expense_id = path.clone();
+    info!("HTTP get_expense_split expense_id:[{}]", expense_id);
+    do_in_transaction(&db, |transaction| db::get_expense_split(transaction, expense_id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_expense_split: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[post("/api/household/settlement/")]
+pub async fn create_settlement(db: web::Data<Pool>, new_settlement: web::Json<schema::NewSettlement>) -> Result<HttpResponse, Error> {
+    let new_settlement = new_settlement.into_inner();
+    info!("HTTP create_settlement new_settlement:[{:?}]", new_settlement);
+    do_in_transaction(&db, |transaction| db::create_settlement(transaction, new_settlement))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_settlement: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/household/settlement/")]
+pub async fn list_settlements(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_settlements");
+    do_in_transaction(&db, |transaction| db::list_settlements(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_settlements: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/household/balance/")]
+pub async fn calculate_household_balance(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP calculate_household_balance");
+    do_in_transaction(&db, |transaction| {
+        let persons = list_persons(transaction)?;
+        let expenses = list_expenses(transaction)?;
+        let expense_splits = db::list_expense_splits(transaction)?;
+        let settlements = db::list_settlements(transaction)?;
+        return Ok((persons, expenses, expense_splits, settlements));
+    })
+        .await
+        .map(|(persons, expenses, expense_splits, settlements)| calculate_balance(&persons, &expenses, &expense_splits, &settlements))
+        .map(|household_balance| HttpResponse::Ok().json(household_balance))
+        .map_err(|err| {
+            error!("HTTP calculate_household_balance: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+// What each person owes towards an expense, rounding is given to the first share so the shares always add up
+pub fn calculate_shares(expense_split: &ExpenseSplit, value: Decimal) -> Vec<(String, Decimal)> {
+    if expense_split.shares.is_empty() {
+        return vec![];
+    }
+    let mut shares: Vec<(String, Decimal)> = expense_split.shares.iter()
+        .map(|expense_share| {
+            let share = match expense_split.split_type {
+                SplitType::Equal => value / Decimal::from(expense_split.shares.len()),
+                SplitType::Percentage => value * expense_share.value.unwrap_or(Decimal::ZERO) / Decimal::ONE_HUNDRED,
+                SplitType::Exact => expense_share.value.unwrap_or(Decimal::ZERO),
+            };
+            return (expense_share.person_id.clone(), share.round_dp(2));
+        })
+        .collect();
+    if expense_split.split_type != SplitType::Exact {
+        let remainder = value - shares.iter().map(|(_, share)| *share).sum::<Decimal>();
+        shares[0].1 += remainder;
+    }
+    return shares;
+}
+
+fn calculate_balance(persons: &[Person], expenses: &[Expense], expense_splits: &[ExpenseSplit], settlements: &[Settlement]) -> HouseholdBalance {
+    let value_by_expense_id: HashMap<&str, Decimal> = expenses.iter()
+        .map(|expense| (expense.id.as_str(), expense.value))
+        .collect();
+    let mut balance_by_person_id: BTreeMap<String, Decimal> = persons.iter()
+        .map(|person| (person.id.clone(), Decimal::ZERO))
+        .collect();
+    for expense_split in expense_splits {
+        let Some(value) = value_by_expense_id.get(expense_split.expense_id.as_str()) else {
+            continue;
+        };
+        *balance_by_person_id.entry(expense_split.paid_by.clone()).or_default() += *value;
+        for (person_id, share) in calculate_shares(expense_split, *value) {
+            *balance_by_person_id.entry(person_id).or_default() -= share;
+        }
+    }
+    for settlement in settlements {
+        *balance_by_person_id.entry(settlement.from_person_id.clone()).or_default() += settlement.value;
+        *balance_by_person_id.entry(settlement.to_person_id.clone()).or_default() -= settlement.value;
+    }
+
+    // Pair the largest debtor with the largest creditor until everyone is square
+    let mut debtors: Vec<(String, Decimal)> = balance_by_person_id.iter()
+        .filter(|(_, balance)| **balance < Decimal::ZERO)
+        .map(|(person_id, balance)| (person_id.clone(), balance.abs()))
+        .collect();
+    let mut creditors: Vec<(String, Decimal)> = balance_by_person_id.iter()
+        .filter(|(_, balance)| **balance > Decimal::ZERO)
+        .map(|(person_id, balance)| (person_id.clone(), *balance))
+        .collect();
+    debtors.sort_by_key(|debtor| Reverse(debtor.1));
+    creditors.sort_by_key(|creditor| Reverse(creditor.1));
+    let mut debts: Vec<Debt> = vec![];
+    let (mut debtor, mut creditor) = (0, 0);
+    while debtor < debtors.len() && creditor < creditors.len() {
+        let value = debtors[debtor].1.min(creditors[creditor].1);
+        debts.push(Debt {
+            from_person_id: debtors[debtor].0.clone(),
+            to_person_id: creditors[creditor].0.clone(),
+            value,
+        });
+        debtors[debtor].1 -= value;
+        creditors[creditor].1 -= value;
+        if debtors[debtor].1 == Decimal::ZERO {
+            debtor += 1;
+        }
+        if creditors[creditor].1 == Decimal::ZERO {
+            creditor += 1;
+        }
+    }
+
+    return HouseholdBalance {
+        balances: balance_by_person_id.into_iter()
+            .map(|(person_id, balance)| PersonBalance { person_id, balance })
+            .collect(),
+        debts,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::household::schema::ExpenseShare;
+
+    fn expense_split(split_type: SplitType, shares: Vec<(&str, Option<i64>)>) -> ExpenseSplit {
+        return ExpenseSplit {
+            expense_id: "expense".to_string(),
+            split_type,
+            paid_by: "me".to_string(),
+            shares: shares.into_iter()
+                .map(|(person_id, value)| ExpenseShare { person_id: person_id.to_string(), value: value.map(Decimal::from) })
+                .collect(),
+        };
+    }
+
+    #[test]
+    fn test_calculate_shares() {
+        let value = Decimal::new(10000, 2);
+        assert_eq!(
+            vec![("me".to_string(), Decimal::new(3334, 2)), ("partner".to_string(), Decimal::new(3333, 2)), ("flatmate".to_string(), Decimal::new(3333, 2))],
+            calculate_shares(&expense_split(SplitType::Equal, vec![("me", None), ("partner", None), ("flatmate", None)]), value),
+        );
+        assert_eq!(
+            vec![("me".to_string(), Decimal::new(25, 0)), ("partner".to_string(), Decimal::new(75, 0))],
+            calculate_shares(&expense_split(SplitType::Percentage, vec![("me", Some(25)), ("partner", Some(75))]), value),
+        );
+        assert_eq!(
+            vec![("me".to_string(), Decimal::new(90, 0)), ("partner".to_string(), Decimal::new(10, 0))],
+            calculate_shares(&expense_split(SplitType::Exact, vec![("me", Some(90)), ("partner", Some(10))]), value),
+        );
+    }
+}
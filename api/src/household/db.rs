@@ -0,0 +1,209 @@
+use std::collections::{HashMap, HashSet};
+use anyhow::anyhow;
+use const_format::formatcp;
+use rusqlite::{params, Transaction};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+use crate::audit::db::{record_create, record_delete, record_update};
+use crate::audit::schema::AuditEntityType;
+use crate::db::{list, single};
+use crate::expense::db::get_expense;
+use crate::household::schema::{ExpenseShare, ExpenseShareRow, ExpenseSplit, ExpenseSplitRow, NewExpenseSplit, NewSettlement, Settlement, SplitType};
+use crate::period_close::db::verify_open;
+use crate::person::db::{get_person, verify_person_id_exists};
+use crate::transaction::db::create_transaction;
+use crate::transaction::schema::{NewTransaction, TransactionType};
+
+const SETTLEMENT_COLUMNS: &str = "id, from_person_id, to_person_id, date, value, transaction_id";
+const SETTLEMENT_SELECT: &str = formatcp!("SELECT {SETTLEMENT_COLUMNS} FROM settlement");
+const SETTLEMENT_RETURNING: &str = formatcp!("RETURNING {SETTLEMENT_COLUMNS}");
+const SETTLEMENT_ORDERING: &str = "ORDER BY date, id ASC";
+
+// Replaces any existing split for the expense
+pub fn set_expense_split(transaction: &Transaction, expense_id: String, new_expense_split: NewExpenseSplit) -> anyhow::Result<Option<ExpenseSplit>> {
+    let Some(expense) = get_expense(transaction, expense_id.clone())? else {
+        return Ok(None);
+    };
+    verify_open(transaction, expense.date, &expense.account_id.into_iter().collect::<Vec<String>>())?;
+    verify_split(transaction, &new_expense_split, expense.value)?;
+    let previous_expense_split = remove_expense_split(transaction, expense_id.clone())?;
+    transaction.execute(
+        "INSERT INTO expense_split (expense_id, type, paid_by) VALUES (?1, ?2, ?3)",
+        [expense_id.clone(), new_expense_split.split_type.to_string(), new_expense_split.paid_by],
+    )?;
+    for expense_share in new_expense_split.shares {
+        transaction.execute(
+            "INSERT INTO expense_split_share (expense_id, person_id, value) VALUES (?1, ?2, ?3)",
+            params![expense_id.clone(), expense_share.person_id, expense_share.value.map(|value| value.to_string())],
+        )?;
+    }
+    let expense_split = get_expense_split(transaction, expense_id.clone())?;
+    if let Some(expense_split) = &expense_split {
+        match &previous_expense_split {
+            Some(previous_expense_split) => record_update(transaction, AuditEntityType::ExpenseSplit, &expense_id, Some(previous_expense_split), expense_split)?,
+            None => record_create(transaction, AuditEntityType::ExpenseSplit, &expense_id, expense_split)?,
+        }
+    }
+    return Ok(expense_split);
+}
+
+pub fn delete_expense_split(transaction: &Transaction, expense_id: String) -> anyhow::Result<Option<ExpenseSplit>> {
+    if let Some(expense) = get_expense(transaction, expense_id.clone())? {
+        verify_open(transaction, expense.date, &expense.account_id.into_iter().collect::<Vec<String>>())?;
+    }
+    let expense_split = remove_expense_split(transaction, expense_id.clone())?;
+    if let Some(expense_split) = &expense_split {
+        record_delete(transaction, AuditEntityType::ExpenseSplit, &expense_id, expense_split)?;
+    }
+    return Ok(expense_split);
+}
+
+// Exact shares have to keep adding up to the expense when its value changes
+pub fn verify_split_value(transaction: &Transaction, expense_id: String, value: Decimal) -> anyhow::Result<()> {
+    let Some(expense_split) = get_expense_split(transaction, expense_id.clone())? else {
+        return Ok(());
+    };
+    let total: Decimal = expense_split.shares.iter().filter_map(|expense_share| expense_share.value).sum();
+    if expense_split.split_type == SplitType::Exact && total != value {
+        return Err(anyhow!("Expense {} has exact shares adding up to {}, change its split before its value", expense_id, total));
+    }
+    return Ok(());
+}
+
+pub fn get_expense_split(transaction: &Transaction, expense_id: String) -> anyhow::Result<Option<ExpenseSplit>> {
+    let expense_split_rows: Vec<ExpenseSplitRow> = list(transaction, "SELECT expense_id, type, paid_by FROM expense_split WHERE expense_id = ?1", [expense_id.clone()])?;
+    let expense_share_rows: Vec<ExpenseShareRow> = list(transaction, "SELECT expense_id, person_id, value FROM expense_split_share WHERE expense_id = ?1 ORDER BY person_id ASC", [expense_id])?;
+    return Ok(join_expense_splits(expense_split_rows, expense_share_rows).pop());
+}
+
+pub fn list_expense_splits(transaction: &Transaction) -> anyhow::Result<Vec<ExpenseSplit>> {
+    let expense_split_rows: Vec<ExpenseSplitRow> = list(transaction, "SELECT expense_id, type, paid_by FROM expense_split ORDER BY expense_id ASC", [])?;
+    let expense_share_rows: Vec<ExpenseShareRow> = list(transaction, "SELECT expense_id, person_id, value FROM expense_split_share ORDER BY expense_id, person_id ASC", [])?;
+    return Ok(join_expense_splits(expense_split_rows, expense_share_rows));
+}
+
+// Records a transfer between the accounts of the two people along with the settlement
+pub fn create_settlement(transaction: &Transaction, new_settlement: NewSettlement) -> anyhow::Result<Option<Settlement>> {
+    if new_settlement.from_person_id == new_settlement.to_person_id {
+        return Err(anyhow!("A person cannot settle with themselves"));
+    }
+    if new_settlement.value <= Decimal::ZERO {
+        return Err(anyhow!("Settlement value must be positive"));
+    }
+    let from_person = get_person(transaction, new_settlement.from_person_id.clone())?
+        .ok_or(anyhow!("Person {} does not exist", new_settlement.from_person_id))?;
+    let to_person = get_person(transaction, new_settlement.to_person_id.clone())?
+        .ok_or(anyhow!("Person {} does not exist", new_settlement.to_person_id))?;
+    let from_account_id = from_person.account_id.clone()
+        .ok_or(anyhow!("Person {} has no account to settle from", from_person.name))?;
+    let to_account_id = to_person.account_id.clone()
+        .ok_or(anyhow!("Person {} has no account to settle to", to_person.name))?;
+    let transfer = create_transaction(transaction, NewTransaction {
+        description: format!("Settle up {} to {}", from_person.name, to_person.name),
+        date: new_settlement.date,
+        value: new_settlement.value,
+        transaction_type: TransactionType::Transfer,
+        account_id: to_account_id,
+        from_account_id: Some(from_account_id),
+    })?;
+    return single(
+        transaction,
+        formatcp!("INSERT INTO settlement ({SETTLEMENT_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6) {SETTLEMENT_RETURNING}"),
+        params![Uuid::new_v4().to_string(), new_settlement.from_person_id, new_settlement.to_person_id, new_settlement.date.to_string(), new_settlement.value.round_dp(2).to_string(), transfer.map(|transfer| transfer.id)],
+    );
+}
+
+pub fn list_settlements(transaction: &Transaction) -> anyhow::Result<Vec<Settlement>> {
+    return list(
+        transaction,
+        formatcp!("{SETTLEMENT_SELECT} {SETTLEMENT_ORDERING}"),
+        [],
+    );
+}
+
+pub fn verify_person_unused(transaction: &Transaction, person_id: String) -> anyhow::Result<()> {
+    let used: i64 = transaction.query_row(
+        "SELECT (SELECT COUNT(*) FROM expense_split WHERE paid_by = ?1) + (SELECT COUNT(*) FROM expense_split_share WHERE person_id = ?1) + (SELECT COUNT(*) FROM settlement WHERE from_person_id = ?1 OR to_person_id = ?1)",
+        [person_id.clone()],
+        |row| row.get(0),
+    )?;
+    if used != 0 {
+        return Err(anyhow!("Person {} is used by splits or settlements", person_id));
+    }
+    return Ok(());
+}
+
+pub fn cascade_delete_expense(transaction: &Transaction, expense_id: String) -> anyhow::Result<()> {
+    remove_expense_split(transaction, expense_id)?;
+    return Ok(());
+}
+
+// Settlements are kept without their transfer so balances don't change
+pub fn cascade_delete_account(transaction: &Transaction, account_id: String) -> anyhow::Result<()> {
+    transaction.execute(
+        "UPDATE settlement SET transaction_id = NULL WHERE transaction_id IN (SELECT id FROM account_transaction WHERE account_id = ?1 OR from_account_id = ?1)",
+        [account_id],
+    )?;
+    return Ok(());
+}
+
+pub fn cascade_delete_transaction(transaction: &Transaction, transaction_id: String) -> anyhow::Result<()> {
+    transaction.execute("UPDATE settlement SET transaction_id = NULL WHERE transaction_id = ?1", [transaction_id])?;
+    return Ok(());
+}
+
+pub fn cascade_merge_transaction(transaction: &Transaction, transaction_id: String, target_transaction_id: String) -> anyhow::Result<()> {
+    transaction.execute("UPDATE settlement SET transaction_id = ?2 WHERE transaction_id = ?1", [transaction_id, target_transaction_id])?;
+    return Ok(());
+}
+
+fn remove_expense_split(transaction: &Transaction, expense_id: String) -> anyhow::Result<Option<ExpenseSplit>> {
+    let expense_split = get_expense_split(transaction, expense_id.clone())?;
+    if expense_split.is_some() {
+        transaction.execute("DELETE FROM expense_split_share WHERE expense_id = ?1", [expense_id.clone()])?;
+        transaction.execute("DELETE FROM expense_split WHERE expense_id = ?1", [expense_id])?;
+    }
+    return Ok(expense_split);
+}
+
+fn join_expense_splits(expense_split_rows: Vec<ExpenseSplitRow>, expense_share_rows: Vec<ExpenseShareRow>) -> Vec<ExpenseSplit> {
+    let mut shares_by_expense_id: HashMap<String, Vec<ExpenseShare>> = HashMap::new();
+    for expense_share_row in expense_share_rows {
+        shares_by_expense_id.entry(expense_share_row.expense_id).or_default().push(ExpenseShare {
+            person_id: expense_share_row.person_id,
+            value: expense_share_row.value,
+        });
+    }
+    return expense_split_rows.into_iter()
+        .map(|expense_split_row| ExpenseSplit {
+            shares: shares_by_expense_id.remove(&expense_split_row.expense_id).unwrap_or_default(),
+            expense_id: expense_split_row.expense_id,
+            split_type: expense_split_row.split_type,
+            paid_by: expense_split_row.paid_by,
+        })
+        .collect();
+}
+
+fn verify_split(transaction: &Transaction, new_expense_split: &NewExpenseSplit, expense_value: Decimal) -> anyhow::Result<()> {
+    verify_person_id_exists(transaction, new_expense_split.paid_by.clone())?;
+    if new_expense_split.shares.is_empty() {
+        return Err(anyhow!("A split needs at least one share"));
+    }
+    let mut person_ids: HashSet<String> = HashSet::new();
+    for expense_share in &new_expense_split.shares {
+        verify_person_id_exists(transaction, expense_share.person_id.clone())?;
+        if !person_ids.insert(expense_share.person_id.clone()) {
+            return Err(anyhow!("Person {} has more than one share", expense_share.person_id));
+        }
+        if new_expense_split.split_type != SplitType::Equal && expense_share.value.is_none() {
+            return Err(anyhow!("Person {} has no share value", expense_share.person_id));
+        }
+    }
+    let total: Decimal = new_expense_split.shares.iter().filter_map(|expense_share| expense_share.value).sum();
+    return match new_expense_split.split_type {
+        SplitType::Equal => Ok(()),
+        SplitType::Percentage if total != Decimal::ONE_HUNDRED => Err(anyhow!("Percentages add up to {} not 100", total)),
+        SplitType::Exact if total != expense_value => Err(anyhow!("Shares add up to {} not {}", total, expense_value)),
+        _ => Ok(()),
+    };
+}
@@ -0,0 +1,159 @@
+#![allow(unreachable_patterns)]
+
+use std::str::FromStr;
+use chrono::NaiveDate;
+use rusqlite::Row;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use crate::db::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpenseSplit {
+    #[serde(rename = "expenseId")]
+    pub expense_id: String,
+    #[serde(rename = "type")]
+    pub split_type: SplitType,
+    // Person who paid the whole expense
+    #[serde(rename = "paidBy")]
+    pub paid_by: String,
+    pub shares: Vec<ExpenseShare>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewExpenseSplit {
+    #[serde(rename = "type")]
+    pub split_type: SplitType,
+    #[serde(rename = "paidBy")]
+    pub paid_by: String,
+    pub shares: Vec<ExpenseShare>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpenseShare {
+    #[serde(rename = "personId")]
+    pub person_id: String,
+    // Unused for equal splits, a percentage for percentage splits and an amount for exact splits
+    #[serde(with = "rust_decimal::serde::float_option", default)]
+    pub value: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Display, EnumString, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+pub enum SplitType {
+    #[serde(rename = "EQUAL" )]
+    #[strum(serialize="EQUAL", to_string="EQUAL")]
+    Equal,
+    #[serde(rename = "PERCENTAGE" )]
+    #[strum(serialize="PERCENTAGE", to_string="PERCENTAGE")]
+    Percentage,
+    #[serde(rename = "EXACT" )]
+    #[strum(serialize="EXACT", to_string="EXACT")]
+    Exact
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Settlement {
+    pub id: String,
+    #[serde(rename = "fromPersonId")]
+    pub from_person_id: String,
+    #[serde(rename = "toPersonId")]
+    pub to_person_id: String,
+    pub date: NaiveDate,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    // The transfer recording the settlement, cleared if the transfer is deleted
+    #[serde(rename = "transactionId")]
+    pub transaction_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewSettlement {
+    #[serde(rename = "fromPersonId")]
+    pub from_person_id: String,
+    #[serde(rename = "toPersonId")]
+    pub to_person_id: String,
+    pub date: NaiveDate,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HouseholdBalance {
+    pub balances: Vec<PersonBalance>,
+    pub debts: Vec<Debt>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PersonBalance {
+    #[serde(rename = "personId")]
+    pub person_id: String,
+    // Positive when the person is owed money
+    #[serde(with = "rust_decimal::serde::float")]
+    pub balance: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Debt {
+    #[serde(rename = "fromPersonId")]
+    pub from_person_id: String,
+    #[serde(rename = "toPersonId")]
+    pub to_person_id: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+}
+
+// Split rows before their shares are attached
+pub struct ExpenseSplitRow {
+    pub expense_id: String,
+    pub split_type: SplitType,
+    pub paid_by: String,
+}
+
+pub struct ExpenseShareRow {
+    pub expense_id: String,
+    pub person_id: String,
+    pub value: Option<Decimal>,
+}
+
+impl FromRow for ExpenseSplitRow {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ExpenseSplitRow {
+            expense_id: row.get("expense_id")?,
+            split_type: row.get("type")?,
+            paid_by: row.get("paid_by")?,
+        })
+    }
+}
+
+impl FromRow for ExpenseShareRow {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ExpenseShareRow {
+            expense_id: row.get("expense_id")?,
+            person_id: row.get("person_id")?,
+            value: crate::db::get_optional_decimal(row, "value")?,
+        })
+    }
+}
+
+impl FromRow for Settlement {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Settlement {
+            id: row.get("id")?,
+            from_person_id: row.get("from_person_id")?,
+            to_person_id: row.get("to_person_id")?,
+            date: crate::db::get_naive_date(row, "date")?,
+            value: crate::db::get_decimal(row, "value")?,
+            transaction_id: row.get("transaction_id")?,
+        })
+    }
+}
+
+impl FromSql for SplitType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str() {
+            Ok(value) => SplitType::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
+            Err(err) => Err(err)
+        }
+    }
+}
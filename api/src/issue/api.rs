@@ -1,13 +1,26 @@
 use std::cmp::Ordering::{Equal, Greater, Less};
 use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 use actix_web::{Error, error, get, HttpResponse, web};
 use chrono::{Datelike, Days, Local, Months, NaiveDate};
 use log::{error, info};
+use rust_decimal::Decimal;
 use crate::account::db::list_accounts;
 use crate::account::schema::Account;
+use crate::budget::api::calculate_budget_variances;
+use crate::budget::db::list_budgets;
+use crate::budget::schema::Budget;
+use crate::credit_card::api::{calculate_credit_card_issues, DEFAULT_CREDIT_UTILISATION_THRESHOLD};
+use crate::credit_card::db::list_credit_cards;
 use crate::db::{do_in_transaction, Pool};
+use crate::expense::db::list_expenses;
+use crate::expense::schema::Expense;
+use crate::expense_category::db::list_expense_categories;
+use crate::expense_category::schema::ExpenseCategory;
 use crate::issue::schema::{Issue, IssueType};
 use crate::setting::db::{get_setting_by_key};
+use crate::subscription::api::calculate_subscription_issues;
+use crate::subscription::db::list_subscriptions;
 use crate::setting::schema::{DateRepeat, RepeatingTransfer, SettingKey};
 use crate::transaction::db::list_transactions;
 use crate::transaction::schema::{Transaction, TransactionType};
@@ -24,10 +37,23 @@ pub async fn list_issues(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
         let accounts: HashMap<String, Account> = list_accounts(transaction)?.iter()
             .map(|account| (account.id.clone(), account.clone()))
             .collect();
-        return Ok((transactions, repeating_transfers, accounts))
+        let budgets = list_budgets(transaction)?;
+        let expenses = list_expenses(transaction)?;
+        let expense_categories = list_expense_categories(transaction)?;
+        let subscriptions = list_subscriptions(transaction)?;
+        let credit_cards = list_credit_cards(transaction)?;
+        let credit_utilisation_threshold = match get_setting_by_key(transaction, SettingKey::CreditUtilisationThreshold)? {
+            // A bad threshold shouldn't hide every other issue
+            Some(setting) => Decimal::from_str(&setting.value).unwrap_or_else(|err| {
+                error!("Ignoring credit utilisation threshold [{}]: [{err}]", setting.value);
+                return DEFAULT_CREDIT_UTILISATION_THRESHOLD;
+            }),
+            None => DEFAULT_CREDIT_UTILISATION_THRESHOLD,
+        };
+        return Ok((transactions, repeating_transfers, accounts, budgets, expenses, expense_categories, subscriptions, (credit_cards, credit_utilisation_threshold)))
     })
         .await
-        .map(|(transactions, repeating_transfers, accounts)| {
+        .map(|(transactions, repeating_transfers, accounts, budgets, expenses, expense_categories, subscriptions, (credit_cards, credit_utilisation_threshold))| {
             let mut issues: Vec<Issue> = vec![];
             let mut account_ids_with_transfers_by_date: HashMap<NaiveDate, HashSet<String>> = HashMap::new();
             let mut dates_with_balances_by_account_ids: HashMap<String, HashSet<NaiveDate>> = HashMap::new();
@@ -55,7 +81,10 @@ pub async fn list_issues(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
 
             calculate_transfer_without_balances(&accounts, &mut issues, account_ids_with_transfers_by_date, &mut dates_with_balances_by_account_ids);
             calculate_no_balances(&accounts, &mut issues, dates_with_balances_by_account_ids);
+            issues.extend(calculate_credit_card_issues(&credit_cards, &transactions, credit_utilisation_threshold, Local::now().date_naive()));
             calculate_no_transfers(transactions, repeating_transfers, &mut issues);
+            calculate_over_budgets(&budgets, &expenses, &expense_categories, &mut issues);
+            issues.extend(calculate_subscription_issues(&subscriptions, &expenses, Local::now().date_naive()));
 
             sort_issues(accounts, &mut issues);
             HttpResponse::Ok().json(issues)
@@ -84,7 +113,26 @@ fn calculate_no_transfers(transactions: Vec<Transaction>, repeating_transfers: V
                 issue_type: IssueType::NoTransfer,
                 date: Some(date),
                 account_id: Some(account_id),
-                from_account_id: Some(repeating_transfer.from_account_id.clone())
+                from_account_id: Some(repeating_transfer.from_account_id.clone()),
+                category_id: None,
+                subscription_id: None
+            })
+        }
+    }
+}
+
+fn calculate_over_budgets(budgets: &[Budget], expenses: &[Expense], expense_categories: &[ExpenseCategory], issues: &mut Vec<Issue>) {
+    // Only the period in progress, earlier periods can't be brought back under budget
+    let today = Local::now().date_naive();
+    for budget_variance in calculate_budget_variances(budgets, expenses, expense_categories, today) {
+        if budget_variance.start <= today && today <= budget_variance.end && budget_variance.remaining < Decimal::ZERO {
+            issues.push(Issue {
+                issue_type: IssueType::OverBudget,
+                date: Some(budget_variance.start),
+                account_id: None,
+                from_account_id: None,
+                category_id: Some(budget_variance.category_id),
+                subscription_id: None
             })
         }
     }
@@ -128,7 +176,9 @@ fn calculate_transfer_without_balances(accounts: &HashMap<String, Account>, issu
                         issue_type: IssueType::TransferWithoutBalance,
                         account_id: Some(account_id.clone()),
                         date: Some(date.clone()),
-                        from_account_id: None
+                        from_account_id: None,
+                        category_id: None,
+                        subscription_id: None
                     })
                 } else {
                     let set = dates_with_balances.unwrap();
@@ -137,7 +187,9 @@ fn calculate_transfer_without_balances(accounts: &HashMap<String, Account>, issu
                             issue_type: IssueType::TransferWithoutBalance,
                             account_id: Some(account_id.clone()),
                             date: Some(date.clone()),
-                            from_account_id: None
+                            from_account_id: None,
+                            category_id: None,
+                            subscription_id: None
                         })
                     }
                 }
@@ -158,7 +210,9 @@ fn calculate_no_balances(accounts: &HashMap<String, Account>, issues: &mut Vec<I
                     issue_type: IssueType::NoBalance,
                     account_id: Some(account_id.clone()),
                     date: Some(first_day_of_this_month.clone()),
-                    from_account_id: None
+                    from_account_id: None,
+                    category_id: None,
+                    subscription_id: None
                 })
             } else {
                 let set = dates_with_balances.unwrap();
@@ -167,7 +221,9 @@ fn calculate_no_balances(accounts: &HashMap<String, Account>, issues: &mut Vec<I
                         issue_type: IssueType::NoBalance,
                         account_id: Some(account_id.clone()),
                         date: Some(first_day_of_this_month.clone()),
-                        from_account_id: None
+                        from_account_id: None,
+                        category_id: None,
+                        subscription_id: None
                     })
                 }
             }
@@ -14,6 +14,10 @@ pub struct Issue {
     pub account_id: Option<String>,
     #[serde(rename = "fromAccountId")]
     pub from_account_id: Option<String>,
+    #[serde(rename = "categoryId")]
+    pub category_id: Option<String>,
+    #[serde(rename = "subscriptionId")]
+    pub subscription_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Display, EnumString, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
@@ -26,5 +30,24 @@ pub enum IssueType {
     NoBalance,
     #[serde(rename = "NO_TRANSFER" )]
     #[strum(serialize="NO_TRANSFER", to_string="NO_TRANSFER")]
-    NoTransfer
+    NoTransfer,
+    #[serde(rename = "OVER_BUDGET" )]
+    #[strum(serialize="OVER_BUDGET", to_string="OVER_BUDGET")]
+    OverBudget,
+    #[serde(rename = "SUBSCRIPTION_MISSING" )]
+    #[strum(serialize="SUBSCRIPTION_MISSING", to_string="SUBSCRIPTION_MISSING")]
+    SubscriptionMissing,
+    #[serde(rename = "SUBSCRIPTION_LATE" )]
+    #[strum(serialize="SUBSCRIPTION_LATE", to_string="SUBSCRIPTION_LATE")]
+    SubscriptionLate,
+    #[serde(rename = "SUBSCRIPTION_PRICE_INCREASE" )]
+    #[strum(serialize="SUBSCRIPTION_PRICE_INCREASE", to_string="SUBSCRIPTION_PRICE_INCREASE")]
+    SubscriptionPriceIncrease,
+    // No transfer into a credit card between its statement closing and its due date
+    #[serde(rename = "CREDIT_CARD_PAYMENT_MISSING" )]
+    #[strum(serialize="CREDIT_CARD_PAYMENT_MISSING", to_string="CREDIT_CARD_PAYMENT_MISSING")]
+    CreditCardPaymentMissing,
+    #[serde(rename = "CREDIT_UTILISATION_HIGH" )]
+    #[strum(serialize="CREDIT_UTILISATION_HIGH", to_string="CREDIT_UTILISATION_HIGH")]
+    CreditUtilisationHigh,
 }
\ No newline at end of file
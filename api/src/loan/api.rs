@@ -0,0 +1,283 @@
+use std::cmp::Ordering;
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use anyhow::anyhow;
+use chrono::{Months, NaiveDate};
+use log::{error, info};
+use rust_decimal::{Decimal, RoundingStrategy};
+use crate::loan::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+use crate::loan::schema::{Loan, LoanPayment, LoanSchedule, LoanScheduledPayment, MAX_TERM_MONTHS};
+use crate::transaction::db::list_account_transactions;
+use crate::transaction::schema::{Transaction, TransactionType};
+
+// Stops a schedule that barely covers the interest from running forever
+const MAX_PAYMENTS: usize = MAX_TERM_MONTHS as usize;
+
+#[post("/api/account/{id}/loan/")]
+pub async fn set_loan(db: web::Data<Pool>, path: web::Path<String>, loan: web::Json<schema::Loan>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let loan = loan.into_inner();
+    info!("HTTP set_loan id:[{}] loan:[{:?}]", id, loan);
+    let option = crate::api::compare_ids(&id, &loan.account_id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::set_loan(transaction, loan))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP set_loan: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/account/{id}/loan/")]
+pub async fn delete_loan(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_loan id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_loan(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_loan: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/account/{id}/loan/")]
+pub async fn get_loan(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_loan id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_loan(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_loan: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/account/{id}/loan/schedule/")]
+pub async fn get_loan_schedule(db: web::Data<Pool>, path: web::Path<String>, query: web::Query<schema::LoanScheduleQuery>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let extra = query.extra.unwrap_or(Decimal::ZERO);
+    info!("HTTP get_loan_schedule id:[{}] extra:[{}]", id, extra);
+    do_in_transaction(&db, move |transaction| {
+        if extra < Decimal::ZERO {
+            return Err(anyhow!("Extra payment cannot be negative"));
+        }
+        let Some(loan) = db::get_loan(transaction, id.clone())? else {
+            return Ok(None);
+        };
+        let transactions = list_account_transactions(transaction, id)?;
+        return calculate_schedule(&loan, &transactions, extra).map(Some);
+    })
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_loan_schedule: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/account/{id}/loan/payment/")]
+pub async fn list_loan_payments(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP list_loan_payments id:[{}]", id);
+    do_in_transaction(&db, move |transaction| {
+        let Some(loan) = db::get_loan(transaction, id.clone())? else {
+            return Ok(None);
+        };
+        let transactions = list_account_transactions(transaction, id)?;
+        return Ok(Some(split_payments(&loan, transactions)?));
+    })
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP list_loan_payments: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+// Projects from the latest recorded balance so the schedule follows what was actually paid
+fn calculate_schedule(loan: &Loan, transactions: &[Transaction], extra: Decimal) -> anyhow::Result<LoanSchedule> {
+    let (anchor_date, anchor_balance) = transactions.iter()
+        .filter(|transaction| transaction.transaction_type == TransactionType::Balance)
+        .max_by_key(|transaction| transaction.date)
+        .map(|transaction| (transaction.date, transaction.value.abs()))
+        .unwrap_or((loan_start(loan), loan.principal));
+    let payment = payment(loan)?;
+    let (payments, payoff_date) = project(loan, payment, extra, anchor_date, anchor_balance)?;
+    let (baseline_payments, baseline_payoff_date) = project(loan, payment, Decimal::ZERO, anchor_date, anchor_balance)?;
+    return Ok(LoanSchedule {
+        account_id: loan.account_id.clone(),
+        payment,
+        extra,
+        anchor_date,
+        anchor_balance,
+        total_interest: payments.iter().map(|payment| payment.interest).sum(),
+        payments,
+        payoff_date,
+        baseline_payoff_date,
+        baseline_total_interest: baseline_payments.iter().map(|payment| payment.interest).sum(),
+    });
+}
+
+// Interest on a scheduled payment is a month of interest on what is left
+fn project(loan: &Loan, payment: Decimal, extra: Decimal, anchor_date: NaiveDate, anchor_balance: Decimal) -> anyhow::Result<(Vec<LoanScheduledPayment>, Option<NaiveDate>)> {
+    let instalment = payment.checked_add(extra).ok_or(anyhow!("Extra payment is too large"))?;
+    let mut number: u32 = 1;
+    while payment_date(loan, number) <= anchor_date {
+        number += 1;
+    }
+    let mut balance = anchor_balance;
+    let mut payments: Vec<LoanScheduledPayment> = vec![];
+    while balance > Decimal::ZERO {
+        let interest = round(balance.checked_mul(monthly_rate(loan)).ok_or(anyhow!("Interest on {} is too large", balance))?);
+        if instalment <= interest || payments.len() == MAX_PAYMENTS {
+            return Ok((payments, None));
+        }
+        let principal = (instalment - interest).min(balance);
+        balance -= principal;
+        payments.push(LoanScheduledPayment {
+            number,
+            date: payment_date(loan, number),
+            payment: principal + interest,
+            principal,
+            interest,
+            balance,
+        });
+        number += 1;
+    }
+    let payoff_date = payments.last().map(|payment| payment.date).unwrap_or(anchor_date);
+    return Ok((payments, Some(payoff_date)));
+}
+
+// Interest builds up daily between payments, a recorded balance starts it again from that date
+fn split_payments(loan: &Loan, mut transactions: Vec<Transaction>) -> anyhow::Result<Vec<LoanPayment>> {
+    // Payments go before a balance on the same day since statements show the balance after them
+    transactions.sort_by(|one, two| {
+        let date = one.date.cmp(&two.date);
+        if date != Ordering::Equal {
+            return date;
+        }
+        return two.transaction_type.cmp(&one.transaction_type);
+    });
+    let too_large = |date: NaiveDate| anyhow!("Loan balance on {} is too large to calculate", date);
+    let mut balance = loan.principal;
+    let mut accrued_from = loan_start(loan);
+    let mut loan_payments: Vec<LoanPayment> = vec![];
+    for transaction in transactions {
+        if transaction.transaction_type == TransactionType::Balance {
+            balance = transaction.value.abs();
+            accrued_from = transaction.date;
+        } else if transaction.account_id == loan.account_id {
+            let days = Decimal::from((transaction.date - accrued_from).num_days().max(0));
+            let interest = balance.checked_mul(loan.interest_rate)
+                .and_then(|value| value.checked_div(Decimal::ONE_HUNDRED))
+                .and_then(|value| value.checked_mul(days))
+                .and_then(|value| value.checked_div(Decimal::from(365)))
+                .ok_or_else(|| too_large(transaction.date))?;
+            let interest = round(interest).min(transaction.value).max(Decimal::ZERO);
+            let principal = transaction.value - interest;
+            balance = balance.checked_sub(principal).ok_or_else(|| too_large(transaction.date))?;
+            accrued_from = transaction.date;
+            loan_payments.push(LoanPayment { transaction, principal, interest, balance });
+        } else {
+            // Money taken out of the loan is borrowed on top
+            balance = balance.checked_add(transaction.value).ok_or_else(|| too_large(transaction.date))?;
+        }
+    }
+    loan_payments.reverse();
+    return Ok(loan_payments);
+}
+
+// The usual fixed monthly payment that pays the loan off over its term
+fn payment(loan: &Loan) -> anyhow::Result<Decimal> {
+    if let Some(payment) = loan.payment {
+        return Ok(payment);
+    }
+    let rate = monthly_rate(loan);
+    let term = Decimal::from(loan.term_months);
+    if rate == Decimal::ZERO {
+        return Ok(round(loan.principal / term));
+    }
+    let too_large = || anyhow!("Payment for {}% over {} months is too large to calculate", loan.interest_rate, loan.term_months);
+    let mut growth = Decimal::ONE;
+    for _ in 0..loan.term_months {
+        growth = growth.checked_mul(Decimal::ONE + rate).ok_or_else(too_large)?;
+    }
+    let payment = loan.principal.checked_mul(rate)
+        .and_then(|value| value.checked_mul(growth))
+        .and_then(|value| value.checked_div(growth - Decimal::ONE))
+        .ok_or_else(too_large)?;
+    return Ok(round(payment));
+}
+
+fn monthly_rate(loan: &Loan) -> Decimal {
+    return loan.interest_rate / Decimal::ONE_HUNDRED / Decimal::from(12);
+}
+
+fn payment_date(loan: &Loan, number: u32) -> NaiveDate {
+    return loan.first_payment_date + Months::new(number - 1);
+}
+
+// The principal is borrowed a month before the first payment
+fn loan_start(loan: &Loan) -> NaiveDate {
+    return loan.first_payment_date - Months::new(1);
+}
+
+fn round(decimal: Decimal) -> Decimal {
+    return decimal.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use super::*;
+
+    fn loan(interest_rate: Decimal) -> Loan {
+        return Loan {
+            account_id: "loan".to_string(),
+            principal: Decimal::new(100000, 0),
+            interest_rate,
+            term_months: 360,
+            first_payment_date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+            payment: None,
+        };
+    }
+
+    #[test]
+    fn test_payment() {
+        assert_eq!(Decimal::new(59955, 2), payment(&loan(Decimal::new(6, 0))).unwrap());
+        assert_eq!(Decimal::new(27778, 2), payment(&loan(Decimal::ZERO)).unwrap());
+        assert_eq!(Decimal::new(700, 0), payment(&Loan { payment: Some(Decimal::new(700, 0)), ..loan(Decimal::new(6, 0)) }).unwrap());
+        assert!(payment(&Loan { term_months: MAX_TERM_MONTHS, ..loan(Decimal::new(1000, 0)) }).is_err());
+    }
+
+    #[test]
+    fn test_split_payments() {
+        let transaction = Transaction {
+            id: "payment".to_string(),
+            description: "".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+            value: Decimal::new(1000, 0),
+            transaction_type: TransactionType::Transfer,
+            account_id: "loan".to_string(),
+            from_account_id: Some("checking".to_string()),
+        };
+        let payments = split_payments(&loan(Decimal::new(6, 0)), vec![transaction.clone()]).unwrap();
+        assert_eq!((Decimal::new(50959, 2), Decimal::new(49041, 2)), (payments[0].interest, payments[0].principal));
+        let huge = Loan { principal: Decimal::from(10u64.pow(18)) * Decimal::from(10u64.pow(9)), ..loan(Decimal::ONE_HUNDRED) };
+        assert!(split_payments(&huge, vec![transaction]).is_err());
+    }
+
+    #[test]
+    fn test_payment_dates() {
+        let loan = loan(Decimal::new(6, 0));
+        assert_eq!(NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(), loan_start(&loan));
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(), payment_date(&loan, 2));
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(), payment_date(&loan, 3));
+    }
+}
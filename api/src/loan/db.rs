@@ -0,0 +1,86 @@
+use anyhow::anyhow;
+use const_format::formatcp;
+use rusqlite::{params, Transaction};
+use rust_decimal::Decimal;
+use crate::account::db::get_account;
+use crate::account::schema::AccountType;
+use crate::db::single;
+use crate::loan::schema::{Loan, MAX_TERM_MONTHS};
+
+const LOAN_COLUMNS: &str = "account_id, principal, interest_rate, term_months, first_payment_date, payment";
+const LOAN_SELECT: &str = formatcp!("SELECT {LOAN_COLUMNS} FROM loan");
+const LOAN_RETURNING: &str = formatcp!("RETURNING {LOAN_COLUMNS}");
+
+// Creates or replaces the loan details of a loan account
+pub fn set_loan(transaction: &Transaction, loan: Loan) -> anyhow::Result<Option<Loan>> {
+    let Some(account) = get_account(transaction, loan.account_id.clone())? else {
+        return Ok(None);
+    };
+    if account.account_type != AccountType::Loan {
+        return Err(anyhow!("Account {} is not a loan", account.id));
+    }
+    verify(&loan)?;
+    return single(
+        transaction,
+        formatcp!("INSERT INTO loan ({LOAN_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6) ON CONFLICT (account_id) DO UPDATE SET principal = ?2, interest_rate = ?3, term_months = ?4, first_payment_date = ?5, payment = ?6 {LOAN_RETURNING}"),
+        params![loan.account_id, loan.principal.to_string(), loan.interest_rate.normalize().to_string(), loan.term_months, loan.first_payment_date.to_string(), loan.payment.map(|payment| payment.to_string())],
+    );
+}
+
+pub fn delete_loan(transaction: &Transaction, account_id: String) -> anyhow::Result<Option<Loan>> {
+    return single(
+        transaction,
+        formatcp!("DELETE FROM loan WHERE account_id = ?1 {LOAN_RETURNING}"),
+        [account_id],
+    );
+}
+
+pub fn get_loan(transaction: &Transaction, account_id: String) -> anyhow::Result<Option<Loan>> {
+    if get_account(transaction, account_id.clone())?.is_none() {
+        return Ok(None);
+    }
+    return single(
+        transaction,
+        formatcp!("{LOAN_SELECT} WHERE account_id = ?1"),
+        [account_id],
+    );
+}
+
+// The loan can't be moved when the target already has its own or is not a loan account, so it is dropped
+pub fn find_merge_conflict(transaction: &Transaction, account_id: String, target_account_id: String) -> anyhow::Result<Option<Loan>> {
+    let loan: Option<Loan> = single(transaction, formatcp!("{LOAN_SELECT} WHERE account_id = ?1"), [account_id])?;
+    let target_account = get_account(transaction, target_account_id.clone())?;
+    let target_loan: Option<Loan> = single(transaction, formatcp!("{LOAN_SELECT} WHERE account_id = ?1"), [target_account_id])?;
+    if target_loan.is_none() && target_account.is_some_and(|target_account| target_account.account_type == AccountType::Loan) {
+        return Ok(None);
+    }
+    return Ok(loan);
+}
+
+pub fn cascade_merge_account(transaction: &Transaction, account_id: String, target_account_id: String) -> anyhow::Result<()> {
+    if find_merge_conflict(transaction, account_id.clone(), target_account_id.clone())?.is_none() {
+        transaction.execute("UPDATE loan SET account_id = ?2 WHERE account_id = ?1", [account_id, target_account_id])?;
+    }
+    return Ok(());
+}
+
+pub fn cascade_delete_account(transaction: &Transaction, account_id: String) -> anyhow::Result<()> {
+    transaction.execute("DELETE FROM loan WHERE account_id = ?1", [account_id])?;
+    return Ok(());
+}
+
+fn verify(loan: &Loan) -> anyhow::Result<()> {
+    if loan.principal <= Decimal::ZERO {
+        return Err(anyhow!("Principal must be positive"));
+    }
+    if loan.interest_rate < Decimal::ZERO {
+        return Err(anyhow!("Interest rate cannot be negative"));
+    }
+    if loan.term_months == 0 || loan.term_months > MAX_TERM_MONTHS {
+        return Err(anyhow!("Term must be between 1 and {} months", MAX_TERM_MONTHS));
+    }
+    if loan.payment.is_some_and(|payment| payment <= Decimal::ZERO) {
+        return Err(anyhow!("Payment must be positive"));
+    }
+    return Ok(());
+}
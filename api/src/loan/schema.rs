@@ -0,0 +1,106 @@
+use std::str::FromStr;
+use chrono::NaiveDate;
+use rusqlite::Row;
+use rusqlite::types::Type;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use crate::db::{FromRow, get_decimal, get_naive_date, get_optional_decimal};
+use crate::transaction::schema::Transaction;
+
+// A hundred years, which also bounds how far a schedule is projected
+pub const MAX_TERM_MONTHS: u32 = 1200;
+
+// Payments are monthly, starting on the first payment date
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Loan {
+    #[serde(rename = "accountId")]
+    pub account_id: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub principal: Decimal,
+    // Yearly percentage
+    #[serde(rename = "interestRate", with = "rust_decimal::serde::float")]
+    pub interest_rate: Decimal,
+    #[serde(rename = "termMonths")]
+    pub term_months: u32,
+    #[serde(rename = "firstPaymentDate")]
+    pub first_payment_date: NaiveDate,
+    // Worked out from the principal, interest rate and term when missing
+    #[serde(with = "rust_decimal::serde::float_option", default)]
+    pub payment: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LoanScheduleQuery {
+    // Paid on top of every remaining payment
+    pub extra: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LoanSchedule {
+    #[serde(rename = "accountId")]
+    pub account_id: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub payment: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub extra: Decimal,
+    // The latest recorded balance, or the principal before the first payment when there isn't one
+    #[serde(rename = "anchorDate")]
+    pub anchor_date: NaiveDate,
+    #[serde(rename = "anchorBalance", with = "rust_decimal::serde::float")]
+    pub anchor_balance: Decimal,
+    pub payments: Vec<LoanScheduledPayment>,
+    // Missing when the payments don't cover the interest
+    #[serde(rename = "payoffDate")]
+    pub payoff_date: Option<NaiveDate>,
+    #[serde(rename = "totalInterest", with = "rust_decimal::serde::float")]
+    pub total_interest: Decimal,
+    // The same schedule without the extra payments
+    #[serde(rename = "baselinePayoffDate")]
+    pub baseline_payoff_date: Option<NaiveDate>,
+    #[serde(rename = "baselineTotalInterest", with = "rust_decimal::serde::float")]
+    pub baseline_total_interest: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LoanScheduledPayment {
+    // Counted from 1 at the first payment date
+    pub number: u32,
+    pub date: NaiveDate,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub payment: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub principal: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub interest: Decimal,
+    // Left owing after the payment
+    #[serde(with = "rust_decimal::serde::float")]
+    pub balance: Decimal,
+}
+
+// A transfer into the loan account split into what paid off the loan and what paid the interest
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LoanPayment {
+    pub transaction: Transaction,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub principal: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub interest: Decimal,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub balance: Decimal,
+}
+
+impl FromRow for Loan {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        // Rates often have more than two decimal places so get_decimal can't be used
+        let interest_rate: String = row.get("interest_rate")?;
+        Ok(Loan {
+            account_id: row.get("account_id")?,
+            principal: get_decimal(row, "principal")?,
+            interest_rate: Decimal::from_str(&interest_rate)
+                .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::from(err)))?,
+            term_months: row.get("term_months")?,
+            first_payment_date: get_naive_date(row, "first_payment_date")?,
+            payment: get_optional_decimal(row, "payment")?,
+        })
+    }
+}
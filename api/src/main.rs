@@ -14,6 +14,25 @@ mod setting;
 mod issue;
 mod statistics;
 mod expense;
+mod account_group;
+mod sankey;
+mod expense_category;
+mod budget;
+mod expense_rule;
+mod expense_classifier;
+mod person;
+mod household;
+mod merchant;
+mod subscription;
+mod project;
+mod expense_import;
+mod tag;
+mod attachment;
+mod audit;
+mod trash;
+mod period_close;
+mod loan;
+mod credit_card;
 
 use db::{Pool};
 
@@ -26,6 +45,7 @@ macro_rules! app (
         .app_data(web::Data::new($pool.clone()))
         .wrap(middleware::Logger::default())
         .wrap(Cors::permissive())
+        .app_data(web::PayloadConfig::new(attachment::db::MAX_ATTACHMENT_SIZE))
 
         .service(setting::api::create_setting)
         .service(setting::api::update_setting)
@@ -36,9 +56,16 @@ macro_rules! app (
         .service(account::api::create_account)
         .service(account::api::update_account)
         .service(account::api::delete_account)
+        .service(account::api::merge_account)
         .service(account::api::get_account)
         .service(account::api::list_accounts)
 
+        .service(account_group::api::create_account_group)
+        .service(account_group::api::update_account_group)
+        .service(account_group::api::delete_account_group)
+        .service(account_group::api::get_account_group)
+        .service(account_group::api::list_account_groups)
+
         .service(transaction::api::list_account_transactions)
         .service(transaction::api::create_transaction)
         .service(transaction::api::update_transaction)
@@ -46,10 +73,112 @@ macro_rules! app (
         .service(transaction::api::get_transaction)
         .service(transaction::api::list_transactions)
 
-        .service(expense::api::list_expense_categories)
+        .service(expense_category::api::create_expense_category)
+        .service(expense_category::api::update_expense_category)
+        .service(expense_category::api::delete_expense_category)
+        .service(expense_category::api::get_expense_category)
+        .service(expense_category::api::list_expense_categories)
+
+        .service(expense_rule::api::create_expense_rule)
+        .service(expense_rule::api::apply_expense_rules)
+        .service(expense_rule::api::update_expense_rule)
+        .service(expense_rule::api::delete_expense_rule)
+        .service(expense_rule::api::get_expense_rule)
+        .service(expense_rule::api::list_expense_rules)
+
+        .service(budget::api::create_budget)
+        .service(budget::api::update_budget)
+        .service(budget::api::delete_budget)
+        .service(budget::api::list_budget_variances)
+        .service(budget::api::get_budget)
+        .service(budget::api::list_budgets)
+
+        .service(person::api::create_person)
+        .service(person::api::update_person)
+        .service(person::api::delete_person)
+        .service(person::api::get_person)
+        .service(person::api::list_persons)
+
+        .service(household::api::set_expense_split)
+        .service(household::api::delete_expense_split)
+        .service(household::api::get_expense_split)
+        .service(household::api::create_settlement)
+        .service(household::api::list_settlements)
+        .service(household::api::calculate_household_balance)
+
+        .service(merchant::api::create_merchant)
+        .service(merchant::api::merge_merchants)
+        .service(merchant::api::top_merchants)
+        .service(merchant::api::update_merchant)
+        .service(merchant::api::delete_merchant)
+        .service(merchant::api::get_merchant)
+        .service(merchant::api::list_merchants)
+
+        .service(subscription::api::create_subscription)
+        .service(subscription::api::list_subscription_proposals)
+        .service(subscription::api::update_subscription)
+        .service(subscription::api::delete_subscription)
+        .service(subscription::api::get_subscription)
+        .service(subscription::api::list_subscriptions)
+
+        .service(project::api::create_project)
+        .service(project::api::update_project)
+        .service(project::api::delete_project)
+        .service(project::api::get_project_summary)
+        .service(project::api::get_project)
+        .service(project::api::list_projects)
+
+        .service(expense_import::api::create_import_profile)
+        .service(expense_import::api::update_import_profile)
+        .service(expense_import::api::delete_import_profile)
+        .service(expense_import::api::get_import_profile)
+        .service(expense_import::api::list_import_profiles)
+
+        .service(tag::api::create_tag)
+        .service(tag::api::update_tag)
+        .service(tag::api::delete_tag)
+        .service(tag::api::get_tag)
+        .service(tag::api::list_tags)
+        .service(tag::api::set_expense_tags)
+        .service(tag::api::get_expense_tags)
+        .service(tag::api::set_transaction_tags)
+        .service(tag::api::get_transaction_tags)
+
+        .service(attachment::api::create_attachment)
+        .service(attachment::api::delete_attachment)
+        .service(attachment::api::download_attachment)
+        .service(attachment::api::get_attachment)
+        .service(attachment::api::list_attachments)
+
+        .service(audit::api::list_audit_entries)
+
+        .service(trash::api::list_trash_entries)
+        .service(trash::api::get_trash_entry)
+        .service(trash::api::restore_trash_entry)
+        .service(trash::api::purge_trash_entry)
+
+        .service(period_close::api::create_period_close)
+        .service(period_close::api::delete_period_close)
+        .service(period_close::api::get_period_close)
+        .service(period_close::api::list_period_closes)
+
+        .service(loan::api::set_loan)
+        .service(loan::api::delete_loan)
+        .service(loan::api::get_loan)
+        .service(loan::api::get_loan_schedule)
+        .service(loan::api::list_loan_payments)
+
+        .service(credit_card::api::set_credit_card)
+        .service(credit_card::api::delete_credit_card)
+        .service(credit_card::api::get_credit_card)
+
         .service(expense::api::create_expense)
+        .service(expense_classifier::api::suggest_categories)
+        .service(expense_import::api::import_expenses)
         .service(expense::api::update_expense)
         .service(expense::api::delete_expense)
+        .service(expense::api::list_account_expense_reports)
+        .service(expense::api::list_outstanding_reimbursements)
         .service(expense::api::get_expense)
         .service(expense::api::list_expenses)
 
@@ -57,6 +186,8 @@ macro_rules! app (
 
         .service(statistics::api::calculate_statistics)
 
+        .service(sankey::api::calculate_sankey)
+
         .service(ResourceFiles::new("/", generate()))
     });
 );
@@ -71,6 +202,7 @@ async fn main() -> std::io::Result<()> {
         .max_size(1)
         .build(manager)
         .unwrap();
+    attachment::api::remove_unreferenced_files(&pool).await;
 
     info!("Starting HTTP server at http://localhost:8080");
     HttpServer::new(move || { app!(pool) })
@@ -95,23 +227,64 @@ fn run_migrations(manager: &SqliteConnectionManager) {
 mod tests {
     use actix_web::{web, App, test, middleware, http, body};
     use actix_cors::Cors;
-    use chrono::{NaiveDate, Local, Datelike};
+    use chrono::{NaiveDate, Local, Datelike, Days, Months, Utc};
     use log::info;
     use r2d2_sqlite::SqliteConnectionManager;
     use rust_decimal::Decimal;
     use actix_web_static_files::ResourceFiles;
     use crate::setting;
     use crate::account;
-    use crate::account::schema::{Account, AccountType, NewAccount};
+    use crate::account::schema::{Account, AccountMerge, AccountMergeConflictKind, AccountType, NewAccount};
     use crate::transaction;
     use crate::transaction::schema::{Transaction, TransactionType, NewTransaction};
     use crate::issue;
     use crate::statistics;
     use crate::expense;
+    use crate::account_group;
+    use crate::sankey;
+    use crate::expense_category;
+    use crate::budget;
+    use crate::expense_rule;
+    use crate::expense_classifier;
+    use crate::person;
+    use crate::household;
+    use crate::merchant;
+    use crate::subscription;
+    use crate::project;
+    use crate::expense_import;
+    use crate::tag;
+    use crate::attachment;
+    use crate::audit;
+    use crate::trash;
+    use crate::period_close;
+    use crate::loan;
+    use crate::credit_card;
+    use crate::credit_card::schema::CreditCard;
+    use crate::loan::schema::{Loan, LoanPayment, LoanSchedule};
+    use crate::period_close::schema::{NewPeriodClose, PeriodClose};
     use crate::issue::schema::{Issue, IssueType};
     use crate::db::Pool;
     use crate::run_migrations;
     use crate::setting::schema::{DateRepeat, NewSetting, Setting, SettingKey, RepeatingTransfer};
+    use crate::account_group::schema::{AccountGroup, NewAccountGroup};
+    use crate::statistics::schema::Statistic;
+    use crate::sankey::schema::{Sankey, SankeyLink};
+    use crate::expense::schema::{Expense, ExpenseKind, NewExpense};
+    use crate::expense_category::schema::{ExpenseCategory, NewExpenseCategory};
+    use crate::budget::schema::{Budget, BudgetPeriod, BudgetVariance, NewBudget};
+    use crate::expense_rule::schema::{ExpenseRule, ExpenseRuleField, ExpenseRuleMatchType, NewExpenseRule};
+    use crate::expense_classifier::schema::{CategorySuggestion, SuggestCategory};
+    use crate::expense::schema::{AccountExpenseReport, ExpenseSearch, OutstandingReimbursement};
+    use crate::person::schema::{NewPerson, Person};
+    use crate::tag::schema::{NewTag, Tag, TagAssignment};
+    use crate::attachment::schema::{Attachment, AttachmentOwnerType};
+    use crate::audit::schema::{AuditEntityType, AuditEntry, AuditOperation};
+    use crate::trash::schema::{TrashEntityType, TrashEntry};
+    use crate::expense_import::schema::{ExpenseImport, ImportedRow, ImportProfile, ImportStatus, NewImportProfile};
+    use crate::project::schema::{NewProject, Project, ProjectSummary};
+    use crate::subscription::schema::{NewSubscription, Subscription, SubscriptionCadence, SubscriptionProposal};
+    use crate::merchant::schema::{CategoryTopMerchants, Merchant, MerchantMerge, NewMerchant};
+    use crate::household::schema::{Debt, ExpenseShare, ExpenseSplit, HouseholdBalance, NewExpenseSplit, NewSettlement, Settlement, SplitType};
 
     include!(concat!(env!("OUT_DIR"), "/generated.rs"));
 
@@ -925,13 +1098,17 @@ mod tests {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(loan.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(savings.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
         ]);
 
@@ -959,19 +1136,25 @@ mod tests {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(loan.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(savings.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::TransferWithoutBalance,
                 account_id: Some(savings.id.clone()),
                 date: Some(NaiveDate::from_ymd_opt(2024, 1, 10).unwrap()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
         ]);
 
@@ -999,31 +1182,41 @@ mod tests {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(loan.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(savings.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::TransferWithoutBalance,
                 account_id: Some(loan.id.clone()),
                 date: Some(NaiveDate::from_ymd_opt(2024, 1, 11).unwrap()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::TransferWithoutBalance,
                 account_id: Some(savings.id.clone()),
                 date: Some(NaiveDate::from_ymd_opt(2024, 1, 11).unwrap()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::TransferWithoutBalance,
                 account_id: Some(savings.id.clone()),
                 date: Some(NaiveDate::from_ymd_opt(2024, 1, 10).unwrap()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
         ]);
 
@@ -1051,25 +1244,33 @@ mod tests {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(loan.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(savings.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::TransferWithoutBalance,
                 account_id: Some(loan.id.clone()),
                 date: Some(NaiveDate::from_ymd_opt(2024, 1, 11).unwrap()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::TransferWithoutBalance,
                 account_id: Some(savings.id.clone()),
                 date: Some(NaiveDate::from_ymd_opt(2024, 1, 10).unwrap()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
         ]);
 
@@ -1097,19 +1298,25 @@ mod tests {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(loan.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(savings.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::TransferWithoutBalance,
                 account_id: Some(savings.id.clone()),
                 date: Some(NaiveDate::from_ymd_opt(2024, 1, 10).unwrap()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
         ]);
 
@@ -1137,13 +1344,17 @@ mod tests {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(loan.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
             Issue {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(savings.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
         ]);
 
@@ -1171,8 +1382,3178 @@ mod tests {
                 issue_type: IssueType::NoBalance,
                 date: Some(Local::now().date_naive().with_day(1).unwrap()),
                 account_id: Some(loan.id.clone()),
-                from_account_id: None
+                from_account_id: None,
+                category_id: None,
+                subscription_id: None
             },
         ]);
     }
+
+    #[actix_web::test]
+    async fn test_account_groups() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        // Create Checking
+        let req = test::TestRequest::post()
+            .uri("/api/account/")
+            .set_json(NewAccount {
+                name: "Checking".to_string(),
+                account_type: AccountType::Checking,
+                hide_new_transactions: false,
+                transfer_without_balance_ignored: false,
+                no_regular_balance: false,
+                hidden: false,
+            })
+            .to_request();
+        let checking: Account = test::call_and_read_body_json(&app, req).await;
+
+        // Create Savings
+        let req = test::TestRequest::post()
+            .uri("/api/account/")
+            .set_json(NewAccount {
+                name: "Savings".to_string(),
+                account_type: AccountType::Savings,
+                hide_new_transactions: false,
+                transfer_without_balance_ignored: false,
+                no_regular_balance: false,
+                hidden: false,
+            })
+            .to_request();
+        let savings: Account = test::call_and_read_body_json(&app, req).await;
+
+        // Create group - [Emergency fund]
+        let req = test::TestRequest::post()
+            .uri("/api/account-group/")
+            .set_json(NewAccountGroup {
+                name: "Emergency fund".to_string(),
+                account_ids: vec![savings.id.clone()],
+            })
+            .to_request();
+        let resp: AccountGroup = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(AccountGroup {
+            id: resp.id.clone(),
+            name: "Emergency fund".to_string(),
+            account_ids: vec![savings.id.clone()],
+        }, resp);
+
+        // Update group - [Emergency fund]
+        let mut account_ids = vec![checking.id.clone(), savings.id.clone()];
+        account_ids.sort();
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account-group/{}/", resp.id.clone()).as_str())
+            .set_json(AccountGroup {
+                id: resp.id.clone(),
+                name: "Joint".to_string(),
+                account_ids: account_ids.clone(),
+            })
+            .to_request();
+        let resp: AccountGroup = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(AccountGroup {
+            id: resp.id.clone(),
+            name: "Joint".to_string(),
+            account_ids: account_ids.clone(),
+        }, resp);
+        let joint = resp;
+
+        // Create group with unknown account
+        let req = test::TestRequest::post()
+            .uri("/api/account-group/")
+            .set_json(NewAccountGroup {
+                name: "Unknown".to_string(),
+                account_ids: vec!["What".to_string()],
+            })
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        let code = response.response().status();
+        let vec = body::to_bytes(response.into_body()).await.unwrap().into();
+        let text = String::from_utf8(vec).unwrap();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, code);
+        assert_eq!("Account What does not exist", text);
+
+        // Create balances for both accounts
+        for (account_id, value) in [(checking.id.clone(), 100), (savings.id.clone(), 250)] {
+            let req = test::TestRequest::post()
+                .uri("/api/transaction/")
+                .set_json(NewTransaction {
+                    description: "".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                    value: Decimal::new(value, 0),
+                    transaction_type: TransactionType::Balance,
+                    account_id,
+                    from_account_id: None,
+                })
+                .to_request();
+            let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        }
+
+        // Group balances include every member
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/group_balance/")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        let value = statistics.last().unwrap().values.iter().find(|value| value.name == joint.id).unwrap();
+        assert_eq!(Decimal::new(350, 0), value.value);
+
+        // Restricting to checking only leaves checking in the group
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/group_balance/?account_types=CHECKING")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        let value = statistics.last().unwrap().values.iter().find(|value| value.name == joint.id).unwrap();
+        assert_eq!(Decimal::new(100, 0), value.value);
+
+        // Restricting to the group ids only reports its accounts
+        let req = test::TestRequest::get()
+            .uri(format!("/api/statistics/yearly/account_balance/?account_group_ids={}&account_ids={}", joint.id, savings.id).as_str())
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        let names: Vec<String> = statistics.last().unwrap().values.iter().map(|value| value.name.clone()).collect();
+        assert_eq!(vec![savings.id.clone()], names);
+
+        // Expenses follow the same account filter
+        for (account_id, value) in [(checking.id.clone(), 30), (savings.id.clone(), 12)] {
+            let req = test::TestRequest::post()
+                .uri("/api/expense/")
+                .set_json(NewExpense {
+                    description: "Shop".to_string(),
+                    external: "".to_string(),
+                    category: "GROCERIES".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                    value: Decimal::new(value, 0),
+                    account_id: Some(account_id),
+                    transaction_id: None,
+                    merchant_id: None,
+                    kind: ExpenseKind::Purchase,
+                    original_expense_id: None,
+                    project_id: None,
+                })
+                .to_request();
+            let _: Expense = test::call_and_read_body_json(&app, req).await;
+        }
+        let total = |statistics: &Vec<Statistic>| statistics.last().unwrap().values.iter().find(|value| value.name == "TOTAL").unwrap().value;
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/expenses/")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Decimal::new(42, 0), total(&statistics));
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/expenses/?account_types=CHECKING")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Decimal::new(30, 0), total(&statistics));
+        let req = test::TestRequest::get()
+            .uri(format!("/api/statistics/yearly/merchants/?account_ids={}", savings.id).as_str())
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Decimal::new(12, 0), total(&statistics));
+
+        // Delete checking removes it from the group
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/account/{}/", checking.id.clone()).as_str())
+            .to_request();
+        let _: Account = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account-group/{}/", joint.id.clone()).as_str())
+            .to_request();
+        let resp: AccountGroup = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![savings.id.clone()], resp.account_ids);
+
+        // Delete group - []
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/account-group/{}/", joint.id.clone()).as_str())
+            .to_request();
+        let _: AccountGroup = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/account-group/")
+            .to_request();
+        let resp: Vec<AccountGroup> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.len(), 0);
+    }
+    #[actix_web::test]
+    async fn test_expense_categories() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        // Seeded categories keep their previous ids
+        let req = test::TestRequest::get()
+            .uri("/api/expense-category/UNKNOWN/")
+            .to_request();
+        let resp: ExpenseCategory = test::call_and_read_body_json(&app, req).await;
+        assert_eq!("Unknown", resp.name);
+
+        // Create category - [Home]
+        let req = test::TestRequest::post()
+            .uri("/api/expense-category/")
+            .set_json(NewExpenseCategory {
+                name: "Home".to_string(),
+                parent_id: None,
+                colour: Some("#00ff00".to_string()),
+                hidden: false,
+            })
+            .to_request();
+        let home: ExpenseCategory = test::call_and_read_body_json(&app, req).await;
+
+        // Create subcategory - [Home, Furniture]
+        let req = test::TestRequest::post()
+            .uri("/api/expense-category/")
+            .set_json(NewExpenseCategory {
+                name: "Furniture".to_string(),
+                parent_id: Some(home.id.clone()),
+                colour: None,
+                hidden: false,
+            })
+            .to_request();
+        let furniture: ExpenseCategory = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Some(home.id.clone()), furniture.parent_id);
+
+        // Parent cannot be moved under its own subcategory
+        let req = test::TestRequest::post()
+            .uri(format!("/api/expense-category/{}/", home.id.clone()).as_str())
+            .set_json(ExpenseCategory {
+                parent_id: Some(furniture.id.clone()),
+                ..home.clone()
+            })
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        let code = response.response().status();
+        let vec = body::to_bytes(response.into_body()).await.unwrap().into();
+        let text = String::from_utf8(vec).unwrap();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, code);
+        assert_eq!("Expense category cannot be its own parent", text);
+
+        // Expenses must use an existing category
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(NewExpense {
+                description: "Couch".to_string(),
+                external: "1".to_string(),
+                category: "What".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+                value: Decimal::new(500, 0),
+                account_id: None,
+                transaction_id: None,
+                merchant_id: None,
+                kind: ExpenseKind::Purchase,
+                original_expense_id: None,
+                project_id: None,
+            })
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        let code = response.response().status();
+        let vec = body::to_bytes(response.into_body()).await.unwrap().into();
+        let text = String::from_utf8(vec).unwrap();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, code);
+        assert_eq!("Expense category What does not exist", text);
+
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(NewExpense {
+                description: "Couch".to_string(),
+                external: "1".to_string(),
+                category: furniture.id.clone(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+                value: Decimal::new(500, 0),
+                account_id: None,
+                transaction_id: None,
+                merchant_id: None,
+                kind: ExpenseKind::Purchase,
+                original_expense_id: None,
+                project_id: None,
+            })
+            .to_request();
+        let couch: Expense = test::call_and_read_body_json(&app, req).await;
+
+        // Statistics need a transaction to pick their dates
+        let req = test::TestRequest::post()
+            .uri("/api/account/")
+            .set_json(NewAccount {
+                name: "Checking".to_string(),
+                account_type: AccountType::Checking,
+                hide_new_transactions: false,
+                transfer_without_balance_ignored: false,
+                no_regular_balance: false,
+                hidden: false,
+            })
+            .to_request();
+        let checking: Account = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(NewTransaction {
+                description: "".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                value: Decimal::new(100, 0),
+                transaction_type: TransactionType::Balance,
+                account_id: checking.id.clone(),
+                from_account_id: None,
+            })
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+
+        // Parents include their subcategories
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/expenses/")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        let value = |name: &str| statistics.last().unwrap().values.iter().find(|value| value.name == name).unwrap().value;
+        assert_eq!(Decimal::new(500, 0), value(&furniture.id));
+        assert_eq!(Decimal::new(500, 0), value(&home.id));
+        assert_eq!(Decimal::new(500, 0), value("TOTAL"));
+
+        // Categories with subcategories or expenses cannot be deleted
+        for (id, message) in [
+            (home.id.clone(), format!("Expense category {} has subcategories", home.id)),
+            (furniture.id.clone(), format!("Expense category {} is used by 1 expenses", furniture.id)),
+            ("UNKNOWN".to_string(), "Expense category UNKNOWN cannot be deleted".to_string()),
+        ] {
+            let req = test::TestRequest::delete()
+                .uri(format!("/api/expense-category/{}/", id).as_str())
+                .to_request();
+            let response = test::call_service(&app, req).await;
+            let code = response.response().status();
+            let vec = body::to_bytes(response.into_body()).await.unwrap().into();
+            let text = String::from_utf8(vec).unwrap();
+            assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, code);
+            assert_eq!(message, text);
+        }
+
+        // Delete expense then both categories - []
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/expense/{}/", couch.id.clone()).as_str())
+            .to_request();
+        let _: Expense = test::call_and_read_body_json(&app, req).await;
+        for id in [furniture.id.clone(), home.id.clone()] {
+            let req = test::TestRequest::delete()
+                .uri(format!("/api/expense-category/{}/", id).as_str())
+                .to_request();
+            let _: ExpenseCategory = test::call_and_read_body_json(&app, req).await;
+        }
+    }
+    #[actix_web::test]
+    async fn test_budgets() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+        let today = Local::now().date_naive();
+
+        // Create budget - [GROCERIES]
+        let req = test::TestRequest::post()
+            .uri("/api/budget/")
+            .set_json(NewBudget {
+                category_id: "GROCERIES".to_string(),
+                value: Decimal::new(600, 0),
+                period: BudgetPeriod::Monthly,
+                rollover: false,
+                effective_from: today,
+            })
+            .to_request();
+        let resp: Budget = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Budget {
+            id: resp.id.clone(),
+            category_id: "GROCERIES".to_string(),
+            value: Decimal::new(600, 0),
+            period: BudgetPeriod::Monthly,
+            rollover: false,
+            effective_from: today,
+        }, resp);
+
+        // Spend more than the budget this month
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(NewExpense {
+                description: "Weekly shop".to_string(),
+                external: "1".to_string(),
+                category: "GROCERIES".to_string(),
+                date: today,
+                value: Decimal::new(650, 0),
+                account_id: None,
+                transaction_id: None,
+                merchant_id: None,
+                kind: ExpenseKind::Purchase,
+                original_expense_id: None,
+                project_id: None,
+            })
+            .to_request();
+        let _: Expense = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/budget/report/")
+            .to_request();
+        let resp: Vec<BudgetVariance> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(1, resp.len());
+        assert_eq!(Decimal::new(600, 0), resp[0].budgeted);
+        assert_eq!(Decimal::new(650, 0), resp[0].actual);
+        assert_eq!(Decimal::new(-50, 0), resp[0].remaining);
+
+        let req = test::TestRequest::get()
+            .uri("/api/issue/")
+            .to_request();
+        let resp: Vec<Issue> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![
+            Issue {
+                issue_type: IssueType::OverBudget,
+                date: Some(today.with_day(1).unwrap()),
+                account_id: None,
+                from_account_id: None,
+                category_id: Some("GROCERIES".to_string()),
+                subscription_id: None
+            },
+        ], resp);
+
+        // Budgets are removed along with their category
+        let req = test::TestRequest::post()
+            .uri("/api/expense-category/")
+            .set_json(NewExpenseCategory {
+                name: "Garden".to_string(),
+                parent_id: None,
+                colour: None,
+                hidden: false,
+            })
+            .to_request();
+        let garden: ExpenseCategory = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/budget/")
+            .set_json(NewBudget {
+                category_id: garden.id.clone(),
+                value: Decimal::new(50, 0),
+                period: BudgetPeriod::Monthly,
+                rollover: false,
+                effective_from: today,
+            })
+            .to_request();
+        let _: Budget = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/expense-category/{}/", garden.id))
+            .to_request();
+        let _: ExpenseCategory = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/budget/")
+            .to_request();
+        let resp: Vec<Budget> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec!["GROCERIES"], resp.iter().map(|budget| budget.category_id.as_str()).collect::<Vec<&str>>());
+    }
+    #[actix_web::test]
+    async fn test_expense_rules() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+        let new_expense = |external: &str, category: &str| NewExpense {
+            description: "Card payment".to_string(),
+            external: external.to_string(),
+            category: category.to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+            value: Decimal::new(25, 0),
+            account_id: None,
+            transaction_id: None,
+            merchant_id: None,
+            kind: ExpenseKind::Purchase,
+            original_expense_id: None,
+            project_id: None,
+        };
+
+        // Created before any rules exist - [UNKNOWN]
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("TESCO 1", "UNKNOWN"))
+            .to_request();
+        let before: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!("UNKNOWN", before.category);
+
+        // Invalid regex
+        let req = test::TestRequest::post()
+            .uri("/api/expense-rule/")
+            .set_json(NewExpenseRule {
+                field: ExpenseRuleField::External,
+                match_type: ExpenseRuleMatchType::Regex,
+                pattern: "(".to_string(),
+                min_value: None,
+                max_value: None,
+                category_id: "GROCERIES".to_string(),
+                description: None,
+                priority: 0,
+            })
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, response.response().status());
+
+        // Create rule - [tesco -> GROCERIES]
+        let req = test::TestRequest::post()
+            .uri("/api/expense-rule/")
+            .set_json(NewExpenseRule {
+                field: ExpenseRuleField::External,
+                match_type: ExpenseRuleMatchType::Contains,
+                pattern: "tesco".to_string(),
+                min_value: None,
+                max_value: Some(Decimal::new(100, 0)),
+                category_id: "GROCERIES".to_string(),
+                description: Some("Tesco".to_string()),
+                priority: 0,
+            })
+            .to_request();
+        let _: ExpenseRule = test::call_and_read_body_json(&app, req).await;
+
+        // New UNKNOWN expenses are categorised, explicit categories are kept
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("TESCO 2", "UNKNOWN"))
+            .to_request();
+        let resp: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(("GROCERIES", "Tesco"), (resp.category.as_str(), resp.description.as_str()));
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("TESCO 3", "HOUSE"))
+            .to_request();
+        let resp: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(("HOUSE", "Card payment"), (resp.category.as_str(), resp.description.as_str()));
+
+        // Re-apply to the existing UNKNOWN expenses
+        let req = test::TestRequest::post()
+            .uri("/api/expense-rule/apply/")
+            .to_request();
+        let resp: Vec<Expense> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![Expense {
+            category: "GROCERIES".to_string(),
+            description: "Tesco".to_string(),
+            ..before
+        }], resp);
+
+        // Rules are removed along with their category
+        let req = test::TestRequest::post()
+            .uri("/api/expense-category/")
+            .set_json(NewExpenseCategory {
+                name: "Discount".to_string(),
+                parent_id: None,
+                colour: None,
+                hidden: false,
+            })
+            .to_request();
+        let discount: ExpenseCategory = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/expense-rule/")
+            .set_json(NewExpenseRule {
+                field: ExpenseRuleField::External,
+                match_type: ExpenseRuleMatchType::Contains,
+                pattern: "aldi".to_string(),
+                min_value: None,
+                max_value: None,
+                category_id: discount.id.clone(),
+                description: None,
+                priority: 0,
+            })
+            .to_request();
+        let _: ExpenseRule = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/expense-category/{}/", discount.id))
+            .to_request();
+        let _: ExpenseCategory = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/expense-rule/")
+            .to_request();
+        let resp: Vec<ExpenseRule> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec!["GROCERIES"], resp.iter().map(|expense_rule| expense_rule.category_id.as_str()).collect::<Vec<&str>>());
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("ALDI 1", "UNKNOWN"))
+            .to_request();
+        let resp: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!("UNKNOWN", resp.category);
+    }
+    #[actix_web::test]
+    async fn test_suggest_categories() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        // Learn from saved expenses
+        let mut expenses: Vec<Expense> = vec![];
+        for (external, category) in [("TESCO 1", "GROCERIES"), ("TESCO 2", "GROCERIES"), ("SHELL 1", "CAR")] {
+            let req = test::TestRequest::post()
+                .uri("/api/expense/")
+                .set_json(NewExpense {
+                    description: "Card payment".to_string(),
+                    external: external.to_string(),
+                    category: category.to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+                    value: Decimal::new(40, 0),
+                    account_id: None,
+                    transaction_id: None,
+                    merchant_id: None,
+                    kind: ExpenseKind::Purchase,
+                    original_expense_id: None,
+                    project_id: None,
+                })
+                .to_request();
+            expenses.push(test::call_and_read_body_json(&app, req).await);
+        }
+
+        let suggest = SuggestCategory {
+            description: "Card payment".to_string(),
+            external: "SHELL 2".to_string(),
+            value: Decimal::new(45, 0),
+            limit: Some(1),
+        };
+        let req = test::TestRequest::post()
+            .uri("/api/expense/suggest-category/")
+            .set_json(suggest.clone())
+            .to_request();
+        let resp: Vec<CategorySuggestion> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(1, resp.len());
+        assert_eq!("CAR", resp[0].category.id);
+
+        // Recategorising the only shell expense changes what is learnt
+        let req = test::TestRequest::post()
+            .uri(format!("/api/expense/{}/", expenses[2].id).as_str())
+            .set_json(Expense {
+                category: "GROCERIES".to_string(),
+                ..expenses[2].clone()
+            })
+            .to_request();
+        let _: Expense = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/expense/suggest-category/")
+            .set_json(suggest)
+            .to_request();
+        let resp: Vec<CategorySuggestion> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![("GROCERIES".to_string(), Decimal::ONE)], resp.iter().map(|suggestion| (suggestion.category.id.clone(), suggestion.confidence)).collect::<Vec<_>>());
+    }
+    #[actix_web::test]
+    async fn test_expense_links() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for (name, account_type) in [("Checking", AccountType::Checking), ("Credit card", AccountType::CreditCard)] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let (checking, credit_card) = (accounts[0].clone(), accounts[1].clone());
+
+        // Credit card goes from 0 to -300 with a 100 payment from checking
+        let mut transactions: Vec<Transaction> = vec![];
+        for (transaction_type, day, value, account_id, from_account_id) in [
+            (TransactionType::Balance, 1, 0, credit_card.id.clone(), None),
+            (TransactionType::Transfer, 20, 100, credit_card.id.clone(), Some(checking.id.clone())),
+            (TransactionType::Balance, 31, -300, credit_card.id.clone(), None),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/transaction/")
+                .set_json(NewTransaction {
+                    description: "".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+                    value: Decimal::new(value, 0),
+                    transaction_type,
+                    account_id,
+                    from_account_id,
+                })
+                .to_request();
+            transactions.push(test::call_and_read_body_json(&app, req).await);
+        }
+
+        // Only transfers can be linked
+        let new_expense = |category: &str, value: i64, transaction_id: Option<String>| NewExpense {
+            description: "".to_string(),
+            external: format!("{category}-{value}"),
+            category: category.to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 20).unwrap(),
+            value: Decimal::new(value, 0),
+            account_id: Some(credit_card.id.clone()),
+            transaction_id,
+            merchant_id: None,
+            kind: ExpenseKind::Purchase,
+            original_expense_id: None,
+            project_id: None,
+        };
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("GROCERIES", 200, Some(transactions[0].id.clone())))
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        let code = response.response().status();
+        let vec = body::to_bytes(response.into_body()).await.unwrap().into();
+        let text = String::from_utf8(vec).unwrap();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, code);
+        assert_eq!(format!("Transaction {} is not a transfer", transactions[0].id), text);
+
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("GROCERIES", 200, Some(transactions[1].id.clone())))
+            .to_request();
+        let linked: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Some(transactions[1].id.clone()), linked.transaction_id);
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("UNKNOWN", 50, None))
+            .to_request();
+        let _: Expense = test::call_and_read_body_json(&app, req).await;
+
+        // 300 spent on the card plus the 100 payment, 250 of it itemised
+        let req = test::TestRequest::get()
+            .uri("/api/expense/account-report/?start=2024-01-01&end=2024-01-31")
+            .to_request();
+        let resp: Vec<AccountExpenseReport> = test::call_and_read_body_json(&app, req).await;
+        // Checking has no balances so nothing can be said about its outflow
+        assert_eq!(vec![
+            AccountExpenseReport {
+                account_id: credit_card.id.clone(),
+                outflow: Decimal::new(400, 0),
+                categorised: Decimal::new(200, 0),
+                uncategorised: Decimal::new(50, 0),
+                unitemised: Decimal::new(150, 0),
+            },
+        ], resp);
+
+        // Purging the transfer from the trash unlinks the expense
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/transaction/{}/", transactions[1].id).as_str())
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/trash/{}/", trash[0].id).as_str())
+            .to_request();
+        let _: TrashEntry = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/{}/", linked.id).as_str())
+            .to_request();
+        let resp: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((Some(credit_card.id.clone()), None), (resp.account_id, resp.transaction_id));
+    }
+    #[actix_web::test]
+    async fn test_household() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for (name, account_type) in [("Checking", AccountType::Checking), ("Partner", AccountType::External)] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+
+        let mut persons: Vec<Person> = vec![];
+        for (name, me, account) in [("Me", true, &accounts[0]), ("Partner", false, &accounts[1])] {
+            let req = test::TestRequest::post()
+                .uri("/api/person/")
+                .set_json(NewPerson {
+                    name: name.to_string(),
+                    me,
+                    account_id: Some(account.id.clone()),
+                })
+                .to_request();
+            persons.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let (me, partner) = (persons[0].clone(), persons[1].clone());
+
+        // Only one person can be me
+        let req = test::TestRequest::post()
+            .uri(format!("/api/person/{}/", partner.id).as_str())
+            .set_json(Person { me: true, ..partner.clone() })
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, response.response().status());
+
+        // I pay 100 of groceries, split equally
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(NewExpense {
+                description: "Groceries".to_string(),
+                external: "1".to_string(),
+                category: "GROCERIES".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+                value: Decimal::new(100, 0),
+                account_id: None,
+                transaction_id: None,
+                merchant_id: None,
+                kind: ExpenseKind::Purchase,
+                original_expense_id: None,
+                project_id: None,
+            })
+            .to_request();
+        let expense: Expense = test::call_and_read_body_json(&app, req).await;
+        let shares = vec![
+            ExpenseShare { person_id: me.id.clone(), value: None },
+            ExpenseShare { person_id: partner.id.clone(), value: None },
+        ];
+        let req = test::TestRequest::post()
+            .uri(format!("/api/expense/{}/split/", expense.id).as_str())
+            .set_json(NewExpenseSplit {
+                split_type: SplitType::Equal,
+                paid_by: me.id.clone(),
+                shares: shares.clone(),
+            })
+            .to_request();
+        let resp: ExpenseSplit = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(2, resp.shares.len());
+
+        // Percentages have to add up
+        let req = test::TestRequest::post()
+            .uri(format!("/api/expense/{}/split/", expense.id).as_str())
+            .set_json(NewExpenseSplit {
+                split_type: SplitType::Percentage,
+                paid_by: me.id.clone(),
+                shares: vec![ExpenseShare { person_id: me.id.clone(), value: Some(Decimal::new(60, 0)) }],
+            })
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        let code = response.response().status();
+        let vec = body::to_bytes(response.into_body()).await.unwrap().into();
+        let text = String::from_utf8(vec).unwrap();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, code);
+        assert_eq!("Percentages add up to 60 not 100", text);
+
+        let req = test::TestRequest::get()
+            .uri("/api/household/balance/")
+            .to_request();
+        let resp: HouseholdBalance = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![Debt {
+            from_person_id: partner.id.clone(),
+            to_person_id: me.id.clone(),
+            value: Decimal::new(50, 0),
+        }], resp.debts);
+
+        // Only my half is reported
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(NewTransaction {
+                description: "".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                value: Decimal::new(100, 0),
+                transaction_type: TransactionType::Balance,
+                account_id: accounts[0].id.clone(),
+                from_account_id: None,
+            })
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/expenses/?my_share=true")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        let value = statistics.last().unwrap().values.iter().find(|value| value.name == "GROCERIES").unwrap();
+        assert_eq!(Decimal::new(50, 0), value.value);
+
+        // Settling up records a transfer and clears the debt
+        let req = test::TestRequest::post()
+            .uri("/api/household/settlement/")
+            .set_json(NewSettlement {
+                from_person_id: partner.id.clone(),
+                to_person_id: me.id.clone(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(),
+                value: Decimal::new(50, 0),
+            })
+            .to_request();
+        let settlement: Settlement = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(format!("/api/transaction/{}/", settlement.transaction_id.clone().unwrap()).as_str())
+            .to_request();
+        let transfer: Transaction = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((accounts[0].id.clone(), Some(accounts[1].id.clone()), Decimal::new(50, 0)), (transfer.account_id, transfer.from_account_id, transfer.value));
+
+        let req = test::TestRequest::get()
+            .uri("/api/household/balance/")
+            .to_request();
+        let resp: HouseholdBalance = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Vec::<Debt>::new(), resp.debts);
+
+        // Exact shares pin the value of the expense
+        let req = test::TestRequest::post()
+            .uri(format!("/api/expense/{}/split/", expense.id).as_str())
+            .set_json(NewExpenseSplit {
+                split_type: SplitType::Exact,
+                paid_by: me.id.clone(),
+                shares: vec![
+                    ExpenseShare { person_id: me.id.clone(), value: Some(Decimal::new(70, 0)) },
+                    ExpenseShare { person_id: partner.id.clone(), value: Some(Decimal::new(30, 0)) },
+                ],
+            })
+            .to_request();
+        let _: ExpenseSplit = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri(format!("/api/expense/{}/", expense.id).as_str())
+            .set_json(Expense { value: Decimal::new(120, 0), ..expense.clone() })
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::get()
+            .uri(format!("/api/audit/?entityType=EXPENSE_SPLIT&entityId={}", expense.id).as_str())
+            .to_request();
+        let resp: Vec<AuditEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(2, resp.len());
+
+        // Splits in a closed period can't be changed
+        let req = test::TestRequest::post()
+            .uri("/api/period-close/")
+            .set_json(NewPeriodClose {
+                account_id: None,
+                closed_through: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+                closed_by: "Reviewer".to_string(),
+            })
+            .to_request();
+        let _: PeriodClose = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/expense/{}/split/", expense.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+    }
+    #[actix_web::test]
+    async fn test_search_expenses() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        for (description, external, category, day, value) in [
+            ("Weekly shop", "TESCO 1", "GROCERIES", 5, 4250),
+            ("Top up shop", "TESCO 2", "GROCERIES", 12, 950),
+            ("Fuel", "SHELL 1", "CAR", 14, 6000),
+            ("Big shop", "ALDI 1", "GROCERIES", 20, 12000),
+            ("Car wash 50% off", "SHELL_2", "CAR", 3, 500),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/expense/")
+                .set_json(NewExpense {
+                    description: description.to_string(),
+                    external: external.to_string(),
+                    category: category.to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+                    value: Decimal::new(value, 2),
+                    account_id: None,
+                    transaction_id: None,
+                    merchant_id: None,
+                    kind: ExpenseKind::Purchase,
+                    original_expense_id: None,
+                    project_id: None,
+                })
+                .to_request();
+            let _: Expense = test::call_and_read_body_json(&app, req).await;
+        }
+
+        let search = |uri: &str| test::TestRequest::get().uri(format!("/api/expense/{uri}").as_str()).to_request();
+        let descriptions = |resp: &ExpenseSearch| resp.expenses.iter().map(|expense| expense.description.clone()).collect::<Vec<String>>();
+
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, search("?categories=GROCERIES&search=SHOP&sort=value&direction=desc")).await;
+        assert_eq!(vec!["Big shop", "Weekly shop", "Top up shop"], descriptions(&resp));
+        assert_eq!(3, resp.summary.count);
+        assert_eq!(Decimal::new(17200, 2), resp.summary.sum);
+        assert_eq!(Decimal::new(5733, 2), resp.summary.average);
+
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, search("?external=tesco&min_value=10&end=2024-01-31")).await;
+        assert_eq!(vec!["Weekly shop"], descriptions(&resp));
+
+        // Summary covers every match while the page is limited
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, search("?start=2024-01-06&page=1&page_size=2")).await;
+        assert_eq!(vec!["Big shop"], descriptions(&resp));
+        assert_eq!(3, resp.summary.count);
+
+        // Pages past what SQLite can offset are refused rather than wrapping around
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, search(&format!("?page={}&page_size=2", usize::MAX))).await.status());
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, search(&format!("?page=1&page_size={}", i64::MAX as u64 + 1))).await.status());
+
+        // Without a page size every expense comes back
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, search("")).await;
+        assert_eq!(vec!["Car wash 50% off", "Weekly shop", "Top up shop", "Fuel", "Big shop"], descriptions(&resp));
+
+        // LIKE wildcards in the search text are taken literally
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, search("?search=%25")).await;
+        assert_eq!(vec!["Car wash 50% off"], descriptions(&resp));
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, search("?external=l_")).await;
+        assert_eq!(vec!["Car wash 50% off"], descriptions(&resp));
+    }
+
+    #[actix_web::test]
+    async fn test_merchants() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/merchant/")
+            .set_json(NewMerchant {
+                name: "Amazon".to_string(),
+                aliases: vec!["Amazon.com".to_string()],
+                patterns: vec!["(".to_string()],
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+
+        let req = test::TestRequest::post()
+            .uri("/api/merchant/")
+            .set_json(NewMerchant {
+                name: "Amazon".to_string(),
+                aliases: vec!["Amazon.com".to_string()],
+                patterns: vec![],
+            })
+            .to_request();
+        let amazon: Merchant = test::call_and_read_body_json(&app, req).await;
+
+        let mut expenses: Vec<Expense> = vec![];
+        for (external, category, day, value) in [
+            ("amazon.com", "ELECTRONICS", 5, 200),
+            ("AMZN Mktp US*2K4", "ELECTRONICS", 6, 50),
+            ("AMZN Mktp US*9Z1", "GIFTS", 7, 30),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/expense/")
+                .set_json(NewExpense {
+                    description: "".to_string(),
+                    external: external.to_string(),
+                    category: category.to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+                    value: Decimal::new(value, 0),
+                    account_id: None,
+                    transaction_id: None,
+                    merchant_id: None,
+                    kind: ExpenseKind::Purchase,
+                    original_expense_id: None,
+                    project_id: None,
+                })
+                .to_request();
+            expenses.push(test::call_and_read_body_json(&app, req).await);
+        }
+        assert_eq!(vec![Some(amazon.id.clone()), None, None], expenses.iter().map(|expense| expense.merchant_id.clone()).collect::<Vec<_>>());
+
+        // Existing expenses pick up a merchant once a pattern matches them
+        let req = test::TestRequest::post()
+            .uri("/api/merchant/")
+            .set_json(NewMerchant {
+                name: "Amazon Marketplace".to_string(),
+                aliases: vec![],
+                patterns: vec!["^AMZN Mktp".to_string()],
+            })
+            .to_request();
+        let marketplace: Merchant = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/{}/", expenses[2].id).as_str())
+            .to_request();
+        let resp: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Some(marketplace.id.clone()), resp.merchant_id);
+
+        // Merging moves the expenses and keeps the old name as an alias
+        let req = test::TestRequest::post()
+            .uri(format!("/api/merchant/{}/merge/", amazon.id).as_str())
+            .set_json(MerchantMerge { merchant_ids: vec![marketplace.id.clone()] })
+            .to_request();
+        let resp: Merchant = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Merchant {
+            id: amazon.id.clone(),
+            name: "Amazon".to_string(),
+            aliases: vec!["Amazon Marketplace".to_string(), "Amazon.com".to_string()],
+            patterns: vec!["^AMZN Mktp".to_string()],
+        }, resp);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/merchant/{}/", marketplace.id).as_str())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::NOT_FOUND, resp.status());
+
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/?merchants={}", amazon.id).as_str())
+            .to_request();
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(3, resp.summary.count);
+
+        let req = test::TestRequest::get()
+            .uri("/api/merchant/top/?start=2024-01-01&end=2024-01-31")
+            .to_request();
+        let resp: Vec<CategoryTopMerchants> = test::call_and_read_body_json(&app, req).await;
+        let electronics = resp.iter().find(|category| category.category_id == "ELECTRONICS").unwrap();
+        assert_eq!(amazon.id, electronics.merchants[0].merchant_id);
+        assert_eq!(Decimal::new(250, 0), electronics.merchants[0].value);
+        assert_eq!(2, electronics.merchants[0].count);
+
+        let req = test::TestRequest::post()
+            .uri("/api/account/")
+            .set_json(NewAccount {
+                name: "Checking".to_string(),
+                account_type: AccountType::Checking,
+                hide_new_transactions: false,
+                transfer_without_balance_ignored: false,
+                no_regular_balance: false,
+                hidden: false,
+            })
+            .to_request();
+        let account: Account = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(NewTransaction {
+                description: "".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                value: Decimal::new(1000, 0),
+                transaction_type: TransactionType::Balance,
+                account_id: account.id.clone(),
+                from_account_id: None,
+            })
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/merchants/")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        let value = |name: &str| statistics.last().unwrap().values.iter().find(|value| value.name == name).unwrap().value;
+        assert_eq!(Decimal::new(280, 0), value(&amazon.id));
+        assert_eq!(Decimal::new(280, 0), value("TOTAL"));
+        assert_eq!(Decimal::ZERO, value("UNKNOWN"));
+
+        // Deleting a merchant leaves its expenses without one
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/merchant/{}/", amazon.id).as_str())
+            .to_request();
+        let _: Merchant = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/{}/", expenses[0].id).as_str())
+            .to_request();
+        let resp: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(None, resp.merchant_id);
+    }
+
+    #[actix_web::test]
+    async fn test_subscriptions() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/merchant/")
+            .set_json(NewMerchant {
+                name: "Streaming".to_string(),
+                aliases: vec![],
+                patterns: vec!["^STREAMING".to_string()],
+            })
+            .to_request();
+        let merchant: Merchant = test::call_and_read_body_json(&app, req).await;
+
+        // Charged monthly until a couple of months ago so the latest charge is missing
+        let start = (Local::now().date_naive() - Days::new(10)).checked_sub_months(Months::new(5)).unwrap();
+        for index in 0..4 {
+            let req = test::TestRequest::post()
+                .uri("/api/expense/")
+                .set_json(NewExpense {
+                    description: "".to_string(),
+                    external: format!("STREAMING {index}"),
+                    category: "SUBSCRIPTIONS".to_string(),
+                    date: start.checked_add_months(Months::new(index)).unwrap(),
+                    value: Decimal::new(999, 2),
+                    account_id: None,
+                    transaction_id: None,
+                    merchant_id: None,
+                    kind: ExpenseKind::Purchase,
+                    original_expense_id: None,
+                    project_id: None,
+                })
+                .to_request();
+            let _: Expense = test::call_and_read_body_json(&app, req).await;
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/api/subscription/proposal/")
+            .to_request();
+        let resp: Vec<SubscriptionProposal> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![SubscriptionProposal {
+            merchant_id: merchant.id.clone(),
+            cadence: SubscriptionCadence::Monthly,
+            value: Decimal::new(999, 2),
+            next_due: start.checked_add_months(Months::new(3)).unwrap().checked_add_months(Months::new(1)).unwrap(),
+            count: 4,
+        }], resp);
+
+        let req = test::TestRequest::post()
+            .uri("/api/subscription/")
+            .set_json(NewSubscription {
+                merchant_id: resp[0].merchant_id.clone(),
+                cadence: resp[0].cadence,
+                value: resp[0].value,
+                start,
+            })
+            .to_request();
+        let subscription: Subscription = test::call_and_read_body_json(&app, req).await;
+
+        // Confirmed subscriptions are no longer proposed
+        let req = test::TestRequest::get()
+            .uri("/api/subscription/proposal/")
+            .to_request();
+        let resp: Vec<SubscriptionProposal> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(0, resp.len());
+
+        let req = test::TestRequest::get()
+            .uri("/api/issue/")
+            .to_request();
+        let resp: Vec<Issue> = test::call_and_read_body_json(&app, req).await;
+        let issues: Vec<&Issue> = resp.iter()
+            .filter(|issue| issue.subscription_id == Some(subscription.id.clone()))
+            .collect();
+        assert_eq!(1, issues.len());
+        assert_eq!(IssueType::SubscriptionMissing, issues[0].issue_type);
+
+        // Deleting the merchant removes its subscriptions
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/merchant/{}/", merchant.id).as_str())
+            .to_request();
+        let _: Merchant = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/subscription/")
+            .to_request();
+        let resp: Vec<Subscription> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(0, resp.len());
+    }
+
+    #[actix_web::test]
+    async fn test_refunds_and_reimbursements() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let new_expense = |external: &str, category: &str, day: u32, value: i64, kind: ExpenseKind, original_expense_id: Option<String>| NewExpense {
+            description: "".to_string(),
+            external: external.to_string(),
+            category: category.to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            value: Decimal::new(value, 0),
+            account_id: None,
+            transaction_id: None,
+            merchant_id: None,
+            kind,
+            original_expense_id,
+            project_id: None,
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("1", "CLOTHING", 2, 100, ExpenseKind::Purchase, None))
+            .to_request();
+        let purchase: Expense = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("2", "WORK", 3, 300, ExpenseKind::Reimbursable, None))
+            .to_request();
+        let reimbursable: Expense = test::call_and_read_body_json(&app, req).await;
+
+        // Purchases can't be reimbursed and refunds need something to refund
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("3", "OTHER", 4, 50, ExpenseKind::Reimbursement, Some(purchase.id.clone())))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("3", "OTHER", 4, 50, ExpenseKind::Refund, None))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("3", "OTHER", 4, 40, ExpenseKind::Refund, Some(purchase.id.clone())))
+            .to_request();
+        let _: Expense = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(new_expense("4", "OTHER", 5, 200, ExpenseKind::Reimbursement, Some(reimbursable.id.clone())))
+            .to_request();
+        let _: Expense = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/expense/reimbursement/")
+            .to_request();
+        let resp: Vec<OutstandingReimbursement> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![OutstandingReimbursement {
+            expense_id: reimbursable.id.clone(),
+            date: reimbursable.date,
+            category: "WORK".to_string(),
+            value: Decimal::new(300, 0),
+            reimbursed: Decimal::new(200, 0),
+            outstanding: Decimal::new(100, 0),
+        }], resp);
+
+        // The original can't be removed while something pays it back
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/expense/{}/", purchase.id).as_str())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+
+        let req = test::TestRequest::post()
+            .uri("/api/account/")
+            .set_json(NewAccount {
+                name: "Checking".to_string(),
+                account_type: AccountType::Checking,
+                hide_new_transactions: false,
+                transfer_without_balance_ignored: false,
+                no_regular_balance: false,
+                hidden: false,
+            })
+            .to_request();
+        let account: Account = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(NewTransaction {
+                description: "".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                value: Decimal::new(1000, 0),
+                transaction_type: TransactionType::Balance,
+                account_id: account.id.clone(),
+                from_account_id: None,
+            })
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+
+        let value = |statistics: &Vec<Statistic>, name: &str| statistics.last().unwrap().values.iter().find(|value| value.name == name).unwrap().value;
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/expenses/")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Decimal::new(60, 0), value(&statistics, "CLOTHING"));
+        assert_eq!(Decimal::new(100, 0), value(&statistics, "WORK"));
+        assert_eq!(Decimal::ZERO, value(&statistics, "OTHER"));
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/expenses/?gross=true")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Decimal::new(100, 0), value(&statistics, "CLOTHING"));
+        assert_eq!(Decimal::new(400, 0), value(&statistics, "TOTAL"));
+    }
+
+    #[actix_web::test]
+    async fn test_projects() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/project/")
+            .set_json(NewProject {
+                name: "Japan".to_string(),
+                start: NaiveDate::from_ymd_opt(2024, 4, 10).unwrap(),
+                end: NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(),
+                budget: None,
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+
+        let req = test::TestRequest::post()
+            .uri("/api/project/")
+            .set_json(NewProject {
+                name: "Japan".to_string(),
+                start: NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(),
+                end: NaiveDate::from_ymd_opt(2024, 4, 10).unwrap(),
+                budget: Some(Decimal::new(3000, 0)),
+            })
+            .to_request();
+        let project: Project = test::call_and_read_body_json(&app, req).await;
+
+        let mut expenses: Vec<Expense> = vec![];
+        for (external, category, value, project_id) in [
+            ("1", "VACATIONS", 1500, Some(project.id.clone())),
+            ("2", "RESTAURANTS", 300, Some(project.id.clone())),
+            ("3", "GROCERIES", 100, None),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/expense/")
+                .set_json(NewExpense {
+                    description: "".to_string(),
+                    external: external.to_string(),
+                    category: category.to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 4, 2).unwrap(),
+                    value: Decimal::new(value, 0),
+                    account_id: None,
+                    transaction_id: None,
+                    merchant_id: None,
+                    kind: ExpenseKind::Purchase,
+                    original_expense_id: None,
+                    project_id,
+                })
+                .to_request();
+            expenses.push(test::call_and_read_body_json(&app, req).await);
+        }
+
+        let req = test::TestRequest::get()
+            .uri(format!("/api/project/{}/summary/", project.id).as_str())
+            .to_request();
+        let resp: ProjectSummary = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Decimal::new(1800, 0), resp.total);
+        assert_eq!(10, resp.days);
+        assert_eq!(Decimal::new(180, 0), resp.daily_average);
+        assert_eq!(Some(Decimal::new(1200, 0)), resp.remaining);
+        assert_eq!(vec!["VACATIONS".to_string(), "RESTAURANTS".to_string()], resp.categories.iter().map(|category| category.category_id.clone()).collect::<Vec<String>>());
+
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/?project_id={}", project.id).as_str())
+            .to_request();
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(2, resp.summary.count);
+
+        // Deleting the project keeps its expenses
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/project/{}/", project.id).as_str())
+            .to_request();
+        let _: Project = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/{}/", expenses[0].id).as_str())
+            .to_request();
+        let resp: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(None, resp.project_id);
+    }
+
+    #[actix_web::test]
+    async fn test_import_expenses() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/import-profile/")
+            .set_json(NewImportProfile {
+                name: "Card".to_string(),
+                delimiter: ",".to_string(),
+                header: true,
+                date_column: 0,
+                date_format: "%d/%m/%Y".to_string(),
+                external_column: 1,
+                description_column: None,
+                value_column: 2,
+                negate: true,
+                duplicate_days: 2,
+            })
+            .to_request();
+        let import_profile: ImportProfile = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/expense-rule/")
+            .set_json(NewExpenseRule {
+                field: ExpenseRuleField::External,
+                match_type: ExpenseRuleMatchType::Contains,
+                pattern: "TESCO".to_string(),
+                min_value: None,
+                max_value: None,
+                category_id: "GROCERIES".to_string(),
+                description: None,
+                priority: 0,
+            })
+            .to_request();
+        let _: ExpenseRule = test::call_and_read_body_json(&app, req).await;
+
+        // Already entered by hand, the statement shows it a day later
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(NewExpense {
+                description: "".to_string(),
+                external: "SHELL".to_string(),
+                category: "CAR".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+                value: Decimal::new(6000, 2),
+                account_id: None,
+                transaction_id: None,
+                merchant_id: None,
+                kind: ExpenseKind::Purchase,
+                original_expense_id: None,
+                project_id: None,
+            })
+            .to_request();
+        let existing: Expense = test::call_and_read_body_json(&app, req).await;
+
+        let content = "Date,Merchant,Amount\n03/01/2024,BP,-60.00\n03/01/2024,Shell,-60.00\n04/01/2024,TESCO STORES,-12.50\n05/01/2024,Refund,5.00\nnot a date,TESCO,-1\n";
+        let import = |dry_run: bool| test::TestRequest::post()
+            .uri("/api/expense/import/")
+            .set_json(ExpenseImport {
+                profile_id: import_profile.id.clone(),
+                content: content.to_string(),
+                account_id: None,
+                dry_run,
+            })
+            .to_request();
+        let statuses = |imported_rows: &Vec<ImportedRow>| imported_rows.iter().map(|imported_row| (imported_row.line, imported_row.status)).collect::<Vec<_>>();
+
+        let resp: Vec<ImportedRow> = test::call_and_read_body_json(&app, import(true)).await;
+        // The same value from another merchant is only flagged
+        assert_eq!(vec![
+            (2, ImportStatus::Flagged),
+            (3, ImportStatus::Skipped),
+            (4, ImportStatus::Created),
+            (5, ImportStatus::Skipped),
+            (6, ImportStatus::Invalid),
+        ], statuses(&resp));
+        assert_eq!(Some(existing.id.clone()), resp[0].duplicate_of);
+        assert_eq!(Some(existing.id.clone()), resp[1].duplicate_of);
+        assert_eq!("GROCERIES", resp[2].expense.as_ref().unwrap().category);
+        assert_eq!(None, resp[2].expense_id);
+        let req = test::TestRequest::get()
+            .uri("/api/expense/")
+            .to_request();
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(1, resp.expenses.len());
+
+        let resp: Vec<ImportedRow> = test::call_and_read_body_json(&app, import(false)).await;
+        assert_eq!(ImportStatus::Created, resp[2].status);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/{}/", resp[2].expense_id.clone().unwrap()).as_str())
+            .to_request();
+        let resp: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Decimal::new(1250, 2), resp.value);
+        assert_eq!("GROCERIES", resp.category);
+
+        // Importing the same statement again creates nothing
+        let resp: Vec<ImportedRow> = test::call_and_read_body_json(&app, import(false)).await;
+        assert_eq!(vec![
+            (2, ImportStatus::Skipped),
+            (3, ImportStatus::Skipped),
+            (4, ImportStatus::Skipped),
+            (5, ImportStatus::Skipped),
+            (6, ImportStatus::Invalid),
+        ], statuses(&resp));
+
+        // Rows in a closed period are skipped on a dry run and a real one alike
+        let req = test::TestRequest::post()
+            .uri("/api/period-close/")
+            .set_json(NewPeriodClose {
+                account_id: None,
+                closed_through: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                closed_by: "Reviewer".to_string(),
+            })
+            .to_request();
+        let _: PeriodClose = test::call_and_read_body_json(&app, req).await;
+        for dry_run in [true, false] {
+            let req = test::TestRequest::post()
+                .uri("/api/expense/import/")
+                .set_json(ExpenseImport {
+                    profile_id: import_profile.id.clone(),
+                    content: "Date,Merchant,Amount\n10/01/2024,ALDI,-3.00\n11/01/2024,ALDI,-3.00\n".to_string(),
+                    account_id: None,
+                    dry_run,
+                })
+                .to_request();
+            let resp: Vec<ImportedRow> = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(vec![(2, ImportStatus::Skipped), (3, ImportStatus::Created)], statuses(&resp));
+            assert_eq!(Some("Date 2024-01-10 is in a period closed through 2024-01-10".to_string()), resp[0].message);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_tags() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut tags: Vec<Tag> = vec![];
+        for name in ["wedding", "tax-deductible"] {
+            let req = test::TestRequest::post()
+                .uri("/api/tag/")
+                .set_json(NewTag { name: name.to_string() })
+                .to_request();
+            tags.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let wedding = tags[0].clone();
+
+        let mut accounts: Vec<Account> = vec![];
+        for (name, account_type) in [("Checking", AccountType::Checking), ("Savings", AccountType::Savings)] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(NewTransaction {
+                description: "Wedding fund".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+                value: Decimal::new(500, 0),
+                transaction_type: TransactionType::Transfer,
+                account_id: accounts[1].id.clone(),
+                from_account_id: Some(accounts[0].id.clone()),
+            })
+            .to_request();
+        let transfer: Transaction = test::call_and_read_body_json(&app, req).await;
+
+        let mut expenses: Vec<Expense> = vec![];
+        for (external, value) in [("1", 200), ("2", 50)] {
+            let req = test::TestRequest::post()
+                .uri("/api/expense/")
+                .set_json(NewExpense {
+                    description: "".to_string(),
+                    external: external.to_string(),
+                    category: "GIFTS".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, 6).unwrap(),
+                    value: Decimal::new(value, 0),
+                    account_id: None,
+                    transaction_id: None,
+                    merchant_id: None,
+                    kind: ExpenseKind::Purchase,
+                    original_expense_id: None,
+                    project_id: None,
+                })
+                .to_request();
+            expenses.push(test::call_and_read_body_json(&app, req).await);
+        }
+
+        let req = test::TestRequest::post()
+            .uri("/api/expense/missing/tag/")
+            .set_json(TagAssignment { tag_ids: vec![wedding.id.clone()] })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::NOT_FOUND, resp.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/expense/{}/tag/", expenses[0].id).as_str())
+            .set_json(TagAssignment { tag_ids: vec!["missing".to_string()] })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+
+        let req = test::TestRequest::post()
+            .uri(format!("/api/expense/{}/tag/", expenses[0].id).as_str())
+            .set_json(TagAssignment { tag_ids: vec![wedding.id.clone(), tags[1].id.clone()] })
+            .to_request();
+        let resp: Vec<Tag> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(2, resp.len());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/transaction/{}/tag/", transfer.id).as_str())
+            .set_json(TagAssignment { tag_ids: vec![wedding.id.clone()] })
+            .to_request();
+        let _: Vec<Tag> = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/?tags={}", wedding.id).as_str())
+            .to_request();
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![expenses[0].clone()], resp.expenses);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/transaction/?tags={}", tags[1].id).as_str())
+            .to_request();
+        let resp: Vec<Transaction> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(0, resp.len());
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/?tags={}", wedding.id).as_str())
+            .to_request();
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Decimal::new(200, 0), resp.summary.sum);
+
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/tags/")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        let value = |name: String| statistics.last().unwrap().values.iter().find(|value| value.name == name).unwrap().value;
+        assert_eq!(Decimal::new(200, 0), value(format!("EXPENSES:{}", wedding.id)));
+        assert_eq!(Decimal::new(500, 0), value(format!("TRANSFERS:{}", wedding.id)));
+        assert_eq!(Decimal::ZERO, value(format!("TRANSFERS:{}", tags[1].id)));
+
+        // Deleting a tag removes it from everything it was on
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/tag/{}/", wedding.id).as_str())
+            .to_request();
+        let _: Tag = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/{}/tag/", expenses[0].id).as_str())
+            .to_request();
+        let resp: Vec<Tag> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![tags[1].clone()], resp);
+    }
+
+    #[actix_web::test]
+    async fn test_attachments() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/account/")
+            .set_json(NewAccount {
+                name: "Checking".to_string(),
+                account_type: AccountType::Checking,
+                hide_new_transactions: false,
+                transfer_without_balance_ignored: false,
+                no_regular_balance: false,
+                hidden: false,
+            })
+            .to_request();
+        let account: Account = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(NewTransaction {
+                description: "".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+                value: Decimal::new(100, 0),
+                transaction_type: TransactionType::Balance,
+                account_id: account.id.clone(),
+                from_account_id: None,
+            })
+            .to_request();
+        let balance: Transaction = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(NewExpense {
+                description: "".to_string(),
+                external: "HARDWARE STORE".to_string(),
+                category: "HOUSE".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 20).unwrap(),
+                value: Decimal::new(40, 0),
+                account_id: Some(account.id.clone()),
+                transaction_id: None,
+                merchant_id: None,
+                kind: ExpenseKind::Purchase,
+                original_expense_id: None,
+                project_id: None,
+            })
+            .to_request();
+        let expense: Expense = test::call_and_read_body_json(&app, req).await;
+
+        // Without a directory configured the receipt is kept in the database
+        let req = test::TestRequest::post()
+            .uri(format!("/api/attachment/?ownerType=EXPENSE&ownerId={}&name=receipt.txt", expense.id).as_str())
+            .insert_header((http::header::CONTENT_TYPE, "text/plain"))
+            .set_payload("abc")
+            .to_request();
+        let receipt: Attachment = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(AttachmentOwnerType::Expense, receipt.owner_type);
+        assert_eq!("text/plain", receipt.content_type);
+        assert_eq!(3, receipt.size);
+        assert_eq!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad", receipt.hash);
+
+        let req = test::TestRequest::get()
+            .uri(format!("/api/attachment/{}/content/", receipt.id).as_str())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!("text/plain", resp.headers().get(http::header::CONTENT_TYPE).unwrap());
+        assert_eq!("abc", test::read_body(resp).await);
+
+        let req = test::TestRequest::post()
+            .uri("/api/attachment/?ownerType=EXPENSE&ownerId=missing&name=receipt.txt")
+            .set_payload("abc")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/attachment/?ownerType=EXPENSE&ownerId={}&name=huge.bin", expense.id).as_str())
+            .set_payload(vec![0u8; attachment::db::MAX_ATTACHMENT_SIZE + 1])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+
+        // With a directory configured the statements are written there instead
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir(&directory).unwrap();
+        let req = test::TestRequest::post()
+            .uri("/api/setting/")
+            .set_json(NewSetting {
+                key: SettingKey::AttachmentDirectory,
+                value: directory.to_string_lossy().to_string(),
+            })
+            .to_request();
+        let _: Setting = test::call_and_read_body_json(&app, req).await;
+        let mut statements: Vec<Attachment> = vec![];
+        for (owner_type, owner_id) in [("TRANSACTION", balance.id.clone()), ("ACCOUNT", account.id.clone())] {
+            let req = test::TestRequest::post()
+                .uri(format!("/api/attachment/?ownerType={owner_type}&ownerId={owner_id}&name=statement.pdf").as_str())
+                .insert_header((http::header::CONTENT_TYPE, "application/pdf"))
+                .set_payload("%PDF-1.4")
+                .to_request();
+            statements.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let file = directory.join(&statements[0].hash);
+        assert!(file.exists());
+        let req = test::TestRequest::get()
+            .uri(format!("/api/attachment/?ownerType=ACCOUNT&ownerId={}", account.id).as_str())
+            .to_request();
+        let resp: Vec<Attachment> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![statements[1].clone()], resp);
+
+        // Attachments stay with a deleted transaction until it is purged from the trash
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/transaction/{}/", balance.id).as_str())
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(format!("/api/attachment/{}/", statements[0].id).as_str())
+            .to_request();
+        let _: Attachment = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/trash/{}/", trash[0].id).as_str())
+            .to_request();
+        let _: TrashEntry = test::call_and_read_body_json(&app, req).await;
+        // The same file is shared until the last attachment using it goes
+        assert!(file.exists());
+        let req = test::TestRequest::get()
+            .uri(format!("/api/attachment/{}/", statements[0].id).as_str())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::NOT_FOUND, resp.status());
+
+        // Files are removed once the purge is committed, along with any left behind by a failed upload,
+        // but only after the grace period so an upload that hasn't committed yet keeps its file
+        let stray = directory.join("0".repeat(64));
+        let recent = directory.join("1".repeat(64));
+        let notes = directory.join("notes.txt");
+        std::fs::write(&stray, "abc").unwrap();
+        std::fs::write(&recent, "abc").unwrap();
+        std::fs::write(&notes, "abc").unwrap();
+        let two_hours_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(2 * 60 * 60);
+        for path in [&file, &stray, &notes] {
+            std::fs::File::options().write(true).open(path).unwrap().set_modified(two_hours_ago).unwrap();
+        }
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/account/{}/", account.id).as_str())
+            .to_request();
+        let _: Account = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/trash/{}/", trash[0].id).as_str())
+            .to_request();
+        let _: TrashEntry = test::call_and_read_body_json(&app, req).await;
+        assert!(!file.exists());
+        assert!(!stray.exists());
+        assert!(recent.exists());
+        assert!(notes.exists());
+        std::fs::remove_file(&recent).unwrap();
+        std::fs::remove_file(&notes).unwrap();
+        let req = test::TestRequest::get()
+            .uri("/api/attachment/")
+            .to_request();
+        let resp: Vec<Attachment> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![receipt.clone()], resp);
+
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/expense/{}/", expense.id).as_str())
+            .to_request();
+        let _: Expense = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/trash/{}/", trash[0].id).as_str())
+            .to_request();
+        let _: TrashEntry = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/attachment/")
+            .to_request();
+        let resp: Vec<Attachment> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(0, resp.len());
+        std::fs::remove_dir(&directory).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_audit() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for name in ["Checking", "Employer"] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type: AccountType::Checking,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(NewTransaction {
+                description: "".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+                value: Decimal::new(100, 0),
+                transaction_type: TransactionType::Transfer,
+                account_id: accounts[0].id.clone(),
+                from_account_id: Some(accounts[1].id.clone()),
+            })
+            .to_request();
+        let transfer: Transaction = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri(format!("/api/transaction/{}/", transfer.id).as_str())
+            .set_json(Transaction { value: Decimal::new(90, 0), ..transfer.clone() })
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(NewExpense {
+                description: "".to_string(),
+                external: "GROCER".to_string(),
+                category: "GROCERIES".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 20).unwrap(),
+                value: Decimal::new(10, 0),
+                account_id: Some(accounts[0].id.clone()),
+                transaction_id: Some(transfer.id.clone()),
+                merchant_id: None,
+                kind: ExpenseKind::Purchase,
+                original_expense_id: None,
+                project_id: None,
+            })
+            .to_request();
+        let expense: Expense = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/setting/")
+            .set_json(NewSetting {
+                key: SettingKey::DefaultTransactionFromAccountId,
+                value: accounts[0].id.clone(),
+            })
+            .to_request();
+        let setting: Setting = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri(format!("/api/audit/?entityType=TRANSACTION&entityId={}", transfer.id).as_str())
+            .to_request();
+        let resp: Vec<AuditEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![AuditOperation::Update, AuditOperation::Create], resp.iter().map(|entry| entry.operation).collect::<Vec<_>>());
+        assert_eq!(Some(serde_json::json!(100.0)), resp[0].before.as_ref().map(|before| before["value"].clone()));
+        assert_eq!(Some(serde_json::json!(90.0)), resp[0].after.as_ref().map(|after| after["value"].clone()));
+        assert_eq!(None, resp[1].before);
+
+        // Everything deleted along with the account is recorded too
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/account/{}/", accounts[0].id).as_str())
+            .to_request();
+        let _: Account = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/audit/?operation=DELETE")
+            .to_request();
+        let resp: Vec<AuditEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![
+            (AuditEntityType::Account, accounts[0].id.clone()),
+            (AuditEntityType::Transaction, transfer.id.clone()),
+            (AuditEntityType::Setting, setting.id.clone()),
+        ], resp.iter().map(|entry| (entry.entity_type, entry.entity_id.clone())).collect::<Vec<_>>());
+        assert_eq!(None, resp[0].after);
+
+        // Purging it from the trash unlinks the expense
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/trash/{}/", trash[0].id).as_str())
+            .to_request();
+        let _: TrashEntry = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/audit/?operation=PURGE")
+            .to_request();
+        let resp: Vec<AuditEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![
+            (AuditEntityType::Account, accounts[0].id.clone()),
+            (AuditEntityType::Transaction, transfer.id.clone()),
+        ], resp.iter().map(|entry| (entry.entity_type, entry.entity_id.clone())).collect::<Vec<_>>());
+        let req = test::TestRequest::get()
+            .uri(format!("/api/audit/?entityType=EXPENSE&entityId={}&operation=UPDATE", expense.id).as_str())
+            .to_request();
+        let resp: Vec<AuditEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(2, resp.len());
+        assert_eq!(serde_json::Value::Null, resp[0].after.as_ref().unwrap()["accountId"]);
+        assert_eq!(serde_json::Value::Null, resp[1].after.as_ref().unwrap()["transactionId"]);
+
+        let today = Utc::now().date_naive();
+        let req = test::TestRequest::get()
+            .uri(format!("/api/audit/?start={}&end={}&limit=3", today, today).as_str())
+            .to_request();
+        let resp: Vec<AuditEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(3, resp.len());
+        let req = test::TestRequest::get()
+            .uri(format!("/api/audit/?end={}", today.pred_opt().unwrap()).as_str())
+            .to_request();
+        let resp: Vec<AuditEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(0, resp.len());
+    }
+
+    #[actix_web::test]
+    async fn test_trash() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for (name, account_type) in [("Checking", AccountType::Checking), ("Savings", AccountType::Savings)] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let (checking, savings) = (accounts[0].clone(), accounts[1].clone());
+        let mut transactions: Vec<Transaction> = vec![];
+        for (transaction_type, account_id, from_account_id) in [
+            (TransactionType::Balance, checking.id.clone(), None),
+            (TransactionType::Transfer, savings.id.clone(), Some(checking.id.clone())),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/transaction/")
+                .set_json(NewTransaction {
+                    description: "".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                    value: Decimal::new(100, 0),
+                    transaction_type,
+                    account_id,
+                    from_account_id,
+                })
+                .to_request();
+            transactions.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(NewExpense {
+                description: "".to_string(),
+                external: "GROCER".to_string(),
+                category: "GROCERIES".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                value: Decimal::new(30, 0),
+                account_id: Some(checking.id.clone()),
+                transaction_id: Some(transactions[1].id.clone()),
+                merchant_id: None,
+                kind: ExpenseKind::Purchase,
+                original_expense_id: None,
+                project_id: None,
+            })
+            .to_request();
+        let expense: Expense = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/setting/")
+            .set_json(NewSetting {
+                key: SettingKey::DefaultTransactionFromAccountId,
+                value: checking.id.clone(),
+            })
+            .to_request();
+        let setting: Setting = test::call_and_read_body_json(&app, req).await;
+
+        // Deleting the account takes its transactions into the trash with it
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/account/{}/", checking.id).as_str())
+            .to_request();
+        let _: Account = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(1, trash.len());
+        assert_eq!((TrashEntityType::Account, checking.id.clone(), 2), (trash[0].entity_type, trash[0].entity_id.clone(), trash[0].cascaded));
+        assert_eq!(vec![setting.clone()], trash[0].settings);
+        let account_trash = trash[0].clone();
+
+        let req = test::TestRequest::get()
+            .uri("/api/account/")
+            .to_request();
+        let resp: Vec<Account> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![savings.clone()], resp);
+        let req = test::TestRequest::get()
+            .uri("/api/transaction/")
+            .to_request();
+        let resp: Vec<Transaction> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(0, resp.len());
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/yearly/account_balance/")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        assert!(statistics.iter().all(|statistic| statistic.values.iter().all(|value| value.name != checking.id)));
+        let req = test::TestRequest::get()
+            .uri(format!("/api/setting/{}/", setting.id).as_str())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::NOT_FOUND, resp.status());
+
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/expense/{}/", expense.id).as_str())
+            .to_request();
+        let _: Expense = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/expense/")
+            .to_request();
+        let resp: ExpenseSearch = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(0, resp.summary.count);
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![TrashEntityType::Expense, TrashEntityType::Account], trash.iter().map(|trash_entry| trash_entry.entity_type).collect::<Vec<_>>());
+
+        // Restoring brings back the transactions and the setting too
+        let req = test::TestRequest::post()
+            .uri(format!("/api/trash/{}/restore/", account_trash.id).as_str())
+            .to_request();
+        let _: TrashEntry = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/transaction/", checking.id).as_str())
+            .to_request();
+        let resp: Vec<Transaction> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(2, resp.len());
+        let req = test::TestRequest::get()
+            .uri(format!("/api/setting/{}/", setting.id).as_str())
+            .to_request();
+        let resp: Setting = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(setting, resp);
+        let req = test::TestRequest::post()
+            .uri(format!("/api/trash/{}/restore/", account_trash.id).as_str())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::NOT_FOUND, resp.status());
+
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri(format!("/api/trash/{}/restore/", trash[0].id).as_str())
+            .to_request();
+        let _: TrashEntry = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/{}/", expense.id).as_str())
+            .to_request();
+        let resp: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(expense, resp);
+
+        // A balance can't come back once another has been entered for the same day
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/transaction/{}/", transactions[0].id).as_str())
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(NewTransaction {
+                description: "".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                value: Decimal::new(120, 0),
+                transaction_type: TransactionType::Balance,
+                account_id: checking.id.clone(),
+                from_account_id: None,
+            })
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri(format!("/api/trash/{}/restore/", trash[0].id).as_str())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/trash/{}/", trash[0].id).as_str())
+            .to_request();
+        let _: TrashEntry = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(0, trash.len());
+    }
+
+    #[actix_web::test]
+    async fn test_merge_accounts() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for name in ["Old Checking", "Checking", "Savings"] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type: AccountType::Checking,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let (old_checking, checking, savings) = (accounts[0].clone(), accounts[1].clone(), accounts[2].clone());
+        let mut transactions: Vec<Transaction> = vec![];
+        for (transaction_type, day, account_id, from_account_id) in [
+            (TransactionType::Balance, 10, old_checking.id.clone(), None),
+            (TransactionType::Balance, 10, checking.id.clone(), None),
+            (TransactionType::Balance, 11, old_checking.id.clone(), None),
+            (TransactionType::Transfer, 10, savings.id.clone(), Some(old_checking.id.clone())),
+            (TransactionType::Transfer, 10, savings.id.clone(), Some(checking.id.clone())),
+            (TransactionType::Transfer, 12, checking.id.clone(), Some(old_checking.id.clone())),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/transaction/")
+                .set_json(NewTransaction {
+                    description: "".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+                    value: Decimal::new(100, 0),
+                    transaction_type,
+                    account_id,
+                    from_account_id,
+                })
+                .to_request();
+            transactions.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(NewExpense {
+                description: "".to_string(),
+                external: "GROCER".to_string(),
+                category: "GROCERIES".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                value: Decimal::new(30, 0),
+                account_id: Some(old_checking.id.clone()),
+                transaction_id: Some(transactions[3].id.clone()),
+                merchant_id: None,
+                kind: ExpenseKind::Purchase,
+                original_expense_id: None,
+                project_id: None,
+            })
+            .to_request();
+        let expense: Expense = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/account-group/")
+            .set_json(NewAccountGroup {
+                name: "Everyday".to_string(),
+                account_ids: vec![old_checking.id.clone()],
+            })
+            .to_request();
+        let account_group: AccountGroup = test::call_and_read_body_json(&app, req).await;
+        for (key, value) in [
+            (SettingKey::DefaultTransactionFromAccountId, old_checking.id.clone()),
+            (SettingKey::RepeatingTransfers, serde_json::to_string(&vec![RepeatingTransfer {
+                start: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                repeat: DateRepeat::MONTHLY,
+                repeat_count: 1,
+                from_account_id: old_checking.id.clone(),
+                to_account_ids: vec![checking.id.clone(), savings.id.clone()],
+            }]).unwrap()),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/setting/")
+                .set_json(NewSetting { key, value })
+                .to_request();
+            let _: Setting = test::call_and_read_body_json(&app, req).await;
+        }
+
+        let req = test::TestRequest::post()
+            .uri("/api/tag/")
+            .set_json(NewTag { name: "Holiday".to_string() })
+            .to_request();
+        let tag: Tag = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri(format!("/api/transaction/{}/tag/", transactions[3].id).as_str())
+            .set_json(TagAssignment { tag_ids: vec![tag.id.clone()] })
+            .to_request();
+        let _: Vec<Tag> = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri(format!("/api/attachment/?ownerType=TRANSACTION&ownerId={}&name=statement.txt", transactions[3].id).as_str())
+            .insert_header((http::header::CONTENT_TYPE, "text/plain"))
+            .set_payload("abc")
+            .to_request();
+        let attachment: Attachment = test::call_and_read_body_json(&app, req).await;
+
+        // Merging into itself or a missing account fails, merging a missing account is not found
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/merge-into/{}/", old_checking.id, old_checking.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/merge-into/missing/", old_checking.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/missing/merge-into/{}/", checking.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::NOT_FOUND, test::call_service(&app, req).await.status());
+
+        // A dry run lists the conflicts and leaves everything alone
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/merge-into/{}/?dryRun=true", old_checking.id, checking.id).as_str())
+            .to_request();
+        let merge: AccountMerge = test::call_and_read_body_json(&app, req).await;
+        assert!(merge.dry_run);
+        assert_eq!(1, merge.moved);
+        let conflicts: Vec<(AccountMergeConflictKind, String, Option<String>)> = merge.conflicts.iter()
+            .map(|conflict| (conflict.kind.clone(), conflict.dropped.id.clone(), conflict.kept.as_ref().map(|kept| kept.id.clone())))
+            .collect();
+        assert_eq!(vec![
+            (AccountMergeConflictKind::SelfTransfer, transactions[5].id.clone(), None),
+            (AccountMergeConflictKind::Balance, transactions[0].id.clone(), Some(transactions[1].id.clone())),
+            (AccountMergeConflictKind::Transfer, transactions[3].id.clone(), Some(transactions[4].id.clone())),
+        ], conflicts);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/transaction/", old_checking.id).as_str())
+            .to_request();
+        let old_checking_transactions: Vec<Transaction> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(4, old_checking_transactions.len());
+
+        // The real merge drops the conflicts, moves everything else over and removes the account
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/merge-into/{}/", old_checking.id, checking.id).as_str())
+            .to_request();
+        let merged: AccountMerge = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(AccountMerge { dry_run: false, ..merge }, merged);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/", old_checking.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::NOT_FOUND, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/transaction/", checking.id).as_str())
+            .to_request();
+        let checking_transactions: Vec<Transaction> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![
+            Transaction { account_id: checking.id.clone(), ..transactions[2].clone() },
+            transactions[1].clone(),
+            transactions[4].clone(),
+        ], checking_transactions);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/expense/{}/", expense.id).as_str())
+            .to_request();
+        let merged_expense: Expense = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((Some(checking.id.clone()), Some(transactions[4].id.clone())), (merged_expense.account_id, merged_expense.transaction_id));
+        let req = test::TestRequest::get()
+            .uri(format!("/api/transaction/{}/tag/", transactions[4].id).as_str())
+            .to_request();
+        let merged_tags: Vec<Tag> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![tag], merged_tags);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/attachment/{}/", attachment.id).as_str())
+            .to_request();
+        let merged_attachment: Attachment = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(transactions[4].id, merged_attachment.owner_id);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account-group/{}/", account_group.id).as_str())
+            .to_request();
+        let merged_account_group: AccountGroup = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![checking.id.clone()], merged_account_group.account_ids);
+        let req = test::TestRequest::get()
+            .uri("/api/setting/")
+            .to_request();
+        let settings: Vec<Setting> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(2, settings.len());
+        assert_eq!(checking.id, settings[0].value);
+        let repeating_transfers: Vec<RepeatingTransfer> = serde_json::from_str(&settings[1].value).unwrap();
+        assert_eq!((checking.id.clone(), vec![savings.id.clone()]), (repeating_transfers[0].from_account_id.clone(), repeating_transfers[0].to_account_ids.clone()));
+    }
+
+    #[actix_web::test]
+    async fn test_merge_accounts_closed_and_trashed() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for name in ["Old Checking", "Checking"] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type: AccountType::Checking,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let (old_checking, checking) = (accounts[0].clone(), accounts[1].clone());
+        let mut transactions: Vec<Transaction> = vec![];
+        for account_id in [old_checking.id.clone(), checking.id.clone()] {
+            let req = test::TestRequest::post()
+                .uri("/api/transaction/")
+                .set_json(NewTransaction {
+                    description: "".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                    value: Decimal::new(100, 0),
+                    transaction_type: TransactionType::Balance,
+                    account_id,
+                    from_account_id: None,
+                })
+                .to_request();
+            transactions.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/transaction/{}/", transactions[0].id).as_str())
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+
+        // Moving into a closed period of the target is refused
+        let req = test::TestRequest::post()
+            .uri("/api/period-close/")
+            .set_json(NewPeriodClose {
+                account_id: Some(checking.id.clone()),
+                closed_through: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+                closed_by: "Reviewer".to_string(),
+            })
+            .to_request();
+        let period_close: PeriodClose = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/merge-into/{}/?dryRun=true", old_checking.id, checking.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/period-close/{}/", period_close.id).as_str())
+            .to_request();
+        let _: PeriodClose = test::call_and_read_body_json(&app, req).await;
+
+        // The trashed balance conflicts with the target balance and is dropped along with its trash entry
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/merge-into/{}/", old_checking.id, checking.id).as_str())
+            .to_request();
+        let merged: AccountMerge = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(0, merged.moved);
+        let conflicts: Vec<(AccountMergeConflictKind, String, Option<String>)> = merged.conflicts.iter()
+            .map(|conflict| (conflict.kind.clone(), conflict.dropped.id.clone(), conflict.kept.as_ref().map(|kept| kept.id.clone())))
+            .collect();
+        assert_eq!(vec![(AccountMergeConflictKind::Balance, transactions[0].id.clone(), Some(transactions[1].id.clone()))], conflicts);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/transaction/", checking.id).as_str())
+            .to_request();
+        let checking_transactions: Vec<Transaction> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![transactions[1].clone()], checking_transactions);
+        let req = test::TestRequest::get()
+            .uri("/api/trash/")
+            .to_request();
+        let trash: Vec<TrashEntry> = test::call_and_read_body_json(&app, req).await;
+        assert!(trash.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_merge_loan_accounts() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for name in ["Old Loan", "Loan", "Other Loan"] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type: AccountType::Loan,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let (old_loan, loan, other_loan) = (accounts[0].clone(), accounts[1].clone(), accounts[2].clone());
+        let details = |account_id: String| Loan {
+            account_id,
+            principal: Decimal::new(10000, 0),
+            interest_rate: Decimal::new(12, 0),
+            term_months: 12,
+            first_payment_date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+            payment: None,
+        };
+        for account_id in [old_loan.id.clone(), other_loan.id.clone()] {
+            let req = test::TestRequest::post()
+                .uri(format!("/api/account/{}/loan/", account_id).as_str())
+                .set_json(details(account_id.clone()))
+                .to_request();
+            let _: Loan = test::call_and_read_body_json(&app, req).await;
+        }
+        let req = test::TestRequest::post()
+            .uri("/api/period-close/")
+            .set_json(NewPeriodClose {
+                account_id: Some(old_loan.id.clone()),
+                closed_through: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+                closed_by: "Reviewer".to_string(),
+            })
+            .to_request();
+        let period_close: PeriodClose = test::call_and_read_body_json(&app, req).await;
+
+        // The loan and close move over when the target has no loan of its own
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/merge-into/{}/", old_loan.id, loan.id).as_str())
+            .to_request();
+        let merged: AccountMerge = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(None, merged.dropped_loan);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/loan/", loan.id).as_str())
+            .to_request();
+        let moved: Loan = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(details(loan.id.clone()), moved);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/period-close/{}/", period_close.id).as_str())
+            .to_request();
+        let moved: PeriodClose = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Some(loan.id.clone()), moved.account_id);
+
+        // Otherwise the target keeps its own and the dry run reports the one dropped
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/merge-into/{}/?dryRun=true", loan.id, other_loan.id).as_str())
+            .to_request();
+        let merge: AccountMerge = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Some(details(loan.id.clone())), merge.dropped_loan);
+        assert_eq!(None, merge.dropped_credit_card);
+    }
+
+    #[actix_web::test]
+    async fn test_period_close() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for (name, account_type) in [("Checking", AccountType::Checking), ("Savings", AccountType::Savings)] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let (checking, savings) = (accounts[0].clone(), accounts[1].clone());
+        let new_transaction = |day: u32, account_id: String, from_account_id: Option<String>| NewTransaction {
+            description: "".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            value: Decimal::new(100, 0),
+            transaction_type: if from_account_id.is_some() { TransactionType::Transfer } else { TransactionType::Balance },
+            account_id,
+            from_account_id,
+        };
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(new_transaction(10, checking.id.clone(), None))
+            .to_request();
+        let balance: Transaction = test::call_and_read_body_json(&app, req).await;
+        let mut expenses: Vec<Expense> = vec![];
+        for (day, account_id) in [(15, Some(checking.id.clone())), (20, None)] {
+            let req = test::TestRequest::post()
+                .uri("/api/expense/")
+                .set_json(NewExpense {
+                    description: "".to_string(),
+                    external: "GROCER".to_string(),
+                    category: "GROCERIES".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+                    value: Decimal::new(30, 0),
+                    account_id,
+                    transaction_id: None,
+                    merchant_id: None,
+                    kind: ExpenseKind::Purchase,
+                    original_expense_id: None,
+                    project_id: None,
+                })
+                .to_request();
+            expenses.push(test::call_and_read_body_json(&app, req).await);
+        }
+
+        // Closing needs to say who did it
+        let req = test::TestRequest::post()
+            .uri("/api/period-close/")
+            .set_json(NewPeriodClose {
+                account_id: None,
+                closed_through: NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(),
+                closed_by: " ".to_string(),
+            })
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri("/api/period-close/")
+            .set_json(NewPeriodClose {
+                account_id: None,
+                closed_through: NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(),
+                closed_by: "Alex".to_string(),
+            })
+            .to_request();
+        let global_close: PeriodClose = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((None, NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(), "Alex".to_string()), (global_close.account_id.clone(), global_close.closed_through, global_close.closed_by.clone()));
+
+        // Nothing on or before the closed date can be created, changed or deleted
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(new_transaction(12, savings.id.clone(), None))
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/transaction/{}/", balance.id).as_str())
+            .set_json(Transaction { date: NaiveDate::from_ymd_opt(2024, 1, 13).unwrap(), ..balance.clone() })
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/transaction/{}/", balance.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/expense/{}/", expenses[0].id).as_str())
+            .set_json(Expense { date: NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(), ..expenses[0].clone() })
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(new_transaction(13, savings.id.clone(), None))
+            .to_request();
+        assert_eq!(http::StatusCode::OK, test::call_service(&app, req).await.status());
+
+        // A close for one account holds back its transfers and expenses but nothing else
+        let req = test::TestRequest::post()
+            .uri("/api/period-close/")
+            .set_json(NewPeriodClose {
+                account_id: Some(checking.id.clone()),
+                closed_through: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+                closed_by: "Sam".to_string(),
+            })
+            .to_request();
+        let account_close: PeriodClose = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(new_transaction(20, savings.id.clone(), Some(checking.id.clone())))
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/expense/{}/", expenses[0].id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(new_transaction(20, savings.id.clone(), None))
+            .to_request();
+        assert_eq!(http::StatusCode::OK, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/expense/{}/", expenses[1].id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::OK, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::get()
+            .uri("/api/period-close/")
+            .to_request();
+        let period_closes: Vec<PeriodClose> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vec![account_close.clone(), global_close.clone()], period_closes);
+
+        // Reopening the account leaves the earlier close for every account in place
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/period-close/{}/", account_close.id).as_str())
+            .to_request();
+        let _: PeriodClose = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/period-close/{}/", account_close.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::NOT_FOUND, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/expense/{}/", expenses[0].id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::OK, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/transaction/{}/", balance.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+
+        let req = test::TestRequest::get()
+            .uri("/api/audit/?entityType=PERIOD_CLOSE")
+            .to_request();
+        let audit_entries: Vec<AuditEntry> = test::call_and_read_body_json(&app, req).await;
+        let operations: Vec<(AuditOperation, String)> = audit_entries.iter()
+            .map(|audit_entry| (audit_entry.operation, audit_entry.entity_id.clone()))
+            .collect();
+        assert_eq!(vec![
+            (AuditOperation::Delete, account_close.id.clone()),
+            (AuditOperation::Create, account_close.id.clone()),
+            (AuditOperation::Create, global_close.id.clone()),
+        ], operations);
+    }
+
+    #[actix_web::test]
+    async fn test_loans() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for (name, account_type) in [("Checking", AccountType::Checking), ("Car loan", AccountType::Loan)] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let (checking, car_loan) = (accounts[0].clone(), accounts[1].clone());
+        let loan = Loan {
+            account_id: car_loan.id.clone(),
+            principal: Decimal::new(10000, 0),
+            interest_rate: Decimal::new(12, 0),
+            term_months: 12,
+            first_payment_date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+            payment: None,
+        };
+
+        // Only loan accounts can have loan details
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/loan/", checking.id).as_str())
+            .set_json(Loan { account_id: checking.id.clone(), ..loan.clone() })
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/loan/", checking.id).as_str())
+            .set_json(loan.clone())
+            .to_request();
+        assert_eq!(http::StatusCode::BAD_REQUEST, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/loan/", car_loan.id).as_str())
+            .set_json(Loan { term_months: 1201, ..loan.clone() })
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/loan/schedule/", car_loan.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::NOT_FOUND, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/loan/", car_loan.id).as_str())
+            .set_json(loan.clone())
+            .to_request();
+        let saved: Loan = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(loan, saved);
+
+        // Without any balances the schedule runs from the principal over the whole term
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/loan/schedule/", car_loan.id).as_str())
+            .to_request();
+        let schedule: LoanSchedule = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Decimal::new(88849, 2), schedule.payment);
+        assert_eq!((NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(), Decimal::new(10000, 0)), (schedule.anchor_date, schedule.anchor_balance));
+        assert_eq!(12, schedule.payments.len());
+        assert_eq!((1, Decimal::new(10000, 2), Decimal::new(78849, 2), Decimal::new(921151, 2)), (schedule.payments[0].number, schedule.payments[0].interest, schedule.payments[0].principal, schedule.payments[0].balance));
+        assert_eq!(Some(NaiveDate::from_ymd_opt(2024, 12, 31).unwrap()), schedule.payoff_date);
+        assert_eq!((schedule.payoff_date, schedule.total_interest), (schedule.baseline_payoff_date, schedule.baseline_total_interest));
+
+        // Extra payments pay it off sooner with less interest
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/loan/schedule/?extra=200", car_loan.id).as_str())
+            .to_request();
+        let extra_schedule: LoanSchedule = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(10, extra_schedule.payments.len());
+        assert_eq!(Some(NaiveDate::from_ymd_opt(2024, 10, 31).unwrap()), extra_schedule.payoff_date);
+        assert!(extra_schedule.total_interest < extra_schedule.baseline_total_interest);
+        assert_eq!((schedule.payoff_date, schedule.total_interest), (extra_schedule.baseline_payoff_date, extra_schedule.baseline_total_interest));
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/loan/schedule/?extra=-1", car_loan.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+
+        // Payments are split using the interest built up since the last payment or balance
+        for (transaction_type, month, day, value, account_id, from_account_id) in [
+            (TransactionType::Transfer, 1, 31, Decimal::new(88849, 2), car_loan.id.clone(), Some(checking.id.clone())),
+            (TransactionType::Balance, 2, 29, Decimal::new(-9000, 0), car_loan.id.clone(), None),
+            (TransactionType::Transfer, 3, 31, Decimal::new(88849, 2), car_loan.id.clone(), Some(checking.id.clone())),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/transaction/")
+                .set_json(NewTransaction {
+                    description: "".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, month, day).unwrap(),
+                    value,
+                    transaction_type,
+                    account_id,
+                    from_account_id,
+                })
+                .to_request();
+            let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        }
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/loan/payment/", car_loan.id).as_str())
+            .to_request();
+        let loan_payments: Vec<LoanPayment> = test::call_and_read_body_json(&app, req).await;
+        let splits: Vec<(NaiveDate, Decimal, Decimal, Decimal)> = loan_payments.iter()
+            .map(|loan_payment| (loan_payment.transaction.date, loan_payment.interest, loan_payment.principal, loan_payment.balance))
+            .collect();
+        assert_eq!(vec![
+            (NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(), Decimal::new(9173, 2), Decimal::new(79676, 2), Decimal::new(820324, 2)),
+            (NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(), Decimal::new(10192, 2), Decimal::new(78657, 2), Decimal::new(921343, 2)),
+        ], splits);
+
+        // The schedule picks up from the latest recorded balance
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/loan/schedule/", car_loan.id).as_str())
+            .to_request();
+        let schedule: LoanSchedule = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(), Decimal::new(9000, 0)), (schedule.anchor_date, schedule.anchor_balance));
+        assert_eq!((3, NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(), Decimal::new(9000, 2)), (schedule.payments[0].number, schedule.payments[0].date, schedule.payments[0].interest));
+
+        let req = test::TestRequest::delete()
+            .uri(format!("/api/account/{}/loan/", car_loan.id).as_str())
+            .to_request();
+        let _: Loan = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/loan/", car_loan.id).as_str())
+            .to_request();
+        assert_eq!(http::StatusCode::NOT_FOUND, test::call_service(&app, req).await.status());
+    }
+
+    #[actix_web::test]
+    async fn test_statistics() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for (name, account_type) in [("Salary", AccountType::External), ("Checking", AccountType::Checking), ("Savings", AccountType::Savings)] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let (salary, checking, savings) = (accounts[0].clone(), accounts[1].clone(), accounts[2].clone());
+
+        for (date, value, transaction_type, account_id, from_account_id) in [
+            (NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 1000, TransactionType::Balance, checking.id.clone(), None),
+            (NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 500, TransactionType::Balance, savings.id.clone(), None),
+            (NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(), 300, TransactionType::Transfer, checking.id.clone(), Some(salary.id.clone())),
+            (NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), 800, TransactionType::Balance, checking.id.clone(), None),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/transaction/")
+                .set_json(NewTransaction {
+                    description: "".to_string(),
+                    date,
+                    value: Decimal::new(value, 0),
+                    transaction_type,
+                    account_id,
+                    from_account_id,
+                })
+                .to_request();
+            let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        }
+        let req = test::TestRequest::post()
+            .uri("/api/expense/")
+            .set_json(NewExpense {
+                description: "Weekly shop".to_string(),
+                external: "".to_string(),
+                category: "GROCERIES".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 20).unwrap(),
+                value: Decimal::new(500, 0),
+                account_id: Some(checking.id.clone()),
+                transaction_id: None,
+                merchant_id: None,
+                kind: ExpenseKind::Purchase,
+                original_expense_id: None,
+                project_id: None,
+            })
+            .to_request();
+        let _: Expense = test::call_and_read_body_json(&app, req).await;
+
+        // Liquid to monthly spending is always reported, 0 when there is no spending to compare against
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/monthly/allocation/")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        let value = |statistic: &Statistic, name: &str| statistic.values.iter().find(|value| value.name == name).unwrap().clone();
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), statistics[0].date);
+        assert_eq!(Decimal::ZERO, value(&statistics[0], "LIQUID_TO_MONTHLY_SPENDING").value);
+        assert_eq!(Decimal::new(6667, 2), value(&statistics[0], "CASH").value);
+        // 1300 liquid against 500 spent over the 31 days of January
+        assert_eq!(Decimal::new(265, 2), value(&statistics[1], "LIQUID_TO_MONTHLY_SPENDING").value);
+        assert_eq!(Decimal::ZERO, value(&statistics[2], "LIQUID_TO_MONTHLY_SPENDING").value);
+
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/monthly/account_balance/?smoothing=sma&window=2&trend=true")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(None, value(&statistics[0], &checking.id).value_smoothed);
+        assert_eq!(Some(Decimal::new(900, 0)), value(&statistics[1], &checking.id).value_smoothed);
+        assert_eq!(Some(Decimal::new(800, 0)), value(&statistics[2], &checking.id).value_smoothed);
+        assert!(statistics.iter().all(|statistic| value(statistic, &checking.id).value_trend.is_some()));
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/monthly/account_balance/")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        assert!(statistics.iter().all(|statistic| value(statistic, &checking.id).value_trend.is_none()));
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/monthly/account_balance/?smoothing=median")
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+
+        let req = test::TestRequest::get()
+            .uri("/api/sankey/?start=2024-01-01&end=2024-02-01")
+            .to_request();
+        let resp: Sankey = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(3, resp.links.len());
+        assert!(resp.links.contains(&SankeyLink { source: salary.id.clone(), target: checking.id.clone(), value: Decimal::new(300, 0) }));
+        assert!(resp.links.contains(&SankeyLink { source: checking.id.clone(), target: "SPENDING".to_string(), value: Decimal::new(500, 0) }));
+        assert!(resp.links.contains(&SankeyLink { source: "SPENDING".to_string(), target: "GROCERIES".to_string(), value: Decimal::new(500, 0) }));
+        let req = test::TestRequest::get()
+            .uri("/api/sankey/?start=2024-02-01&end=2024-01-01")
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+    }
+
+    #[actix_web::test]
+    async fn test_credit_cards() {
+        let manager = SqliteConnectionManager::memory();
+        run_migrations(&manager);
+        let pool = Pool::new(manager).unwrap();
+
+        info!("Starting test service");
+        let app = test::init_service(app!(pool)).await;
+
+        let mut accounts: Vec<Account> = vec![];
+        for (name, account_type) in [("Checking", AccountType::Checking), ("Card", AccountType::CreditCard)] {
+            let req = test::TestRequest::post()
+                .uri("/api/account/")
+                .set_json(NewAccount {
+                    name: name.to_string(),
+                    account_type,
+                    hide_new_transactions: false,
+                    transfer_without_balance_ignored: false,
+                    no_regular_balance: false,
+                    hidden: false,
+                })
+                .to_request();
+            accounts.push(test::call_and_read_body_json(&app, req).await);
+        }
+        let (checking, card) = (accounts[0].clone(), accounts[1].clone());
+        let credit_card = CreditCard {
+            account_id: card.id.clone(),
+            credit_limit: Decimal::new(1000, 0),
+            statement_day: 31,
+            due_day: 25,
+        };
+
+        // Only credit card accounts can have card details and the days have to fit in a month
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/credit-card/", checking.id).as_str())
+            .set_json(CreditCard { account_id: checking.id.clone(), ..credit_card.clone() })
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/credit-card/", card.id).as_str())
+            .set_json(CreditCard { due_day: 32, ..credit_card.clone() })
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri(format!("/api/account/{}/credit-card/", card.id).as_str())
+            .set_json(credit_card.clone())
+            .to_request();
+        let saved: CreditCard = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(credit_card, saved);
+        let req = test::TestRequest::get()
+            .uri(format!("/api/account/{}/credit-card/", card.id).as_str())
+            .to_request();
+        let saved: CreditCard = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(credit_card, saved);
+
+        // A balance owed in the latest statement cycle that is already due needs paying and is half the limit,
+        // statements close at the end of the month and are due on the 25th of the next
+        let today = Local::now().date_naive();
+        let balance_date = today.with_day(15).unwrap() - Months::new(if today.day() > 25 { 1 } else { 2 });
+        for (transaction_type, account_id, value) in [
+            (TransactionType::Balance, checking.id.clone(), Decimal::new(2000, 0)),
+            (TransactionType::Balance, card.id.clone(), Decimal::new(-500, 0)),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/transaction/")
+                .set_json(NewTransaction {
+                    description: "".to_string(),
+                    date: balance_date,
+                    value,
+                    transaction_type,
+                    account_id,
+                    from_account_id: None,
+                })
+                .to_request();
+            let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        }
+        let card_issues = || async {
+            let req = test::TestRequest::get()
+                .uri("/api/issue/")
+                .to_request();
+            let issues: Vec<Issue> = test::call_and_read_body_json(&app, req).await;
+            return issues.into_iter()
+                .filter(|issue| issue.issue_type == IssueType::CreditCardPaymentMissing || issue.issue_type == IssueType::CreditUtilisationHigh)
+                .collect::<Vec<Issue>>();
+        };
+        let issues = card_issues().await;
+        assert_eq!(2, issues.len());
+        let payment_missing = issues.iter().find(|issue| issue.issue_type == IssueType::CreditCardPaymentMissing).unwrap().clone();
+        let utilisation_high = issues.iter().find(|issue| issue.issue_type == IssueType::CreditUtilisationHigh).unwrap().clone();
+        assert_eq!(Some(balance_date), utilisation_high.date);
+        assert!(payment_missing.date.unwrap() > balance_date);
+        assert_eq!(25, payment_missing.date.unwrap().day());
+
+        // Paying by the due date and raising the threshold clears both
+        let req = test::TestRequest::post()
+            .uri("/api/transaction/")
+            .set_json(NewTransaction {
+                description: "".to_string(),
+                date: payment_missing.date.unwrap(),
+                value: Decimal::new(500, 0),
+                transaction_type: TransactionType::Transfer,
+                account_id: card.id.clone(),
+                from_account_id: Some(checking.id.clone()),
+            })
+            .to_request();
+        let _: Transaction = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/setting/")
+            .set_json(NewSetting {
+                key: SettingKey::CreditUtilisationThreshold,
+                value: "0".to_string(),
+            })
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        let req = test::TestRequest::post()
+            .uri("/api/setting/")
+            .set_json(NewSetting {
+                key: SettingKey::CreditUtilisationThreshold,
+                value: "60".to_string(),
+            })
+            .to_request();
+        let threshold: Setting = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(Vec::<Issue>::new(), card_issues().await);
+
+        // A threshold that isn't a number is refused, and one already stored falls back to the default
+        let req = test::TestRequest::post()
+            .uri(format!("/api/setting/{}/", threshold.id).as_str())
+            .set_json(Setting { value: "lots".to_string(), ..threshold.clone() })
+            .to_request();
+        assert_eq!(http::StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        pool.get().unwrap().execute("UPDATE setting SET value = 'lots' WHERE id = ?1", [threshold.id.clone()]).unwrap();
+        let issues = card_issues().await;
+        assert_eq!(vec![IssueType::CreditUtilisationHigh], issues.iter().map(|issue| issue.issue_type.clone()).collect::<Vec<IssueType>>());
+        pool.get().unwrap().execute("UPDATE setting SET value = '60' WHERE id = ?1", [threshold.id.clone()]).unwrap();
+
+        // Utilisation over time only follows balances
+        let req = test::TestRequest::get()
+            .uri("/api/statistics/monthly/credit_utilisation/")
+            .to_request();
+        let statistics: Vec<Statistic> = test::call_and_read_body_json(&app, req).await;
+        let latest = statistics.last().unwrap();
+        let utilisation: Vec<(String, Decimal)> = latest.values.iter()
+            .map(|value| (value.name.clone(), value.value))
+            .collect();
+        assert!(utilisation.contains(&(card.id.clone(), Decimal::new(5000, 2))));
+        assert!(utilisation.contains(&("TOTAL".to_string(), Decimal::new(5000, 2))));
+        assert!(!utilisation.iter().any(|(name, _)| name == &checking.id));
+    }
 }
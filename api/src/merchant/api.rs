@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, HashMap};
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use log::{error, info};
+use rust_decimal::Decimal;
+use crate::merchant::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+use crate::expense::db::list_expenses;
+use crate::expense::schema::Expense;
+use crate::expense_category::db::{ancestors_by_expense_category_id, list_expense_categories};
+use crate::expense_category::schema::ExpenseCategory;
+use crate::merchant::schema::{CategoryTopMerchants, MerchantSpending};
+
+const DEFAULT_TOP_MERCHANTS: usize = 5;
+
+#[post("/api/merchant/")]
+pub async fn create_merchant(db: web::Data<Pool>, new_merchant: web::Json<schema::NewMerchant>) -> Result<HttpResponse, Error> {
+    let new_merchant = new_merchant.into_inner();
+    info!("HTTP create_merchant new_merchant:[{:?}]", new_merchant);
+    do_in_transaction(&db, |transaction| db::create_merchant(transaction, new_merchant))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_merchant: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[post("/api/merchant/{id}/merge/")]
+pub async fn merge_merchants(db: web::Data<Pool>, path: web::Path<String>, merchant_merge: web::Json<schema::MerchantMerge>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let merchant_merge = merchant_merge.into_inner();
+    info!("HTTP merge_merchants id:[{}] merchant_merge:[{:?}]", id, merchant_merge);
+    do_in_transaction(&db, |transaction| db::merge_merchants(transaction, id, merchant_merge))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP merge_merchants: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/merchant/top/")]
+pub async fn top_merchants(db: web::Data<Pool>, query: web::Query<schema::TopMerchantsQuery>) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    info!("HTTP top_merchants [{query:?}]");
+    do_in_transaction(&db, move |transaction| {
+        if query.end < query.start {
+            return Err(anyhow!("End {} cannot be before start {}", query.end, query.start));
+        }
+        let expenses = list_expenses(transaction)?;
+        let expense_categories = list_expense_categories(transaction)?;
+        return Ok(calculate_top_merchants(&expenses, &expense_categories, query.start, query.end, query.limit.unwrap_or(DEFAULT_TOP_MERCHANTS)));
+    })
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP top_merchants: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[post("/api/merchant/{id}/")]
+pub async fn update_merchant(db: web::Data<Pool>, path: web::Path<String>, updated_merchant: web::Json<schema::Merchant>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let updated_merchant = updated_merchant.into_inner();
+    info!("HTTP update_merchant id:[{}] updated_merchant:[{:?}]", id, updated_merchant);
+    let option = crate::api::compare_ids(&id, &updated_merchant.id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::update_merchant(transaction, updated_merchant))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP update_merchant: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/merchant/{id}/")]
+pub async fn delete_merchant(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_merchant id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_merchant(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_merchant: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/merchant/{id}/")]
+pub async fn get_merchant(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_merchant id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_merchant(transaction, id))
+        .await.map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_merchant: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/merchant/")]
+pub async fn list_merchants(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_merchants");
+    do_in_transaction(&db, |transaction| db::list_merchants(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_merchants: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+// Spending on a subcategory also counts towards every category above it
+fn calculate_top_merchants(expenses: &[Expense], expense_categories: &[ExpenseCategory], start: NaiveDate, end: NaiveDate, limit: usize) -> Vec<CategoryTopMerchants> {
+    let ancestors_by_category = ancestors_by_expense_category_id(expense_categories);
+    let mut spending_by_category: BTreeMap<String, HashMap<String, (Decimal, usize)>> = BTreeMap::new();
+    for expense in expenses {
+        if expense.date < start || end < expense.date {
+            continue;
+        }
+        let Some(merchant_id) = &expense.merchant_id else {
+            continue;
+        };
+        let categories = ancestors_by_category.get(&expense.category).cloned().unwrap_or(vec![expense.category.clone()]);
+        for category in categories {
+            let (value, count) = spending_by_category.entry(category).or_default()
+                .entry(merchant_id.clone()).or_insert((Decimal::ZERO, 0));
+            *value += expense.value;
+            *count += 1;
+        }
+    }
+    return spending_by_category.into_iter()
+        .map(|(category_id, spending_by_merchant)| {
+            let mut merchants: Vec<MerchantSpending> = spending_by_merchant.into_iter()
+                .map(|(merchant_id, (value, count))| MerchantSpending { merchant_id, value, count })
+                .collect();
+            merchants.sort_by(|one, two| two.value.cmp(&one.value).then(one.merchant_id.cmp(&two.merchant_id)));
+            merchants.truncate(limit);
+            return CategoryTopMerchants { category_id, merchants };
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expense_category(id: &str, parent_id: Option<&str>) -> ExpenseCategory {
+        ExpenseCategory {
+            id: id.to_string(),
+            name: id.to_string(),
+            parent_id: parent_id.map(|parent_id| parent_id.to_string()),
+            colour: None,
+            hidden: false,
+        }
+    }
+
+    #[test]
+    fn test_calculate_top_merchants() {
+        let expenses = vec![
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(), merchant_id: Some("cafe".to_string()), ..Expense::for_test("1", "COFFEE", Decimal::new(5, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(), merchant_id: Some("cafe".to_string()), ..Expense::for_test("2", "COFFEE", Decimal::new(5, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 4).unwrap(), merchant_id: Some("diner".to_string()), ..Expense::for_test("3", "RESTAURANTS", Decimal::new(30, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(), merchant_id: Some("bistro".to_string()), ..Expense::for_test("4", "RESTAURANTS", Decimal::new(20, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 6).unwrap(), merchant_id: None, ..Expense::for_test("5", "RESTAURANTS", Decimal::new(100, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 20).unwrap(), merchant_id: Some("bistro".to_string()), ..Expense::for_test("6", "RESTAURANTS", Decimal::new(100, 0)) },
+        ];
+        let expense_categories = vec![
+            expense_category("FOOD", None),
+            expense_category("COFFEE", Some("FOOD")),
+            expense_category("RESTAURANTS", Some("FOOD")),
+        ];
+
+        let top = calculate_top_merchants(&expenses, &expense_categories, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(), 2);
+        let merchants = |category_id: &str| top.iter()
+            .find(|category| category.category_id == category_id)
+            .map(|category| category.merchants.iter().map(|merchant| (merchant.merchant_id.as_str(), merchant.value, merchant.count)).collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(vec![("cafe", Decimal::new(10, 0), 2)], merchants("COFFEE"));
+        assert_eq!(vec![("diner", Decimal::new(30, 0), 1), ("bistro", Decimal::new(20, 0), 1)], merchants("RESTAURANTS"));
+        // The cafe is cut by the limit once both subcategories roll up
+        assert_eq!(vec![("diner", Decimal::new(30, 0), 1), ("bistro", Decimal::new(20, 0), 1)], merchants("FOOD"));
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use anyhow::anyhow;
+use regex::Regex;
+use rusqlite::Transaction;
+use uuid::Uuid;
+use crate::db::list;
+use crate::expense::db::relink_expenses;
+use crate::expense::schema::Expense;
+use crate::merchant::schema::{Merchant, MerchantMatchRow, MerchantMerge, MerchantRow, NewMerchant};
+
+const ALIAS: &str = "ALIAS";
+const PATTERN: &str = "PATTERN";
+
+pub fn create_merchant(transaction: &Transaction, new_merchant: NewMerchant) -> anyhow::Result<Option<Merchant>> {
+    verify_patterns(&new_merchant.patterns)?;
+    let id = Uuid::new_v4().to_string();
+    transaction.execute("INSERT INTO merchant (id, name) VALUES (?1, ?2)", [id.clone(), new_merchant.name])?;
+    insert_matches(transaction, &id, new_merchant.aliases, new_merchant.patterns)?;
+    assign_unmatched_expenses(transaction)?;
+    return get_merchant(transaction, id);
+}
+
+pub fn update_merchant(transaction: &Transaction, updated_merchant: Merchant) -> anyhow::Result<Option<Merchant>> {
+    verify_patterns(&updated_merchant.patterns)?;
+    let updated = transaction.execute(
+        "UPDATE merchant SET name = ?1 WHERE id = ?2",
+        [updated_merchant.name, updated_merchant.id.clone()],
+    )?;
+    if updated == 0 {
+        return Ok(None);
+    }
+    transaction.execute("DELETE FROM merchant_match WHERE merchant_id = ?1", [updated_merchant.id.clone()])?;
+    insert_matches(transaction, &updated_merchant.id, updated_merchant.aliases, updated_merchant.patterns)?;
+    assign_unmatched_expenses(transaction)?;
+    return get_merchant(transaction, updated_merchant.id);
+}
+
+pub fn delete_merchant(transaction: &Transaction, id: String) -> anyhow::Result<Option<Merchant>> {
+    let merchant = get_merchant(transaction, id.clone())?;
+    if merchant.is_some() {
+        crate::subscription::db::cascade_delete_merchant(transaction, id.clone())?;
+        relink_expenses(transaction, "merchant_id = ?1", [id.clone()], |expense| Expense { merchant_id: None, ..expense })?;
+        transaction.execute("DELETE FROM merchant_match WHERE merchant_id = ?1", [id.clone()])?;
+        transaction.execute("DELETE FROM merchant WHERE id = ?1", [id])?;
+    }
+    return Ok(merchant);
+}
+
+pub fn get_merchant(transaction: &Transaction, id: String) -> anyhow::Result<Option<Merchant>> {
+    return Ok(list_merchants(transaction)?.into_iter().find(|merchant| merchant.id == id));
+}
+
+pub fn list_merchants(transaction: &Transaction) -> anyhow::Result<Vec<Merchant>> {
+    let merchant_rows: Vec<MerchantRow> = list(transaction, "SELECT id, name FROM merchant ORDER BY name, id ASC", [])?;
+    let merchant_match_rows: Vec<MerchantMatchRow> = list(transaction, "SELECT merchant_id, kind, value FROM merchant_match ORDER BY merchant_id, kind, value ASC", [])?;
+    let mut matches_by_merchant_id: HashMap<String, (Vec<String>, Vec<String>)> = HashMap::new();
+    for merchant_match_row in merchant_match_rows {
+        let (aliases, patterns) = matches_by_merchant_id.entry(merchant_match_row.merchant_id).or_default();
+        if merchant_match_row.kind == ALIAS {
+            aliases.push(merchant_match_row.value);
+        } else {
+            patterns.push(merchant_match_row.value);
+        }
+    }
+    return Ok(merchant_rows.into_iter()
+        .map(|merchant_row| {
+            let (aliases, patterns) = matches_by_merchant_id.remove(&merchant_row.id).unwrap_or_default();
+            return Merchant { id: merchant_row.id, name: merchant_row.name, aliases, patterns };
+        })
+        .collect());
+}
+
+// Moves every alias, pattern and expense into the target, the old names become aliases so they keep matching
+pub fn merge_merchants(transaction: &Transaction, id: String, merchant_merge: MerchantMerge) -> anyhow::Result<Option<Merchant>> {
+    if get_merchant(transaction, id.clone())?.is_none() {
+        return Ok(None);
+    }
+    for merchant_id in merchant_merge.merchant_ids {
+        if merchant_id == id {
+            continue;
+        }
+        let merchant = get_merchant(transaction, merchant_id.clone())?
+            .ok_or(anyhow!("Merchant {} does not exist", merchant_id))?;
+        transaction.execute("INSERT OR IGNORE INTO merchant_match (merchant_id, kind, value) SELECT ?1, kind, value FROM merchant_match WHERE merchant_id = ?2", [id.clone(), merchant_id.clone()])?;
+        transaction.execute("INSERT OR IGNORE INTO merchant_match (merchant_id, kind, value) VALUES (?1, ?2, ?3)", [id.clone(), ALIAS.to_string(), merchant.name])?;
+        relink_expenses(transaction, "merchant_id = ?1", [merchant_id.clone()], |expense| Expense { merchant_id: Some(id.clone()), ..expense })?;
+        crate::subscription::db::cascade_merge_merchant(transaction, merchant_id.clone(), id.clone())?;
+        delete_merchant(transaction, merchant_id)?;
+    }
+    return get_merchant(transaction, id);
+}
+
+pub fn resolve_merchant_id(transaction: &Transaction, external: &str) -> anyhow::Result<Option<String>> {
+    let merchants = list_merchants(transaction)?;
+    return Ok(match_merchant(&merchants, &compile_patterns(&merchants), external));
+}
+
+pub fn verify_merchant_id_exists(transaction: &Transaction, id: String) -> anyhow::Result<()> {
+    let result = get_merchant(transaction, id.clone())?;
+    if result.is_none() {
+        return Err(anyhow!("Merchant {} does not exist", id));
+    }
+    return Ok(());
+}
+
+// Compiled once per lookup rather than once per expense
+pub fn compile_patterns(merchants: &[Merchant]) -> Vec<(String, Vec<Regex>)> {
+    return merchants.iter()
+        .map(|merchant| (merchant.id.clone(), merchant.patterns.iter().filter_map(|pattern| Regex::new(pattern).ok()).collect()))
+        .collect();
+}
+
+// Aliases are checked across every merchant before any pattern
+pub fn match_merchant(merchants: &[Merchant], patterns: &[(String, Vec<Regex>)], external: &str) -> Option<String> {
+    let external = external.trim();
+    if external.is_empty() {
+        return None;
+    }
+    let by_alias = merchants.iter()
+        .find(|merchant| merchant.aliases.iter().any(|alias| alias.trim().eq_ignore_ascii_case(external)));
+    if let Some(merchant) = by_alias {
+        return Some(merchant.id.clone());
+    }
+    return patterns.iter()
+        .find(|(_, regexes)| regexes.iter().any(|regex| regex.is_match(external)))
+        .map(|(merchant_id, _)| merchant_id.clone());
+}
+
+// Expenses saved before a merchant existed pick it up once it does
+fn assign_unmatched_expenses(transaction: &Transaction) -> anyhow::Result<()> {
+    let merchants = list_merchants(transaction)?;
+    let patterns = compile_patterns(&merchants);
+    let mut statement = transaction.prepare("SELECT id, external FROM expense WHERE merchant_id IS NULL")?;
+    let unmatched: Vec<(String, String)> = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (expense_id, external) in unmatched {
+        if let Some(merchant_id) = match_merchant(&merchants, &patterns, &external) {
+            relink_expenses(transaction, "id = ?1", [expense_id], |expense| Expense { merchant_id: Some(merchant_id.clone()), ..expense })?;
+        }
+    }
+    return Ok(());
+}
+
+fn insert_matches(transaction: &Transaction, merchant_id: &str, aliases: Vec<String>, patterns: Vec<String>) -> anyhow::Result<()> {
+    for (kind, values) in [(ALIAS, aliases), (PATTERN, patterns)] {
+        for value in values {
+            transaction.execute(
+                "INSERT OR IGNORE INTO merchant_match (merchant_id, kind, value) VALUES (?1, ?2, ?3)",
+                [merchant_id.to_string(), kind.to_string(), value],
+            )?;
+        }
+    }
+    return Ok(());
+}
+
+fn verify_patterns(patterns: &[String]) -> anyhow::Result<()> {
+    for pattern in patterns {
+        Regex::new(pattern).map_err(|err| anyhow!("Pattern {} is not a valid regex: {}", pattern, err))?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_merchant() {
+        let merchants = vec![
+            Merchant { id: "amazon".to_string(), name: "Amazon".to_string(), aliases: vec!["Amazon.com".to_string()], patterns: vec!["^AMZN".to_string()] },
+            Merchant { id: "marketplace".to_string(), name: "Marketplace".to_string(), aliases: vec!["AMZN Mktp US*2K4".to_string()], patterns: vec![] },
+        ];
+        let patterns = compile_patterns(&merchants);
+
+        assert_eq!(Some("amazon".to_string()), match_merchant(&merchants, &patterns, "amazon.com "));
+        // Aliases win over patterns from an earlier merchant
+        assert_eq!(Some("marketplace".to_string()), match_merchant(&merchants, &patterns, "AMZN Mktp US*2K4"));
+        assert_eq!(Some("amazon".to_string()), match_merchant(&merchants, &patterns, "AMZN Mktp US*9Z1"));
+        assert_eq!(None, match_merchant(&merchants, &patterns, "Tesco"));
+    }
+}
@@ -0,0 +1,84 @@
+use chrono::NaiveDate;
+use rusqlite::Row;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use crate::db::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Merchant {
+    pub id: String,
+    pub name: String,
+    // Matched against the whole external string ignoring case
+    pub aliases: Vec<String>,
+    // Regexes matched anywhere in the external string, tried after every alias
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewMerchant {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MerchantMerge {
+    // Merged into the merchant in the path and then deleted
+    #[serde(rename = "merchantIds")]
+    pub merchant_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TopMerchantsQuery {
+    // Inclusive on both ends
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    // Defaults to 5 merchants per category
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CategoryTopMerchants {
+    #[serde(rename = "categoryId")]
+    pub category_id: String,
+    pub merchants: Vec<MerchantSpending>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MerchantSpending {
+    #[serde(rename = "merchantId")]
+    pub merchant_id: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    pub count: usize,
+}
+
+pub struct MerchantRow {
+    pub id: String,
+    pub name: String,
+}
+
+pub struct MerchantMatchRow {
+    pub merchant_id: String,
+    pub kind: String,
+    pub value: String,
+}
+
+impl FromRow for MerchantRow {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(MerchantRow {
+            id: row.get("id")?,
+            name: row.get("name")?,
+        })
+    }
+}
+
+impl FromRow for MerchantMatchRow {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(MerchantMatchRow {
+            merchant_id: row.get("merchant_id")?,
+            kind: row.get("kind")?,
+            value: row.get("value")?,
+        })
+    }
+}
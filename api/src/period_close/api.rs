@@ -0,0 +1,56 @@
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use log::{error, info};
+use crate::period_close::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+
+#[post("/api/period-close/")]
+pub async fn create_period_close(db: web::Data<Pool>, new_period_close: web::Json<schema::NewPeriodClose>) -> Result<HttpResponse, Error> {
+    let new_period_close = new_period_close.into_inner();
+    info!("HTTP create_period_close new_period_close:[{:?}]", new_period_close);
+    do_in_transaction(&db, |transaction| db::create_period_close(transaction, new_period_close))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_period_close: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+// Reopens the period
+#[delete("/api/period-close/{id}/")]
+pub async fn delete_period_close(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_period_close id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_period_close(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_period_close: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/period-close/{id}/")]
+pub async fn get_period_close(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_period_close id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_period_close(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_period_close: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/period-close/")]
+pub async fn list_period_closes(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_period_closes");
+    do_in_transaction(&db, |transaction| db::list_period_closes(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_period_closes: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
@@ -0,0 +1,124 @@
+use std::str::FromStr;
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use const_format::formatcp;
+use rusqlite::{params, Transaction};
+use uuid::Uuid;
+use crate::account::db::verify_account_id_exists;
+use crate::audit::db::{record_create, record_delete, record_update};
+use crate::audit::schema::AuditEntityType;
+use crate::db::{list, single, timestamp};
+use crate::period_close::schema::{NewPeriodClose, PeriodClose};
+
+const PERIOD_CLOSE_COLUMNS: &str = "id, account_id, closed_through, closed_by, closed_at";
+const PERIOD_CLOSE_SELECT: &str = formatcp!("SELECT {PERIOD_CLOSE_COLUMNS} FROM period_close");
+const PERIOD_CLOSE_RETURNING: &str = formatcp!("RETURNING {PERIOD_CLOSE_COLUMNS}");
+const PERIOD_CLOSE_ORDERING: &str = "ORDER BY closed_through DESC, closed_at DESC";
+
+pub fn create_period_close(transaction: &Transaction, new_period_close: NewPeriodClose) -> anyhow::Result<Option<PeriodClose>> {
+    if new_period_close.closed_by.trim().is_empty() {
+        return Err(anyhow!("Closed by must be non empty"));
+    }
+    if let Some(account_id) = new_period_close.account_id.clone() {
+        verify_account_id_exists(transaction, account_id)?;
+    }
+    let period_close: Option<PeriodClose> = single(
+        transaction,
+        formatcp!("INSERT INTO period_close ({PERIOD_CLOSE_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5) {PERIOD_CLOSE_RETURNING}"),
+        params![Uuid::new_v4().to_string(), new_period_close.account_id, new_period_close.closed_through.to_string(), new_period_close.closed_by.trim(), timestamp()],
+    )?;
+    if let Some(period_close) = &period_close {
+        record_create(transaction, AuditEntityType::PeriodClose, &period_close.id, period_close)?;
+    }
+    return Ok(period_close);
+}
+
+// Reopening removes the close, any earlier close for the same accounts applies again
+pub fn delete_period_close(transaction: &Transaction, id: String) -> anyhow::Result<Option<PeriodClose>> {
+    let period_close: Option<PeriodClose> = single(
+        transaction,
+        formatcp!("DELETE FROM period_close WHERE id = ?1 {PERIOD_CLOSE_RETURNING}"),
+        [id],
+    )?;
+    if let Some(period_close) = &period_close {
+        record_delete(transaction, AuditEntityType::PeriodClose, &period_close.id, period_close)?;
+    }
+    return Ok(period_close);
+}
+
+pub fn get_period_close(transaction: &Transaction, id: String) -> anyhow::Result<Option<PeriodClose>> {
+    return single(
+        transaction,
+        formatcp!("{PERIOD_CLOSE_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn list_period_closes(transaction: &Transaction) -> anyhow::Result<Vec<PeriodClose>> {
+    return list(
+        transaction,
+        formatcp!("{PERIOD_CLOSE_SELECT} {PERIOD_CLOSE_ORDERING}"),
+        [],
+    );
+}
+
+// The latest date closed for any of the accounts, closes without an account count for all of them
+pub fn closed_through(transaction: &Transaction, account_ids: &[String]) -> anyhow::Result<Option<NaiveDate>> {
+    let mut account_ids: Vec<Option<&String>> = account_ids.iter().map(Some).collect();
+    if account_ids.is_empty() {
+        account_ids.push(None);
+    }
+    let mut latest: Option<NaiveDate> = None;
+    for account_id in account_ids {
+        let closed_through: Option<String> = transaction.query_row(
+            "SELECT MAX(closed_through) FROM period_close WHERE account_id IS NULL OR account_id = ?1",
+            params![account_id],
+            |row| row.get(0),
+        )?;
+        if let Some(closed_through) = closed_through {
+            latest = latest.max(Some(NaiveDate::from_str(&closed_through)?));
+        }
+    }
+    return Ok(latest);
+}
+
+pub fn verify_open(transaction: &Transaction, date: NaiveDate, account_ids: &[String]) -> anyhow::Result<()> {
+    if let Some(closed_through) = closed_through(transaction, account_ids)? {
+        if date <= closed_through {
+            return Err(anyhow!("Date {} is in a period closed through {}", date, closed_through));
+        }
+    }
+    return Ok(());
+}
+
+// Closes carry over so the merged transactions stay closed
+pub fn cascade_merge_account(transaction: &Transaction, account_id: String, target_account_id: String) -> anyhow::Result<()> {
+    let previous_period_closes: Vec<PeriodClose> = list(
+        transaction,
+        formatcp!("{PERIOD_CLOSE_SELECT} WHERE account_id = ?1"),
+        [account_id.clone()],
+    )?;
+    for previous_period_close in previous_period_closes {
+        let period_close: Option<PeriodClose> = single(
+            transaction,
+            formatcp!("UPDATE period_close SET account_id = ?1 WHERE id = ?2 {PERIOD_CLOSE_RETURNING}"),
+            [target_account_id.clone(), previous_period_close.id.clone()],
+        )?;
+        if let Some(period_close) = &period_close {
+            record_update(transaction, AuditEntityType::PeriodClose, &period_close.id, Some(&previous_period_close), period_close)?;
+        }
+    }
+    return Ok(());
+}
+
+pub fn cascade_delete_account(transaction: &Transaction, account_id: String) -> anyhow::Result<()> {
+    let period_closes: Vec<PeriodClose> = list(
+        transaction,
+        formatcp!("DELETE FROM period_close WHERE account_id = ?1 {PERIOD_CLOSE_RETURNING}"),
+        [account_id],
+    )?;
+    for period_close in period_closes {
+        record_delete(transaction, AuditEntityType::PeriodClose, &period_close.id, &period_close)?;
+    }
+    return Ok(());
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use crate::db::{FromRow, get_date_time, get_naive_date};
+
+// Nothing on or before the date can be changed, for one account or for every account when there isn't one
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PeriodClose {
+    pub id: String,
+    #[serde(rename = "accountId")]
+    pub account_id: Option<String>,
+    #[serde(rename = "closedThrough")]
+    pub closed_through: NaiveDate,
+    #[serde(rename = "closedBy")]
+    pub closed_by: String,
+    #[serde(rename = "closedAt")]
+    pub closed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewPeriodClose {
+    #[serde(rename = "accountId")]
+    pub account_id: Option<String>,
+    #[serde(rename = "closedThrough")]
+    pub closed_through: NaiveDate,
+    #[serde(rename = "closedBy")]
+    pub closed_by: String,
+}
+
+impl FromRow for PeriodClose {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(PeriodClose {
+            id: row.get("id")?,
+            account_id: row.get("account_id")?,
+            closed_through: get_naive_date(row, "closed_through")?,
+            closed_by: row.get("closed_by")?,
+            closed_at: get_date_time(row, "closed_at")?,
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use log::{error, info};
+use crate::person::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+
+#[post("/api/person/")]
+pub async fn create_person(db: web::Data<Pool>, new_person: web::Json<schema::NewPerson>) -> Result<HttpResponse, Error> {
+    let new_person = new_person.into_inner();
+    info!("HTTP create_person new_person:[{:?}]", new_person);
+    do_in_transaction(&db, |transaction| db::create_person(transaction, new_person))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_person: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+
+#[post("/api/person/{id}/")]
+pub async fn update_person(db: web::Data<Pool>, path: web::Path<String>, updated_person: web::Json<schema::Person>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let updated_person = updated_person.into_inner();
+    info!("HTTP update_person id:[{}] updated_person:[{:?}]", id, updated_person);
+    let option = crate::api::compare_ids(&id, &updated_person.id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::update_person(transaction, updated_person))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP update_person: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/person/{id}/")]
+pub async fn delete_person(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_person id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_person(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_person: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/person/{id}/")]
+pub async fn get_person(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_person id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_person(transaction, id))
+        .await.map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_person: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/person/")]
+pub async fn list_persons(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_persons");
+    do_in_transaction(&db, |transaction| db::list_persons(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_persons: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
\ No newline at end of file
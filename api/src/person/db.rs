@@ -0,0 +1,84 @@
+use anyhow::anyhow;
+use const_format::formatcp;
+use rusqlite::{params, Transaction};
+use uuid::Uuid;
+use crate::account::db::verify_account_id_exists;
+use crate::db::{list, single};
+use crate::person::schema::{NewPerson, Person};
+
+const PERSON_COLUMNS: &str = "id, name, me, account_id";
+const PERSON_SELECT: &str = formatcp!("SELECT {PERSON_COLUMNS} FROM person");
+const PERSON_RETURNING: &str = formatcp!("RETURNING {PERSON_COLUMNS}");
+const PERSON_ORDERING: &str = "ORDER BY name, id ASC";
+
+pub fn create_person(transaction: &Transaction, new_person: NewPerson) -> anyhow::Result<Option<Person>> {
+    let id = Uuid::new_v4().to_string();
+    verify(transaction, &id, new_person.me, new_person.account_id.clone())?;
+    return single(
+        transaction,
+        formatcp!("INSERT INTO person ({PERSON_COLUMNS}) VALUES (?1, ?2, ?3, ?4) {PERSON_RETURNING}"),
+        params![id, new_person.name, new_person.me as i32, new_person.account_id],
+    );
+}
+
+pub fn update_person(transaction: &Transaction, updated_person: Person) -> anyhow::Result<Option<Person>> {
+    verify(transaction, &updated_person.id, updated_person.me, updated_person.account_id.clone())?;
+    return single(
+        transaction,
+        formatcp!("UPDATE person SET name = ?1, me = ?2, account_id = ?3 WHERE id = ?4 {PERSON_RETURNING}"),
+        params![updated_person.name, updated_person.me as i32, updated_person.account_id, updated_person.id],
+    );
+}
+
+pub fn delete_person(transaction: &Transaction, id: String) -> anyhow::Result<Option<Person>> {
+    crate::household::db::verify_person_unused(transaction, id.clone())?;
+    return single(
+        transaction,
+        formatcp!("DELETE FROM person WHERE id = ?1 {PERSON_RETURNING}"),
+        [id],
+    );
+}
+
+pub fn get_person(transaction: &Transaction, id: String) -> anyhow::Result<Option<Person>> {
+    return single(
+        transaction,
+        formatcp!("{PERSON_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn list_persons(transaction: &Transaction) -> anyhow::Result<Vec<Person>> {
+    return list(
+        transaction,
+        formatcp!("{PERSON_SELECT} {PERSON_ORDERING}"),
+        [],
+    );
+}
+
+pub fn verify_person_id_exists(transaction: &Transaction, id: String) -> anyhow::Result<()> {
+    let result = get_person(transaction, id.clone())?;
+    if result.is_none() {
+        return Err(anyhow!("Person {} does not exist", id));
+    }
+    return Ok(());
+}
+
+pub fn cascade_delete_account(transaction: &Transaction, account_id: String) -> anyhow::Result<()> {
+    transaction.execute("UPDATE person SET account_id = NULL WHERE account_id = ?1", [account_id])?;
+    return Ok(());
+}
+
+pub fn cascade_merge_account(transaction: &Transaction, account_id: String, target_account_id: String) -> anyhow::Result<()> {
+    transaction.execute("UPDATE person SET account_id = ?2 WHERE account_id = ?1", [account_id, target_account_id])?;
+    return Ok(());
+}
+
+fn verify(transaction: &Transaction, id: &str, me: bool, account_id: Option<String>) -> anyhow::Result<()> {
+    if let Some(account_id) = account_id {
+        verify_account_id_exists(transaction, account_id)?;
+    }
+    if me && list_persons(transaction)?.iter().any(|person| person.me && person.id != id) {
+        return Err(anyhow!("Another person is already me"));
+    }
+    return Ok(());
+}
@@ -0,0 +1,33 @@
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use crate::db::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Person {
+    pub id: String,
+    pub name: String,
+    // The person whose share is reported by statistics, only one person can be me
+    pub me: bool,
+    // Where settlements to and from this person are recorded
+    #[serde(rename = "accountId")]
+    pub account_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewPerson {
+    pub name: String,
+    pub me: bool,
+    #[serde(rename = "accountId")]
+    pub account_id: Option<String>,
+}
+
+impl FromRow for Person {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Person {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            me: row.get::<_, i32>("me")? != 0,
+            account_id: row.get("account_id")?,
+        })
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use log::{error, info};
+use rust_decimal::Decimal;
+use crate::project::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+use crate::expense::api::spending;
+use crate::expense::db::list_expenses;
+use crate::expense::schema::Expense;
+use crate::project::schema::{Project, ProjectCategorySummary, ProjectSummary};
+
+#[post("/api/project/")]
+pub async fn create_project(db: web::Data<Pool>, new_project: web::Json<schema::NewProject>) -> Result<HttpResponse, Error> {
+    let new_project = new_project.into_inner();
+    info!("HTTP create_project new_project:[{:?}]", new_project);
+    do_in_transaction(&db, |transaction| db::create_project(transaction, new_project))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_project: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+
+#[post("/api/project/{id}/")]
+pub async fn update_project(db: web::Data<Pool>, path: web::Path<String>, updated_project: web::Json<schema::Project>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let updated_project = updated_project.into_inner();
+    info!("HTTP update_project id:[{}] updated_project:[{:?}]", id, updated_project);
+    let option = crate::api::compare_ids(&id, &updated_project.id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::update_project(transaction, updated_project))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP update_project: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/project/{id}/")]
+pub async fn delete_project(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_project id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_project(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_project: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/project/{id}/summary/")]
+pub async fn get_project_summary(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_project_summary id:[{}]", id);
+    do_in_transaction(&db, |transaction| {
+        let project = db::get_project(transaction, id)?;
+        let expenses = list_expenses(transaction)?;
+        return Ok(project.map(|project| calculate_project_summary(&project, expenses)));
+    })
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_project_summary: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/project/{id}/")]
+pub async fn get_project(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_project id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_project(transaction, id))
+        .await.map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_project: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/project/")]
+pub async fn list_projects(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_projects");
+    do_in_transaction(&db, |transaction| db::list_projects(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_projects: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+fn calculate_project_summary(project: &Project, expenses: Vec<Expense>) -> ProjectSummary {
+    let mut category_summaries: HashMap<String, ProjectCategorySummary> = HashMap::new();
+    for expense in spending(expenses, false) {
+        if expense.project_id.as_ref() != Some(&project.id) {
+            continue;
+        }
+        let category_summary = category_summaries.entry(expense.category.clone())
+            .or_insert(ProjectCategorySummary { category_id: expense.category.clone(), value: Decimal::ZERO, count: 0 });
+        category_summary.value += expense.value;
+        category_summary.count += 1;
+    }
+    let mut categories: Vec<ProjectCategorySummary> = category_summaries.into_values().collect();
+    categories.sort_by(|one, two| two.value.cmp(&one.value).then(one.category_id.cmp(&two.category_id)));
+
+    let total: Decimal = categories.iter().map(|category| category.value).sum();
+    let days = (project.end - project.start).num_days() + 1;
+    return ProjectSummary {
+        project_id: project.id.clone(),
+        total,
+        days,
+        daily_average: (total / Decimal::from(days)).round_dp(2),
+        remaining: project.budget.map(|budget| budget - total),
+        categories,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::expense::schema::ExpenseKind;
+    use super::*;
+
+    #[test]
+    fn test_calculate_project_summary() {
+        let project = Project {
+            id: "japan".to_string(),
+            name: "Japan".to_string(),
+            start: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            end: NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+            budget: Some(Decimal::new(2000, 0)),
+        };
+        let expenses = vec![
+            Expense { date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), project_id: Some("japan".to_string()), ..Expense::for_test("flights", "VACATIONS", Decimal::new(900, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), project_id: Some("japan".to_string()), ..Expense::for_test("ramen", "RESTAURANTS", Decimal::new(20, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), project_id: Some("japan".to_string()), ..Expense::for_test("sushi", "RESTAURANTS", Decimal::new(80, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), kind: ExpenseKind::Refund, original_expense_id: Some("flights".to_string()), project_id: Some("japan".to_string()), ..Expense::for_test("refund", "OTHER", Decimal::new(100, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), project_id: Some("alps".to_string()), ..Expense::for_test("skis", "VACATIONS", Decimal::new(500, 0)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), ..Expense::for_test("groceries", "GROCERIES", Decimal::new(50, 0)) },
+        ];
+
+        assert_eq!(ProjectSummary {
+            project_id: "japan".to_string(),
+            total: Decimal::new(900, 0),
+            days: 7,
+            daily_average: Decimal::new(12857, 2),
+            remaining: Some(Decimal::new(1100, 0)),
+            categories: vec![
+                ProjectCategorySummary { category_id: "VACATIONS".to_string(), value: Decimal::new(800, 0), count: 2 },
+                ProjectCategorySummary { category_id: "RESTAURANTS".to_string(), value: Decimal::new(100, 0), count: 2 },
+            ],
+        }, calculate_project_summary(&project, expenses));
+    }
+}
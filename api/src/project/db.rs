@@ -0,0 +1,73 @@
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use const_format::formatcp;
+use rusqlite::{params, Transaction};
+use uuid::Uuid;
+use crate::db::{list, single};
+use crate::expense::db::relink_expenses;
+use crate::expense::schema::Expense;
+use crate::project::schema::{NewProject, Project};
+
+const PROJECT_COLUMNS: &str = "id, name, start, end, budget";
+const PROJECT_SELECT: &str = formatcp!("SELECT {PROJECT_COLUMNS} FROM project");
+const PROJECT_RETURNING: &str = formatcp!("RETURNING {PROJECT_COLUMNS}");
+const PROJECT_ORDERING: &str = "ORDER BY start DESC, name, id ASC";
+
+pub fn create_project(transaction: &Transaction, new_project: NewProject) -> anyhow::Result<Option<Project>> {
+    verify_dates(new_project.start, new_project.end)?;
+    return single(
+        transaction,
+        formatcp!("INSERT INTO project ({PROJECT_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5) {PROJECT_RETURNING}"),
+        params![Uuid::new_v4().to_string(), new_project.name, new_project.start.to_string(), new_project.end.to_string(), new_project.budget.map(|budget| budget.round_dp(2).to_string())],
+    );
+}
+
+pub fn update_project(transaction: &Transaction, updated_project: Project) -> anyhow::Result<Option<Project>> {
+    verify_dates(updated_project.start, updated_project.end)?;
+    return single(
+        transaction,
+        formatcp!("UPDATE project SET name = ?1, start = ?2, end = ?3, budget = ?4 WHERE id = ?5 {PROJECT_RETURNING}"),
+        params![updated_project.name, updated_project.start.to_string(), updated_project.end.to_string(), updated_project.budget.map(|budget| budget.round_dp(2).to_string()), updated_project.id],
+    );
+}
+
+// Expenses are unassigned rather than deleted, they still happened
+pub fn delete_project(transaction: &Transaction, id: String) -> anyhow::Result<Option<Project>> {
+    relink_expenses(transaction, "project_id = ?1", [id.clone()], |expense| Expense { project_id: None, ..expense })?;
+    return single(
+        transaction,
+        formatcp!("DELETE FROM project WHERE id = ?1 {PROJECT_RETURNING}"),
+        [id],
+    );
+}
+
+pub fn get_project(transaction: &Transaction, id: String) -> anyhow::Result<Option<Project>> {
+    return single(
+        transaction,
+        formatcp!("{PROJECT_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn list_projects(transaction: &Transaction) -> anyhow::Result<Vec<Project>> {
+    return list(
+        transaction,
+        formatcp!("{PROJECT_SELECT} {PROJECT_ORDERING}"),
+        [],
+    );
+}
+
+pub fn verify_project_id_exists(transaction: &Transaction, id: String) -> anyhow::Result<()> {
+    let result = get_project(transaction, id.clone())?;
+    if result.is_none() {
+        return Err(anyhow!("Project {} does not exist", id));
+    }
+    return Ok(());
+}
+
+fn verify_dates(start: NaiveDate, end: NaiveDate) -> anyhow::Result<()> {
+    if end < start {
+        return Err(anyhow!("End {} cannot be before start {}", end, start));
+    }
+    return Ok(());
+}
@@ -0,0 +1,63 @@
+use chrono::NaiveDate;
+use rusqlite::Row;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use crate::db::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    // Inclusive on both ends, expenses outside the range can still be assigned such as flights booked in advance
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    #[serde(with = "rust_decimal::serde::float_option", default)]
+    pub budget: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewProject {
+    pub name: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    #[serde(with = "rust_decimal::serde::float_option", default)]
+    pub budget: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProjectSummary {
+    #[serde(rename = "projectId")]
+    pub project_id: String,
+    // Net of refunds and reimbursements
+    #[serde(with = "rust_decimal::serde::float")]
+    pub total: Decimal,
+    pub days: i64,
+    #[serde(rename = "dailyAverage", with = "rust_decimal::serde::float")]
+    pub daily_average: Decimal,
+    // Only present when the project has a budget
+    #[serde(with = "rust_decimal::serde::float_option", default)]
+    pub remaining: Option<Decimal>,
+    // Largest first
+    pub categories: Vec<ProjectCategorySummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProjectCategorySummary {
+    #[serde(rename = "categoryId")]
+    pub category_id: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    pub count: usize,
+}
+
+impl FromRow for Project {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Project {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            start: crate::db::get_naive_date(row, "start")?,
+            end: crate::db::get_naive_date(row, "end")?,
+            budget: crate::db::get_optional_decimal(row, "budget")?,
+        })
+    }
+}
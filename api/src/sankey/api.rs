@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, HashMap};
+use actix_web::{Error, error, get, HttpResponse, web};
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use log::{error, info};
+use rust_decimal::Decimal;
+use crate::account::db::list_accounts;
+use crate::account::schema::{Account, AccountType};
+use crate::db::{do_in_transaction, Pool};
+use crate::expense::db::list_expenses;
+use crate::expense::schema::Expense;
+use crate::expense_category::db::list_expense_categories;
+use crate::expense_category::schema::ExpenseCategory;
+use crate::sankey::schema::{Sankey, SankeyLink, SankeyNode, SankeyNodeType, SankeyQuery};
+use crate::transaction::db::list_transactions;
+use crate::transaction::schema::Transaction;
+use crate::transaction::schema::TransactionType::{Balance, Transfer};
+
+const SPENDING_NODE_ID: &str = "SPENDING";
+const RESIDUAL_NODE_ID: &str = "RESIDUAL";
+
+#[get("/api/sankey/")]
+pub async fn calculate_sankey(db: web::Data<Pool>, query: web::Query<SankeyQuery>) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    info!("HTTP calculate_sankey [{query:?}]");
+    do_in_transaction(&db, move |transaction| {
+        if query.end < query.start {
+            return Err(anyhow!("End {} cannot be before start {}", query.end, query.start));
+        }
+        let transactions = list_transactions(transaction)?;
+        let accounts = list_accounts(transaction)?;
+        let expenses = list_expenses(transaction)?;
+        let expense_categories = list_expense_categories(transaction)?;
+        return Ok((query, transactions, accounts, expenses, expense_categories));
+    })
+        .await
+        .map(|(query, transactions, accounts, expenses, expense_categories)| calculate(&transactions, &accounts, &expenses, &expense_categories, query.start, query.end))
+        .map(|sankey| HttpResponse::Ok().json(sankey))
+        .map_err(|err| {
+            error!("HTTP calculate_sankey: [{err}]");
+            return error::ErrorInternalServerError(err);
+        })
+}
+
+// Flow is the change in balance that isn't explained by transfers, so negative flow is money that was spent
+pub fn calculate_flow_by_account(transactions: &[Transaction], start: NaiveDate, end: NaiveDate) -> HashMap<String, Decimal> {
+    let mut start_balance_by_account_id: HashMap<String, (NaiveDate, Decimal)> = HashMap::new();
+    let mut end_balance_by_account_id: HashMap<String, (NaiveDate, Decimal)> = HashMap::new();
+    let mut transfers_by_account_id: HashMap<String, Decimal> = HashMap::new();
+
+    for transaction in transactions {
+        match transaction.transaction_type {
+            Balance => {
+                if transaction.date <= start {
+                    latest_balance(&mut start_balance_by_account_id, transaction);
+                }
+                if transaction.date <= end {
+                    latest_balance(&mut end_balance_by_account_id, transaction);
+                }
+            }
+            Transfer => {
+                if start < transaction.date && transaction.date <= end {
+                    *transfers_by_account_id.entry(transaction.account_id.clone()).or_insert(Decimal::ZERO) += transaction.value;
+                    *transfers_by_account_id.entry(transaction.from_account_id.clone().unwrap()).or_insert(Decimal::ZERO) -= transaction.value;
+                }
+            }
+        }
+    }
+
+    let mut flow_by_account_id: HashMap<String, Decimal> = HashMap::new();
+    for (account_id, (_, end_balance)) in end_balance_by_account_id.iter() {
+        let start_balance = start_balance_by_account_id.get(account_id).map(|(_, balance)| *balance).unwrap_or(Decimal::ZERO);
+        let transfers = transfers_by_account_id.get(account_id).cloned().unwrap_or(Decimal::ZERO);
+        flow_by_account_id.insert(account_id.clone(), end_balance - start_balance - transfers);
+    }
+    return flow_by_account_id;
+}
+
+fn calculate(transactions: &[Transaction], accounts: &[Account], expenses: &[Expense], expense_categories: &[ExpenseCategory], start: NaiveDate, end: NaiveDate) -> Sankey {
+    let mut nodes: Vec<SankeyNode> = vec![];
+    let mut links: Vec<SankeyLink> = vec![];
+
+    for account in accounts {
+        nodes.push(SankeyNode {
+            id: account.id.clone(),
+            name: account.name.clone(),
+            node_type: node_type_from_account_type(&account.account_type),
+        });
+    }
+
+    // Transfers in both directions between the same pair of accounts are netted into a single link
+    let mut transfers_by_account_ids: BTreeMap<(String, String), Decimal> = BTreeMap::new();
+    for transaction in transactions {
+        if transaction.transaction_type != Transfer || transaction.date <= start || end < transaction.date {
+            continue;
+        }
+        let from_account_id = transaction.from_account_id.clone().unwrap();
+        if from_account_id < transaction.account_id {
+            *transfers_by_account_ids.entry((from_account_id, transaction.account_id.clone())).or_insert(Decimal::ZERO) += transaction.value;
+        } else {
+            *transfers_by_account_ids.entry((transaction.account_id.clone(), from_account_id)).or_insert(Decimal::ZERO) -= transaction.value;
+        }
+    }
+    for ((one, two), value) in transfers_by_account_ids {
+        if value > Decimal::ZERO {
+            links.push(SankeyLink { source: one, target: two, value });
+        } else if value < Decimal::ZERO {
+            links.push(SankeyLink { source: two, target: one, value: value.abs() });
+        }
+    }
+
+    // Spending leaves cash and credit cards, so any outflow from them feeds the spending node
+    let flow_by_account_id = calculate_flow_by_account(transactions, start, end);
+    let mut spending = Decimal::ZERO;
+    for account in accounts {
+        if account.account_type != AccountType::Checking && account.account_type != AccountType::CreditCard {
+            continue;
+        }
+        let flow = flow_by_account_id.get(&account.id).cloned().unwrap_or(Decimal::ZERO);
+        if flow < Decimal::ZERO {
+            links.push(SankeyLink { source: account.id.clone(), target: SPENDING_NODE_ID.to_string(), value: flow.abs() });
+            spending += flow.abs();
+        }
+    }
+    nodes.push(SankeyNode { id: SPENDING_NODE_ID.to_string(), name: "Spending".to_string(), node_type: SankeyNodeType::Spending });
+
+    let mut expenses_by_category: BTreeMap<String, Decimal> = BTreeMap::new();
+    for expense in crate::expense::api::spending(expenses.to_vec(), false) {
+        if start < expense.date && expense.date <= end {
+            *expenses_by_category.entry(expense.category.clone()).or_insert(Decimal::ZERO) += expense.value;
+        }
+    }
+    let mut explained = Decimal::ZERO;
+    for (category, value) in expenses_by_category {
+        let name = expense_categories.iter()
+            .find(|expense_category| expense_category.id == category)
+            .map(|expense_category| expense_category.name.clone())
+            .unwrap_or(category.clone());
+        nodes.push(SankeyNode { id: category.clone(), name, node_type: SankeyNodeType::Expense });
+        if value > Decimal::ZERO {
+            links.push(SankeyLink { source: SPENDING_NODE_ID.to_string(), target: category, value });
+            explained += value;
+        }
+    }
+
+    nodes.push(SankeyNode { id: RESIDUAL_NODE_ID.to_string(), name: "Unexplained".to_string(), node_type: SankeyNodeType::Residual });
+    if spending > explained {
+        links.push(SankeyLink { source: SPENDING_NODE_ID.to_string(), target: RESIDUAL_NODE_ID.to_string(), value: spending - explained });
+    }
+
+    return Sankey { nodes, links };
+}
+
+fn latest_balance(balance_by_account_id: &mut HashMap<String, (NaiveDate, Decimal)>, transaction: &Transaction) {
+    let current = balance_by_account_id.get(&transaction.account_id);
+    if current.is_none_or(|(date, _)| *date <= transaction.date) {
+        balance_by_account_id.insert(transaction.account_id.clone(), (transaction.date, transaction.value));
+    }
+}
+
+fn node_type_from_account_type(account_type: &AccountType) -> SankeyNodeType {
+    match account_type {
+        AccountType::External => SankeyNodeType::Income,
+        AccountType::Checking => SankeyNodeType::Cash,
+        AccountType::Savings => SankeyNodeType::Asset,
+        AccountType::Investment => SankeyNodeType::Asset,
+        AccountType::Retirement => SankeyNodeType::Asset,
+        AccountType::PhysicalAsset => SankeyNodeType::Asset,
+        AccountType::Loan => SankeyNodeType::Liability,
+        AccountType::CreditCard => SankeyNodeType::Liability,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use crate::transaction::schema::TransactionType;
+    use super::*;
+
+    fn account(id: &str, account_type: AccountType) -> Account {
+        Account {
+            id: id.to_string(),
+            name: id.to_string(),
+            account_type,
+            hide_new_transactions: false,
+            transfer_without_balance_ignored: false,
+            no_regular_balance: false,
+            hidden: false,
+        }
+    }
+
+    fn transaction(transaction_type: TransactionType, date: NaiveDate, value: i64, account_id: &str, from_account_id: Option<&str>) -> Transaction {
+        Transaction {
+            id: format!("{account_id}-{date}-{transaction_type}"),
+            description: "".to_string(),
+            date,
+            value: Decimal::new(value, 0),
+            transaction_type,
+            account_id: account_id.to_string(),
+            from_account_id: from_account_id.map(|from_account_id| from_account_id.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_calculate() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let accounts = vec![
+            account("employer", AccountType::External),
+            account("checking", AccountType::Checking),
+            account("savings", AccountType::Savings),
+        ];
+        let transactions = vec![
+            transaction(TransactionType::Balance, start, 500, "checking", None),
+            transaction(TransactionType::Transfer, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(), 1000, "checking", Some("employer")),
+            transaction(TransactionType::Transfer, NaiveDate::from_ymd_opt(2024, 1, 16).unwrap(), 300, "savings", Some("checking")),
+            transaction(TransactionType::Balance, end, 800, "checking", None),
+        ];
+        let expenses = vec![
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 20).unwrap(), ..Expense::for_test("groceries", "GROCERIES", Decimal::new(250, 0)) },
+        ];
+
+        let expense_categories = vec![
+            ExpenseCategory {
+                id: "GROCERIES".to_string(),
+                name: "Groceries".to_string(),
+                parent_id: None,
+                colour: None,
+                hidden: false,
+            },
+        ];
+
+        let sankey = calculate(&transactions, &accounts, &expenses, &expense_categories, start, end);
+        let link = |source: &str, target: &str| sankey.links.iter()
+            .find(|link| link.source == source && link.target == target)
+            .map(|link| link.value);
+        assert_eq!(Some(Decimal::new(1000, 0)), link("employer", "checking"));
+        assert_eq!(Some(Decimal::new(300, 0)), link("checking", "savings"));
+        // 500 + 1000 - 300 leaves 1200 but only 800 remained
+        assert_eq!(Some(Decimal::new(400, 0)), link("checking", SPENDING_NODE_ID));
+        assert_eq!(Some(Decimal::new(250, 0)), link(SPENDING_NODE_ID, "GROCERIES"));
+        assert_eq!(Some(Decimal::new(150, 0)), link(SPENDING_NODE_ID, RESIDUAL_NODE_ID));
+        assert_eq!(5, sankey.links.len());
+    }
+}
@@ -0,0 +1,2 @@
+pub mod schema;
+pub mod api;
\ No newline at end of file
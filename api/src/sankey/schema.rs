@@ -0,0 +1,59 @@
+#![allow(unreachable_patterns)]
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Sankey {
+    pub nodes: Vec<SankeyNode>,
+    pub links: Vec<SankeyLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SankeyNode {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub node_type: SankeyNodeType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SankeyLink {
+    pub source: String,
+    pub target: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SankeyQuery {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+#[derive(Debug, Clone, Display, EnumString, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+pub enum SankeyNodeType {
+    #[serde(rename = "INCOME" )]
+    #[strum(serialize="INCOME", to_string="INCOME")]
+    Income,
+    #[serde(rename = "CASH" )]
+    #[strum(serialize="CASH", to_string="CASH")]
+    Cash,
+    #[serde(rename = "ASSET" )]
+    #[strum(serialize="ASSET", to_string="ASSET")]
+    Asset,
+    #[serde(rename = "LIABILITY" )]
+    #[strum(serialize="LIABILITY", to_string="LIABILITY")]
+    Liability,
+    #[serde(rename = "SPENDING" )]
+    #[strum(serialize="SPENDING", to_string="SPENDING")]
+    Spending,
+    #[serde(rename = "EXPENSE" )]
+    #[strum(serialize="EXPENSE", to_string="EXPENSE")]
+    Expense,
+    #[serde(rename = "RESIDUAL" )]
+    #[strum(serialize="RESIDUAL", to_string="RESIDUAL")]
+    Residual,
+}
@@ -1,11 +1,16 @@
 use std::cmp::Ordering;
+use std::path::Path;
+use std::str::FromStr;
 use anyhow::anyhow;
 use chrono::Local;
 use const_format::formatcp;
 use log::info;
 use rusqlite::{Transaction};
+use rust_decimal::Decimal;
 use uuid::Uuid;
 use crate::account::db::{verify_account_id_exists};
+use crate::audit::db::{record_create, record_delete, record_restore, record_update};
+use crate::audit::schema::AuditEntityType;
 use crate::db::{list, single};
 use crate::setting::schema::{NewSetting, RepeatingTransfer, Setting, SettingKey};
 
@@ -17,28 +22,41 @@ const SETTING_ORDERING: &str = "ORDER BY key ASC";
 pub fn create_setting(transaction: &Transaction, new_setting: NewSetting) -> anyhow::Result<Option<Setting>> {
     verify_new(transaction, new_setting.key.clone())?;
     verify(transaction, new_setting.key.clone(), new_setting.value.clone())?;
-    return single(
+    let setting: Option<Setting> = single(
         transaction,
         formatcp!("INSERT INTO setting ({SETTING_COLUMNS}) VALUES (?1, ?2, ?3) {SETTING_RETURNING}"),
         [Uuid::new_v4().to_string(), new_setting.key.to_string(), new_setting.value],
-    );
+    )?;
+    if let Some(setting) = &setting {
+        record_create(transaction, AuditEntityType::Setting, &setting.id, setting)?;
+    }
+    return Ok(setting);
 }
 
 pub fn update_setting(transaction: &Transaction, updated_setting: Setting) -> anyhow::Result<Option<Setting>> {
     verify(transaction, updated_setting.key.clone(), updated_setting.value.clone())?;
-    return single(
+    let previous_setting = get_setting(transaction, updated_setting.id.clone())?;
+    let setting: Option<Setting> = single(
         transaction,
         formatcp!("UPDATE setting SET key = ?1, value = ?2 WHERE id = ?3 {SETTING_RETURNING}"),
         [updated_setting.key.to_string(), updated_setting.value, updated_setting.id],
-    );
+    )?;
+    if let Some(setting) = &setting {
+        record_update(transaction, AuditEntityType::Setting, &setting.id, previous_setting.as_ref(), setting)?;
+    }
+    return Ok(setting);
 }
 
 pub fn delete_setting(transaction: &Transaction, id: String) -> anyhow::Result<Option<Setting>> {
-    return single(
+    let setting: Option<Setting> = single(
         transaction,
         formatcp!("DELETE FROM setting WHERE id = ?1 {SETTING_RETURNING}"),
         [id],
-    );
+    )?;
+    if let Some(setting) = &setting {
+        record_delete(transaction, AuditEntityType::Setting, &setting.id, setting)?;
+    }
+    return Ok(setting);
 }
 
 pub fn get_setting(transaction: &Transaction, id: String) -> anyhow::Result<Option<Setting>> {
@@ -78,12 +96,54 @@ pub fn cascade_delete_account(transaction: &Transaction, account_id: String) ->
                 SettingKey::RepeatingTransfers => {
                     delete_account_ids_in_repeating_transfers(transaction, &account_id, setting)?;
                 }
+                SettingKey::AttachmentDirectory | SettingKey::CreditUtilisationThreshold => {}
             }
         }
     }
     Ok(())
 }
 
+pub fn cascade_merge_account(transaction: &Transaction, account_id: String, target_account_id: String) -> anyhow::Result<()> {
+    let settings = list_settings(transaction)?;
+    for setting in settings {
+        if setting.value.contains(account_id.as_str()) {
+            match setting.key {
+                SettingKey::DefaultTransactionFromAccountId => {
+                    info!("Updating setting [{}] [{}] because account [{}] was merged into [{}]", setting.id, setting.key, account_id, target_account_id);
+                    update_setting(transaction, Setting { value: target_account_id.clone(), ..setting })?;
+                },
+                SettingKey::RepeatingTransfers => {
+                    merge_account_ids_in_repeating_transfers(transaction, &account_id, &target_account_id, setting)?;
+                }
+                SettingKey::AttachmentDirectory | SettingKey::CreditUtilisationThreshold => {}
+            }
+        }
+    }
+    return Ok(());
+}
+
+// Puts back settings changed when an account was deleted, unless the setting has since been replaced by another
+pub fn restore_settings(transaction: &Transaction, settings: Vec<Setting>) -> anyhow::Result<()> {
+    for setting in settings {
+        if get_setting(transaction, setting.id.clone())?.is_some() {
+            update_setting(transaction, setting)?;
+        } else if get_setting_by_key(transaction, setting.key.clone())?.is_some() {
+            info!("Not restoring setting [{}] [{}] because it has been replaced", setting.id, setting.key);
+        } else {
+            verify(transaction, setting.key.clone(), setting.value.clone())?;
+            let restored: Option<Setting> = single(
+                transaction,
+                formatcp!("INSERT INTO setting ({SETTING_COLUMNS}) VALUES (?1, ?2, ?3) {SETTING_RETURNING}"),
+                [setting.id, setting.key.to_string(), setting.value],
+            )?;
+            if let Some(restored) = &restored {
+                record_restore(transaction, AuditEntityType::Setting, &restored.id, restored)?;
+            }
+        }
+    }
+    return Ok(());
+}
+
 
 
 fn delete_account_ids_in_repeating_transfers(transaction: &Transaction, account_id: &String, setting: Setting) -> anyhow::Result<()> {
@@ -127,6 +187,47 @@ fn delete_account_ids_in_repeating_transfers(transaction: &Transaction, account_
     Ok(())
 }
 
+fn merge_account_ids_in_repeating_transfers(transaction: &Transaction, account_id: &String, target_account_id: &String, setting: Setting) -> anyhow::Result<()> {
+    let repeating_transfers: Vec<RepeatingTransfer> = serde_json::from_str(setting.value.as_str())?;
+    let mut to_save_repeating_transfers: Vec<RepeatingTransfer> = vec![];
+
+    for repeating_transfer in repeating_transfers {
+        let mut repeating_transfer = repeating_transfer.clone();
+        if repeating_transfer.from_account_id.eq(account_id) {
+            repeating_transfer.from_account_id = target_account_id.clone();
+        }
+        let mut to_account_ids: Vec<String> = vec![];
+        for id in repeating_transfer.to_account_ids {
+            let id = if account_id.eq(&id) { target_account_id.clone() } else { id };
+            // The target may already be there and can't transfer to itself
+            if !to_account_ids.contains(&id) && !repeating_transfer.from_account_id.eq(&id) {
+                to_account_ids.push(id);
+            }
+        }
+        repeating_transfer.to_account_ids = to_account_ids;
+        if repeating_transfer.to_account_ids.len() != 0 {
+            to_save_repeating_transfers.push(repeating_transfer)
+        } else {
+            info!("Dropping repeating_transfer [{:?}] because account [{}] was merged into its only other account [{}]", repeating_transfer, account_id, target_account_id);
+        }
+    }
+
+    if to_save_repeating_transfers.len() == 0 {
+        info!("Deleting setting [{}] [{}] because account [{}] was merged and no repeating transfers remain", setting.id, setting.key, account_id);
+        delete_setting(transaction, setting.id)?;
+    } else {
+        info!("Updating setting [{}] [{}] because account [{}] was merged into [{}]", setting.id, setting.key, account_id, target_account_id);
+        let serialized = serde_json::to_string(&to_save_repeating_transfers)?;
+        update_setting(transaction, Setting {
+            id: setting.id,
+            key: setting.key,
+            value: serialized,
+        })?;
+    }
+
+    Ok(())
+}
+
 fn verify_new(transaction: &Transaction, setting_key: SettingKey) -> anyhow::Result<()> {
     let exists = list_settings(transaction)?.iter().any(|setting| setting.key == setting_key);
     return if exists {
@@ -140,11 +241,29 @@ fn verify(transaction: &Transaction, setting_key: SettingKey, value: String) ->
     match setting_key {
         SettingKey::DefaultTransactionFromAccountId => verify_account_id_exists(transaction, value),
         SettingKey::RepeatingTransfers => verify_repeating_transfers(transaction, value),
+        SettingKey::AttachmentDirectory => verify_attachment_directory(value),
+        SettingKey::CreditUtilisationThreshold => verify_credit_utilisation_threshold(value),
     }
 }
 
 
 
+fn verify_attachment_directory(value: String) -> anyhow::Result<()> {
+    if !Path::new(&value).is_dir() {
+        return Err(anyhow!("Attachment directory {} does not exist", value));
+    }
+    return Ok(());
+}
+
+fn verify_credit_utilisation_threshold(value: String) -> anyhow::Result<()> {
+    let threshold = Decimal::from_str(&value)
+        .map_err(|_| anyhow!("Credit utilisation threshold {} is not a number", value))?;
+    if threshold <= Decimal::ZERO {
+        return Err(anyhow!("Credit utilisation threshold must be positive"));
+    }
+    return Ok(());
+}
+
 fn verify_repeating_transfers(transaction: &Transaction, value: String) -> anyhow::Result<()> {
     let repeating_transfers: Vec<RepeatingTransfer> = serde_json::from_str(value.as_str())?;
     if repeating_transfers.len() == 0 {
@@ -29,6 +29,14 @@ pub enum SettingKey {
     #[serde(rename = "REPEATING_TRANSFERS" )]
     #[strum(serialize="REPEATING_TRANSFERS", to_string="REPEATING_TRANSFERS")]
     RepeatingTransfers,
+    // Attachments are stored in the database unless this points at a directory
+    #[serde(rename = "ATTACHMENT_DIRECTORY" )]
+    #[strum(serialize="ATTACHMENT_DIRECTORY", to_string="ATTACHMENT_DIRECTORY")]
+    AttachmentDirectory,
+    // Percentage of a credit card's limit above which an issue is raised
+    #[serde(rename = "CREDIT_UTILISATION_THRESHOLD" )]
+    #[strum(serialize="CREDIT_UTILISATION_THRESHOLD", to_string="CREDIT_UTILISATION_THRESHOLD")]
+    CreditUtilisationThreshold,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
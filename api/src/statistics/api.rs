@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::hash::Hash;
+use std::str::FromStr;
 use actix_web::{Error, error, get, HttpResponse, web};
 use anyhow::anyhow;
 use chrono::{Datelike, Days, Local, Months, NaiveDate};
@@ -10,11 +11,28 @@ use strum::IntoEnumIterator;
 use strum_macros::{EnumIter, Display};
 use crate::account::db::list_accounts;
 use crate::account::schema::{Account, AccountType};
+use crate::account_group::db::list_account_groups;
+use crate::account_group::schema::AccountGroup;
+use crate::credit_card::api::credit_utilisation;
+use crate::credit_card::db::list_credit_cards;
+use crate::credit_card::schema::CreditCard;
 use crate::db::{do_in_transaction, Pool};
+use crate::expense::api::spending;
 use crate::expense::db::list_expenses;
-use crate::expense::schema::{Expense, ExpenseCategory};
+use crate::expense::schema::Expense;
+use crate::household::api::calculate_shares;
+use crate::household::db::list_expense_splits;
+use crate::household::schema::ExpenseSplit;
+use crate::merchant::db::list_merchants;
+use crate::merchant::schema::Merchant;
+use crate::tag::db::{list_expense_tag_links, list_tags, list_transaction_tag_links};
+use crate::tag::schema::{Tag, TagLink};
+use crate::person::db::list_persons;
+use crate::person::schema::Person;
+use crate::expense_category::db::{ancestors_by_expense_category_id, list_expense_categories};
+use crate::expense_category::schema::{ExpenseCategory, UNKNOWN_EXPENSE_CATEGORY_ID};
 use crate::statistics::api::Period::{Monthly, Weekly, Yearly};
-use crate::statistics::schema::{Statistic, Value};
+use crate::statistics::schema::{Statistic, StatisticsQuery, Value};
 use crate::transaction::db::list_transactions;
 use crate::transaction::schema::Transaction;
 use crate::transaction::schema::TransactionType::{Balance, Transfer};
@@ -36,6 +54,34 @@ enum Category {
     Flow,
     FlowGrouping,
     Expenses,
+    Allocation,
+    Merchants,
+    Tags,
+    CreditUtilisation,
+    GroupBalances,
+    GroupTransfers,
+    GroupFlow,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Smoothing {
+    Simple,
+    Exponential,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Smoothings {
+    smoothing: Option<Smoothing>,
+    window: usize,
+    trend: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AccountFilter {
+    hidden: bool,
+    account_ids: Option<Vec<String>>,
+    account_types: Option<Vec<AccountType>>,
+    account_group_ids: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Display, EnumIter, Hash)]
@@ -75,30 +121,55 @@ enum FlowGroupType {
 }
 
 #[get("/api/statistics/{period}/{category}/")]
-pub async fn calculate_statistics(db: web::Data<Pool>, path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+pub async fn calculate_statistics(db: web::Data<Pool>, path: web::Path<(String, String)>, query: web::Query<StatisticsQuery>) -> Result<HttpResponse, Error> {
     let (period, category) = path.into_inner();
-    info!("HTTP statistics [{period:?}] [{category:?}]");
+    let query = query.into_inner();
+    info!("HTTP statistics [{period:?}] [{category:?}] [{query:?}]");
     do_in_transaction(&db, |transaction| {
         let (period, category) = extract_params((period, category))?;
+        let smoothings = extract_smoothings(query.clone())?;
+        let my_share = query.my_share.unwrap_or(false);
+        let gross = query.gross.unwrap_or(false);
+        let account_filter = extract_account_filter(query)?;
         let transactions = list_transactions(transaction)?;
         let accounts = list_accounts(transaction)?;
-        let expenses = list_expenses(transaction)?;
-        return Ok((period, category, transactions, accounts, expenses));
+        let mut expenses = spending(list_expenses(transaction)?, gross);
+        if my_share {
+            expenses = my_share_of_expenses(expenses, &list_persons(transaction)?, &list_expense_splits(transaction)?)?;
+        }
+        let expense_categories = list_expense_categories(transaction)?;
+        let merchants = list_merchants(transaction)?;
+        let tagging = (list_tags(transaction)?, list_expense_tag_links(transaction)?, list_transaction_tag_links(transaction)?);
+        let account_groups = list_account_groups(transaction)?;
+        let credit_cards = list_credit_cards(transaction)?;
+        let (transactions, accounts, expenses) = filter_accounts(transactions, accounts, expenses, &account_groups, &account_filter);
+        return Ok((period, category, smoothings, transactions, accounts, expenses, expense_categories, merchants, tagging, (account_groups, credit_cards)));
     })
         .await
-        .map(|(period, category, mut transactions, accounts, mut expenses)| {
+        .map(|(period, category, smoothings, mut transactions, accounts, mut expenses, expense_categories, merchants, tagging, (account_groups, credit_cards))| {
+            if transactions.is_empty() {
+                return vec![];
+            }
             transactions.sort_by(|one, two| one.date.cmp(&two.date));
             expenses.sort_by(|one, two| one.date.cmp(&two.date));
             let dates = dates(period, Local::now().date_naive(), transactions[0].date);
-            match category {
+            let statistics = match category {
                 Category::AccountBalances => calculate_account_balances(transactions, accounts, dates),
                 Category::AccountTransfers => calculate_account_transfers(transactions, accounts, dates),
                 Category::TotalBalances => calculate_total_balances(transactions, accounts, dates),
                 Category::TotalTransfers => calculate_total_transfers(transactions, accounts, dates),
                 Category::Flow => calculate_flow(transactions, accounts, dates),
                 Category::FlowGrouping => calculate_flow_grouping(transactions, accounts, dates),
-                Category::Expenses => calculate_expenses(transactions, accounts, expenses, dates),
-            }
+                Category::Expenses => calculate_expenses(transactions, accounts, expenses, expense_categories, dates),
+                Category::Allocation => calculate_allocation(transactions, accounts, expenses, expense_categories, dates),
+                Category::Merchants => calculate_merchants(expenses, merchants, dates),
+                Category::Tags => calculate_tags(transactions, expenses, tagging, dates),
+                Category::CreditUtilisation => calculate_credit_utilisation(transactions, accounts, credit_cards, dates),
+                Category::GroupBalances => calculate_group_balances(transactions, accounts, account_groups, dates),
+                Category::GroupTransfers => calculate_group_transfers(transactions, accounts, account_groups, dates),
+                Category::GroupFlow => calculate_group_flow(transactions, accounts, account_groups, dates),
+            };
+            smooth(statistics, &smoothings)
         })
         .map(|statistics| HttpResponse::Ok().json(statistics))
         .map_err(|err| {
@@ -124,14 +195,26 @@ fn calculate_total_transfers(transactions: Vec<Transaction>, accounts: Vec<Accou
 }
 
 fn calculate_flow(transactions: Vec<Transaction>, accounts: Vec<Account>, dates: Vec<NaiveDate>) -> Vec<Statistic> {
-    return calculate_flow_internal(transactions, accounts, dates, calculate_flow_total);
+    return calculate_flow_internal(transactions, accounts, dates, accumulate_totals, calculate_flow_total);
 }
 
 fn calculate_flow_grouping(transactions: Vec<Transaction>, accounts: Vec<Account>, dates: Vec<NaiveDate>) -> Vec<Statistic> {
-    return calculate_flow_internal(transactions, accounts, dates, calculate_flow_grouping_total);
+    return calculate_flow_internal(transactions, accounts, dates, accumulate_totals, calculate_flow_grouping_total);
 }
 
-fn calculate_expenses(transactions: Vec<Transaction>, accounts: Vec<Account>, expenses: Vec<Expense>, dates: Vec<NaiveDate>) -> Vec<Statistic> {
+fn calculate_group_balances(transactions: Vec<Transaction>, accounts: Vec<Account>, account_groups: Vec<AccountGroup>, dates: Vec<NaiveDate>) -> Vec<Statistic> {
+    return calculate_internal(transactions, accounts, dates, |transaction| transaction.transaction_type == Balance, add_balance, |map, _| accumulate_groups(map, &account_groups));
+}
+
+fn calculate_group_transfers(transactions: Vec<Transaction>, accounts: Vec<Account>, account_groups: Vec<AccountGroup>, dates: Vec<NaiveDate>) -> Vec<Statistic> {
+    return calculate_internal(transactions, accounts, dates, |transaction| transaction.transaction_type == Transfer, add_transfer, |map, _| accumulate_groups(map, &account_groups));
+}
+
+fn calculate_group_flow(transactions: Vec<Transaction>, accounts: Vec<Account>, account_groups: Vec<AccountGroup>, dates: Vec<NaiveDate>) -> Vec<Statistic> {
+    return calculate_flow_internal(transactions, accounts, dates, |map, _| accumulate_groups(map, &account_groups), calculate_group_flow_total);
+}
+
+fn calculate_expenses(transactions: Vec<Transaction>, accounts: Vec<Account>, expenses: Vec<Expense>, expense_categories: Vec<ExpenseCategory>, dates: Vec<NaiveDate>) -> Vec<Statistic> {
     let mut cash_map: HashMap<NaiveDate, Decimal> = calculate_flow_grouping(transactions, accounts, dates.clone()).iter()
         .map(|statistic| (statistic.date.clone(), statistic.values.iter()
             .find(|value| value.name == "CASH".to_string())
@@ -141,13 +224,16 @@ fn calculate_expenses(transactions: Vec<Transaction>, accounts: Vec<Account>, ex
 
     let mut statistics: Vec<Statistic> = vec![];
 
-    let mut previous_value_by_accumulator_key: HashMap<String, Decimal> = ExpenseCategory::iter()
-        .map(|category| (category.to_string(), Decimal::ZERO))
+    // Parent categories include everything spent in their subcategories
+    let ancestors_by_category = ancestors_by_expense_category_id(&expense_categories);
+
+    let mut previous_value_by_accumulator_key: HashMap<String, Decimal> = expense_categories.iter()
+        .map(|category| (category.id.clone(), Decimal::ZERO))
         .collect();
     previous_value_by_accumulator_key.insert("TOTAL".to_string(), Decimal::ZERO);
 
-    let mut current_value_by_accumulator_key: HashMap<String, Decimal> = ExpenseCategory::iter()
-        .map(|category| (category.to_string(), Decimal::ZERO))
+    let mut current_value_by_accumulator_key: HashMap<String, Decimal> = expense_categories.iter()
+        .map(|category| (category.id.clone(), Decimal::ZERO))
         .collect();
     current_value_by_accumulator_key.insert("TOTAL".to_string(), Decimal::ZERO);
 
@@ -159,11 +245,19 @@ fn calculate_expenses(transactions: Vec<Transaction>, accounts: Vec<Account>, ex
         let value = current_value_by_accumulator_key.remove(&"CASH".to_string()).unwrap_or(Decimal::ZERO);
         current_value_by_accumulator_key.insert("CASH".to_string(), value + cash_map.remove(&date).unwrap_or(Decimal::ZERO));
         while current_expense.is_some() && current_expense.unwrap().date <= date {
-            let expense = current_expense.unwrap();        
+            let expense = current_expense.unwrap();
+
+            // Expenses whose category has since gone are counted as unknown rather than dropped
+            let ancestors = ancestors_by_category.get(&expense.category)
+                .or_else(|| ancestors_by_category.get(UNKNOWN_EXPENSE_CATEGORY_ID))
+                .cloned()
+                .unwrap_or_else(|| vec![UNKNOWN_EXPENSE_CATEGORY_ID.to_string()]);
+            for category in &ancestors {
+                let value = current_value_by_accumulator_key.remove(category).unwrap_or(Decimal::ZERO);
+                current_value_by_accumulator_key.insert(category.clone(), value + expense.value);
+            }
+
 
-            let value = current_value_by_accumulator_key.remove(&expense.category.to_string()).unwrap();
-            current_value_by_accumulator_key.insert(expense.category.to_string(), value + expense.value);
-            
             let value = current_value_by_accumulator_key.remove(&"TOTAL".to_string()).unwrap();
             current_value_by_accumulator_key.insert("TOTAL".to_string(), value + expense.value);
             
@@ -180,6 +274,169 @@ fn calculate_expenses(transactions: Vec<Transaction>, accounts: Vec<Account>, ex
     return statistics;
 }
 
+// Expenses without a merchant are accumulated under UNKNOWN
+fn calculate_merchants(expenses: Vec<Expense>, merchants: Vec<Merchant>, dates: Vec<NaiveDate>) -> Vec<Statistic> {
+    let mut statistics: Vec<Statistic> = vec![];
+
+    let mut previous_value_by_accumulator_key: HashMap<String, Decimal> = merchants.iter()
+        .map(|merchant| (merchant.id.clone(), Decimal::ZERO))
+        .collect();
+    previous_value_by_accumulator_key.insert("UNKNOWN".to_string(), Decimal::ZERO);
+    previous_value_by_accumulator_key.insert("TOTAL".to_string(), Decimal::ZERO);
+    let mut current_value_by_accumulator_key = previous_value_by_accumulator_key.clone();
+
+    let mut expenses_iterator = expenses.iter();
+    let mut first_expense_encountered = false;
+    let mut current_expense = expenses_iterator.next();
+
+    for date in dates {
+        while current_expense.is_some() && current_expense.unwrap().date <= date {
+            let expense = current_expense.unwrap();
+            let merchant_id = expense.merchant_id.clone().unwrap_or("UNKNOWN".to_string());
+            *current_value_by_accumulator_key.entry(merchant_id).or_insert(Decimal::ZERO) += expense.value;
+            *current_value_by_accumulator_key.get_mut("TOTAL").unwrap() += expense.value;
+            current_expense = expenses_iterator.next();
+            first_expense_encountered = true;
+        }
+
+        if first_expense_encountered {
+            statistics.push(create_statistic_by_key(date, &current_value_by_accumulator_key, &previous_value_by_accumulator_key));
+            previous_value_by_accumulator_key = current_value_by_accumulator_key.clone();
+        }
+    }
+
+    return statistics;
+}
+
+// Each tag has an EXPENSES:<tag id> and a TRANSFERS:<tag id> value
+fn calculate_tags(transactions: Vec<Transaction>, expenses: Vec<Expense>, (tags, expense_tag_links, transaction_tag_links): (Vec<Tag>, Vec<TagLink>, Vec<TagLink>), dates: Vec<NaiveDate>) -> Vec<Statistic> {
+    fn tag_ids_by_item_id(tag_links: Vec<TagLink>) -> HashMap<String, Vec<String>> {
+        let mut tag_ids_by_item_id: HashMap<String, Vec<String>> = HashMap::new();
+        for tag_link in tag_links {
+            tag_ids_by_item_id.entry(tag_link.item_id).or_default().push(tag_link.tag_id);
+        }
+        return tag_ids_by_item_id;
+    }
+    let tag_ids_by_expense_id = tag_ids_by_item_id(expense_tag_links);
+    let tag_ids_by_transaction_id = tag_ids_by_item_id(transaction_tag_links);
+
+    let mut statistics: Vec<Statistic> = vec![];
+
+    let mut previous_value_by_accumulator_key: HashMap<String, Decimal> = HashMap::new();
+    for tag in &tags {
+        previous_value_by_accumulator_key.insert(format!("EXPENSES:{}", tag.id), Decimal::ZERO);
+        previous_value_by_accumulator_key.insert(format!("TRANSFERS:{}", tag.id), Decimal::ZERO);
+    }
+    let mut current_value_by_accumulator_key = previous_value_by_accumulator_key.clone();
+
+    let mut expenses_iterator = expenses.iter().peekable();
+    let mut transactions_iterator = transactions.iter()
+        .filter(|transaction| transaction.transaction_type == Transfer)
+        .peekable();
+    let mut first_item_encountered = false;
+
+    for date in dates {
+        while let Some(expense) = expenses_iterator.next_if(|expense| expense.date <= date) {
+            first_item_encountered = true;
+            for tag_id in tag_ids_by_expense_id.get(&expense.id).into_iter().flatten() {
+                if let Some(value) = current_value_by_accumulator_key.get_mut(&format!("EXPENSES:{tag_id}")) {
+                    *value += expense.value;
+                }
+            }
+        }
+        while let Some(transaction) = transactions_iterator.next_if(|transaction| transaction.date <= date) {
+            first_item_encountered = true;
+            for tag_id in tag_ids_by_transaction_id.get(&transaction.id).into_iter().flatten() {
+                if let Some(value) = current_value_by_accumulator_key.get_mut(&format!("TRANSFERS:{tag_id}")) {
+                    *value += transaction.value;
+                }
+            }
+        }
+        if first_item_encountered {
+            statistics.push(create_statistic_by_key(date, &current_value_by_accumulator_key, &previous_value_by_accumulator_key));
+            previous_value_by_accumulator_key = current_value_by_accumulator_key.clone();
+        }
+    }
+
+    return statistics;
+}
+
+// Each card has a value named after its account id, TOTAL is what is owed across the cards against their combined limit
+fn calculate_credit_utilisation(transactions: Vec<Transaction>, accounts: Vec<Account>, credit_cards: Vec<CreditCard>, dates: Vec<NaiveDate>) -> Vec<Statistic> {
+    return calculate_internal(transactions, accounts, dates, |transaction| transaction.transaction_type == Balance, add_balance, |map, _| {
+        let mut utilisation: HashMap<String, Decimal> = HashMap::new();
+        let mut owed = Decimal::ZERO;
+        let mut limit = Decimal::ZERO;
+        for credit_card in &credit_cards {
+            if let Some(balance) = map.get(&credit_card.account_id) {
+                utilisation.insert(credit_card.account_id.clone(), credit_utilisation(credit_card, *balance));
+                owed += (-balance).max(Decimal::ZERO);
+                limit += credit_card.credit_limit;
+            }
+        }
+        utilisation.insert("TOTAL".to_string(), percentage(owed, limit));
+        return utilisation;
+    });
+}
+
+fn calculate_allocation(transactions: Vec<Transaction>, accounts: Vec<Account>, expenses: Vec<Expense>, expense_categories: Vec<ExpenseCategory>, dates: Vec<NaiveDate>) -> Vec<Statistic> {
+    let spending_by_date: HashMap<NaiveDate, Decimal> = calculate_expenses(transactions.clone(), accounts.clone(), expenses, expense_categories, dates.clone()).iter()
+        .map(|statistic| (statistic.date, statistic.values.iter()
+            .find(|value| value.name == "TOTAL")
+            .map(|value| value.value_difference)
+            .unwrap_or(Decimal::ZERO))
+        ).collect();
+
+    let mut statistics: Vec<Statistic> = vec![];
+
+    let account_by_account_id: HashMap<String, Account> = accounts.iter()
+        .map(|account| (account.id.clone(), account.clone()))
+        .collect();
+
+    let mut current_balances_by_account_id: HashMap<String, Decimal> = accounts.iter()
+        .map(|account| (account.id.clone(), Decimal::ZERO))
+        .collect();
+
+    let mut previous_allocation: HashMap<String, Decimal> = HashMap::new();
+    let mut previous_date: Option<NaiveDate> = None;
+
+    let mut transaction_iterator = transactions.iter().filter(|transaction| transaction.transaction_type == Balance);
+    let mut first_transaction_encountered = false;
+    let mut current_transaction = transaction_iterator.next();
+
+    for date in dates {
+        while current_transaction.is_some() && current_transaction.unwrap().date <= date {
+            current_balances_by_account_id = add_balance(current_balances_by_account_id, current_transaction.unwrap());
+            current_transaction = transaction_iterator.next();
+            first_transaction_encountered = true;
+        }
+
+        let totals = accumulate_totals(&current_balances_by_account_id, &account_by_account_id);
+        let mut current_allocation = calculate_allocation_total(&totals, &current_balances_by_account_id, &account_by_account_id);
+
+        // Spending is per period, so scale it to a month to keep the ratio comparable between periods
+        // and report 0 when there is no earlier period or no spending to compare against
+        let spending = spending_by_date.get(&date).cloned().unwrap_or(Decimal::ZERO).abs();
+        let monthly_spending = match previous_date {
+            Some(previous_date) if !spending.is_zero() => {
+                let days = Decimal::from((date - previous_date).num_days());
+                spending * Decimal::from(365) / (days * Decimal::from(12))
+            }
+            _ => Decimal::ZERO,
+        };
+        let liquid = totals.get(&TotalType::Cash).unwrap() + totals.get(&TotalType::ShortTermAsset).unwrap();
+        current_allocation.insert("LIQUID_TO_MONTHLY_SPENDING".to_string(), ratio(liquid, monthly_spending));
+
+        if first_transaction_encountered {
+            statistics.push(create_statistic_by_key(date, &current_allocation, &previous_allocation));
+            previous_allocation = current_allocation.clone();
+        }
+        previous_date = Some(date);
+    }
+
+    return statistics;
+}
+
 fn calculate_internal<
     Filter: FnMut(&Transaction) -> bool,
     Mapper: FnMut(HashMap<String, Decimal>, &Transaction) -> HashMap<String, Decimal>,
@@ -230,12 +487,15 @@ fn calculate_internal<
 }
 
 fn calculate_flow_internal<
+    TotalKey: Hash + PartialEq + Eq,
+    Totaller: FnMut(&HashMap<String, Decimal>, &HashMap<String, Account>) -> HashMap<TotalKey, Decimal>,
     AccumulatorKey: Hash + PartialEq + Eq + Display + Clone,
-    Accumulator: FnMut(&HashMap<TotalType, Decimal>, &HashMap<TotalType, Decimal>) -> HashMap<AccumulatorKey, Decimal>
+    Accumulator: FnMut(&HashMap<TotalKey, Decimal>, &HashMap<TotalKey, Decimal>) -> HashMap<AccumulatorKey, Decimal>
 >(
     transactions: Vec<Transaction>,
     accounts: Vec<Account>,
     dates: Vec<NaiveDate>,
+    mut totaller: Totaller,
     mut accumulator: Accumulator,
 ) -> Vec<Statistic> {
     let mut statistics: Vec<Statistic> = vec![];
@@ -269,8 +529,8 @@ fn calculate_flow_internal<
             first_transaction_encountered = true;
         }
 
-        let accumulated_balances = accumulate_totals(&current_balances_by_account_id, &account_by_account_id);
-        let accumulated_transfers = accumulate_totals(&current_transfers_by_account_id, &account_by_account_id);
+        let accumulated_balances = totaller(&current_balances_by_account_id, &account_by_account_id);
+        let accumulated_transfers = totaller(&current_transfers_by_account_id, &account_by_account_id);
 
         let current_flow = accumulator(&accumulated_balances, &accumulated_transfers);
 
@@ -299,12 +559,24 @@ fn accumulate_totals(map: &HashMap<String, Decimal>, account_by_account_id: &Has
     return value_by_total;
 }
 
+fn accumulate_groups(map: &HashMap<String, Decimal>, account_groups: &[AccountGroup]) -> HashMap<String, Decimal> {
+    // Accounts can belong to many groups so each group is totalled independently
+    return account_groups.iter()
+        .map(|account_group| (account_group.id.clone(), account_group.account_ids.iter()
+            .map(|account_id| map.get(account_id).cloned().unwrap_or(Decimal::ZERO))
+            .sum()))
+        .collect();
+}
+
 fn add_transfer(mut map: HashMap<String, Decimal>, transaction: &Transaction) -> HashMap<String, Decimal> {
-    let value = map.remove(&transaction.account_id.clone()).unwrap();
-    map.insert(transaction.account_id.clone(), value + transaction.value);
+    // Either side may have been filtered out, in which case only the remaining side moves
+    if let Some(value) = map.remove(&transaction.account_id) {
+        map.insert(transaction.account_id.clone(), value + transaction.value);
+    }
     let from_account_id = transaction.from_account_id.clone().unwrap();
-    let value = map.remove(&from_account_id).unwrap();
-    map.insert(from_account_id, value - transaction.value);
+    if let Some(value) = map.remove(&from_account_id) {
+        map.insert(from_account_id, value - transaction.value);
+    }
     return map;
 }
 
@@ -337,6 +609,49 @@ fn calculate_flow_grouping_total(balances: &HashMap<TotalType, Decimal>, transfe
     return flow_grouping_total;
 }
 
+fn calculate_allocation_total(totals: &HashMap<TotalType, Decimal>, balances_by_account_id: &HashMap<String, Decimal>, account_by_account_id: &HashMap<String, Account>) -> HashMap<String, Decimal> {
+    let asset_types = [TotalType::Cash, TotalType::ShortTermAsset, TotalType::LongTermAsset, TotalType::PhysicalAsset, TotalType::Retirement];
+    let liability_types = [TotalType::ShortTermLiability, TotalType::LongTermLiability];
+
+    let assets: Decimal = asset_types.iter().map(|total_type| totals.get(total_type).unwrap()).sum();
+    // Liabilities are stored as negative balances
+    let liabilities: Decimal = liability_types.iter().map(|total_type| totals.get(total_type).unwrap().abs()).sum();
+    let net_worth = assets - liabilities;
+
+    let mut allocation: HashMap<String, Decimal> = HashMap::new();
+    for total_type in asset_types.iter() {
+        allocation.insert(total_type.to_string(), percentage(*totals.get(total_type).unwrap(), assets));
+    }
+    for total_type in liability_types.iter() {
+        allocation.insert(total_type.to_string(), percentage(totals.get(total_type).unwrap().abs(), assets));
+    }
+    allocation.insert("DEBT_TO_ASSET".to_string(), ratio(liabilities, assets));
+
+    for (account_id, balance) in balances_by_account_id.iter() {
+        if total_type_from_account(account_by_account_id.get(account_id).unwrap()) != TotalType::Income {
+            allocation.insert(account_id.clone(), percentage(*balance, net_worth));
+        }
+    }
+    return allocation;
+}
+
+fn percentage(value: Decimal, total: Decimal) -> Decimal {
+    return ratio(value * Decimal::ONE_HUNDRED, total);
+}
+
+fn ratio(value: Decimal, total: Decimal) -> Decimal {
+    if total.is_zero() {
+        return Decimal::ZERO;
+    }
+    return (value / total).round_dp(2);
+}
+
+fn calculate_group_flow_total(balances: &HashMap<String, Decimal>, transfers: &HashMap<String, Decimal>) -> HashMap<String, Decimal> {
+    return balances.iter()
+        .map(|(account_group_id, balance)| (account_group_id.clone(), balance - transfers.get(account_group_id).unwrap()))
+        .collect();
+}
+
 fn create_statistic_by_key<T: ToString + PartialEq + Eq + Hash>(date: NaiveDate, current_values_by_key: &HashMap<T, Decimal>, previous_values_by_key: &HashMap<T, Decimal>) -> Statistic {
     let values = current_values_by_key.iter()
         .map(|(account_id, transfer)| {
@@ -346,12 +661,218 @@ fn create_statistic_by_key<T: ToString + PartialEq + Eq + Hash>(date: NaiveDate,
                 value_difference: previous_values_by_key.get(&account_id)
                     .map(|previous_value| transfer - previous_value)
                     .unwrap_or(Decimal::ZERO),
+                value_smoothed: None,
+                value_difference_smoothed: None,
+                value_trend: None,
+                value_difference_trend: None,
             }
         }).collect();
 
     Statistic { date, values }
 }
 
+fn smooth(mut statistics: Vec<Statistic>, smoothings: &Smoothings) -> Vec<Statistic> {
+    if smoothings.smoothing.is_none() && !smoothings.trend {
+        return statistics;
+    }
+
+    // Statistics are ordered by date, so collect each named series in order alongside where its entries live
+    let mut positions_by_name: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+    for (statistic_index, statistic) in statistics.iter().enumerate() {
+        for (value_index, value) in statistic.values.iter().enumerate() {
+            positions_by_name.entry(value.name.clone())
+                .or_default()
+                .push((statistic_index, value_index));
+        }
+    }
+
+    for positions in positions_by_name.values() {
+        let values: Vec<Decimal> = positions.iter()
+            .map(|(statistic_index, value_index)| statistics[*statistic_index].values[*value_index].value)
+            .collect();
+        let value_differences: Vec<Decimal> = positions.iter()
+            .map(|(statistic_index, value_index)| statistics[*statistic_index].values[*value_index].value_difference)
+            .collect();
+
+        let (values_smoothed, value_differences_smoothed) = match smoothings.smoothing {
+            Some(Smoothing::Simple) => (simple_moving_average(&values, smoothings.window), simple_moving_average(&value_differences, smoothings.window)),
+            Some(Smoothing::Exponential) => (exponential_moving_average(&values, smoothings.window), exponential_moving_average(&value_differences, smoothings.window)),
+            None => (vec![None; values.len()], vec![None; values.len()]),
+        };
+        let (values_trend, value_differences_trend) = if smoothings.trend {
+            (least_squares_trend(&values), least_squares_trend(&value_differences))
+        } else {
+            (vec![None; values.len()], vec![None; values.len()])
+        };
+
+        for (index, (statistic_index, value_index)) in positions.iter().enumerate() {
+            let value = &mut statistics[*statistic_index].values[*value_index];
+            value.value_smoothed = values_smoothed[index];
+            value.value_difference_smoothed = value_differences_smoothed[index];
+            value.value_trend = values_trend[index];
+            value.value_difference_trend = value_differences_trend[index];
+        }
+    }
+
+    return statistics;
+}
+
+fn simple_moving_average(values: &[Decimal], window: usize) -> Vec<Option<Decimal>> {
+    return (0..values.len())
+        .map(|index| {
+            if index + 1 < window {
+                return None;
+            }
+            let sum: Decimal = values[index + 1 - window..=index].iter().sum();
+            return Some((sum / Decimal::from(window)).round_dp(2));
+        })
+        .collect();
+}
+
+fn exponential_moving_average(values: &[Decimal], window: usize) -> Vec<Option<Decimal>> {
+    // Standard smoothing factor so the window roughly matches the centre of mass of a simple moving average
+    let alpha = Decimal::TWO / Decimal::from(window + 1);
+    let mut current: Option<Decimal> = None;
+    return values.iter()
+        .map(|value| {
+            let next = match current {
+                None => *value,
+                Some(previous) => alpha * value + (Decimal::ONE - alpha) * previous,
+            };
+            current = Some(next);
+            return Some(next.round_dp(2));
+        })
+        .collect();
+}
+
+fn least_squares_trend(values: &[Decimal]) -> Vec<Option<Decimal>> {
+    let count = Decimal::from(values.len());
+    if values.len() < 2 {
+        return values.iter().map(|value| Some(*value)).collect();
+    }
+    let mut sum_x = Decimal::ZERO;
+    let mut sum_y = Decimal::ZERO;
+    let mut sum_xy = Decimal::ZERO;
+    let mut sum_xx = Decimal::ZERO;
+    for (index, value) in values.iter().enumerate() {
+        let x = Decimal::from(index);
+        sum_x += x;
+        sum_y += value;
+        sum_xy += x * value;
+        sum_xx += x * x;
+    }
+    let slope = (count * sum_xy - sum_x * sum_y) / (count * sum_xx - sum_x * sum_x);
+    let intercept = (sum_y - slope * sum_x) / count;
+    return (0..values.len())
+        .map(|index| Some((intercept + slope * Decimal::from(index)).round_dp(2)))
+        .collect();
+}
+
+fn filter_accounts(mut transactions: Vec<Transaction>, mut accounts: Vec<Account>, mut expenses: Vec<Expense>, account_groups: &[AccountGroup], account_filter: &AccountFilter) -> (Vec<Transaction>, Vec<Account>, Vec<Expense>) {
+    let grouped_account_ids: Option<HashSet<String>> = account_filter.account_group_ids.as_ref()
+        .map(|account_group_ids| account_groups.iter()
+            .filter(|account_group| account_group_ids.contains(&account_group.id))
+            .flat_map(|account_group| account_group.account_ids.clone())
+            .collect());
+    let hidden_account_ids: HashSet<String> = accounts.iter()
+        .filter(|account| !account_filter.hidden && account.hidden)
+        .map(|account| account.id.clone())
+        .collect();
+
+    accounts.retain(|account| {
+        (account_filter.hidden || !account.hidden)
+            && account_filter.account_ids.as_ref().is_none_or(|account_ids| account_ids.contains(&account.id))
+            && account_filter.account_types.as_ref().is_none_or(|account_types| account_types.contains(&account.account_type))
+            && grouped_account_ids.as_ref().is_none_or(|account_ids| account_ids.contains(&account.id))
+    });
+
+    // Keep transfers that touch any remaining account so the remaining side still moves
+    let account_ids: HashSet<String> = accounts.iter().map(|account| account.id.clone()).collect();
+    transactions.retain(|transaction| {
+        account_ids.contains(&transaction.account_id)
+            || transaction.from_account_id.as_ref().is_some_and(|from_account_id| account_ids.contains(from_account_id))
+    });
+
+    // Expenses without an account are only left out once specific accounts are asked for
+    let narrowed = account_filter.account_ids.is_some()
+        || account_filter.account_types.is_some()
+        || account_filter.account_group_ids.is_some();
+    expenses.retain(|expense| match &expense.account_id {
+        None => !narrowed,
+        Some(account_id) => account_ids.contains(account_id) || (!narrowed && !hidden_account_ids.contains(account_id)),
+    });
+
+    return (transactions, accounts, expenses);
+}
+
+// Split expenses only count the share of the person marked as me, unsplit expenses count in full
+fn my_share_of_expenses(expenses: Vec<Expense>, persons: &[Person], expense_splits: &[ExpenseSplit]) -> anyhow::Result<Vec<Expense>> {
+    let me = persons.iter()
+        .find(|person| person.me)
+        .ok_or(anyhow!("No person is marked as me"))?;
+    let expense_splits_by_expense_id: HashMap<&str, &ExpenseSplit> = expense_splits.iter()
+        .map(|expense_split| (expense_split.expense_id.as_str(), expense_split))
+        .collect();
+    return Ok(expenses.into_iter()
+        .map(|expense| match expense_splits_by_expense_id.get(expense.id.as_str()) {
+            None => expense,
+            Some(expense_split) => {
+                let value = calculate_shares(expense_split, expense.value).into_iter()
+                    .filter(|(person_id, _)| *person_id == me.id)
+                    .map(|(_, share)| share)
+                    .sum();
+                Expense { value, ..expense }
+            }
+        })
+        .collect());
+}
+
+fn extract_account_filter(query: StatisticsQuery) -> anyhow::Result<AccountFilter> {
+    fn split(raw: Option<String>) -> Option<Vec<String>> {
+        return raw.map(|raw| raw.split(',')
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .collect());
+    }
+    let account_types = match split(query.account_types) {
+        None => None,
+        Some(raw_account_types) => {
+            let mut account_types = vec![];
+            for raw_account_type in raw_account_types {
+                account_types.push(AccountType::from_str(raw_account_type.to_uppercase().as_str())
+                    .map_err(|_| anyhow!("Unknown account type {raw_account_type}"))?);
+            }
+            Some(account_types)
+        }
+    };
+    return Ok(AccountFilter {
+        hidden: query.hidden.unwrap_or(true),
+        account_ids: split(query.account_ids),
+        account_types,
+        account_group_ids: split(query.account_group_ids),
+    });
+}
+
+fn extract_smoothings(query: StatisticsQuery) -> anyhow::Result<Smoothings> {
+    let smoothing = match query.smoothing {
+        None => None,
+        Some(raw_smoothing) => {
+            if raw_smoothing.eq_ignore_ascii_case("sma") {
+                Some(Smoothing::Simple)
+            } else if raw_smoothing.eq_ignore_ascii_case("ema") {
+                Some(Smoothing::Exponential)
+            } else {
+                return Err(anyhow!("Unknown smoothing {raw_smoothing}"));
+            }
+        }
+    };
+    let window = query.window.unwrap_or(3);
+    if window == 0 {
+        return Err(anyhow!("Smoothing window must be greater than 0"));
+    }
+    return Ok(Smoothings { smoothing, window, trend: query.trend.unwrap_or(false) });
+}
+
 fn extract_params(path: (String, String)) -> anyhow::Result<(Period, Category)> {
     let (raw_period, raw_category) = path.into();
     let period = if raw_period.eq_ignore_ascii_case("weekly") {
@@ -377,6 +898,20 @@ fn extract_params(path: (String, String)) -> anyhow::Result<(Period, Category)>
         Category::FlowGrouping
     } else if raw_category.eq_ignore_ascii_case("expenses")  {
         Category::Expenses
+    } else if raw_category.eq_ignore_ascii_case("allocation") {
+        Category::Allocation
+    } else if raw_category.eq_ignore_ascii_case("merchants") {
+        Category::Merchants
+    } else if raw_category.eq_ignore_ascii_case("tags") {
+        Category::Tags
+    } else if raw_category.eq_ignore_ascii_case("credit_utilisation") {
+        Category::CreditUtilisation
+    } else if raw_category.eq_ignore_ascii_case("group_balance") {
+        Category::GroupBalances
+    } else if raw_category.eq_ignore_ascii_case("group_transfer") {
+        Category::GroupTransfers
+    } else if raw_category.eq_ignore_ascii_case("group_flow") {
+        Category::GroupFlow
     } else {
         return Err(anyhow!("Unknown category {raw_category}"));
     };
@@ -533,4 +1068,73 @@ mod tests {
             NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
         ])
     }
-}
+
+    #[test]
+    fn test_simple_moving_average() {
+        let values = vec![Decimal::new(10, 0), Decimal::new(20, 0), Decimal::new(30, 0), Decimal::new(50, 0)];
+        assert_eq!(simple_moving_average(&values, 3), vec![
+            None,
+            None,
+            Some(Decimal::new(2000, 2)),
+            Some(Decimal::new(3333, 2)),
+        ]);
+        assert_eq!(simple_moving_average(&values, 1), values.iter().map(|value| Some(*value)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_exponential_moving_average() {
+        let values = vec![Decimal::new(10, 0), Decimal::new(20, 0), Decimal::new(30, 0)];
+        assert_eq!(exponential_moving_average(&values, 3), vec![
+            Some(Decimal::new(1000, 2)),
+            Some(Decimal::new(1500, 2)),
+            Some(Decimal::new(2250, 2)),
+        ]);
+    }
+
+    #[test]
+    fn test_least_squares_trend() {
+        let values = vec![Decimal::new(1, 0), Decimal::new(3, 0), Decimal::new(2, 0), Decimal::new(4, 0)];
+        assert_eq!(least_squares_trend(&values), vec![
+            Some(Decimal::new(130, 2)),
+            Some(Decimal::new(210, 2)),
+            Some(Decimal::new(290, 2)),
+            Some(Decimal::new(370, 2)),
+        ]);
+        assert_eq!(least_squares_trend(&[Decimal::new(5, 0)]), vec![Some(Decimal::new(5, 0))]);
+    }
+
+    #[test]
+    fn test_allocation_total() {
+        let account = |id: &str, account_type: AccountType| Account {
+            id: id.to_string(),
+            name: id.to_string(),
+            account_type,
+            hide_new_transactions: false,
+            transfer_without_balance_ignored: false,
+            no_regular_balance: false,
+            hidden: false,
+        };
+        let account_by_account_id: HashMap<String, Account> = vec![
+            account("checking", AccountType::Checking),
+            account("savings", AccountType::Savings),
+            account("card", AccountType::CreditCard),
+            account("external", AccountType::External),
+        ].into_iter().map(|account| (account.id.clone(), account)).collect();
+        let balances_by_account_id: HashMap<String, Decimal> = HashMap::from([
+            ("checking".to_string(), Decimal::new(250, 0)),
+            ("savings".to_string(), Decimal::new(750, 0)),
+            ("card".to_string(), Decimal::new(-200, 0)),
+            ("external".to_string(), Decimal::ZERO),
+        ]);
+        let totals = accumulate_totals(&balances_by_account_id, &account_by_account_id);
+
+        let allocation = calculate_allocation_total(&totals, &balances_by_account_id, &account_by_account_id);
+        assert_eq!(allocation.get("CASH"), Some(&Decimal::new(2500, 2)));
+        assert_eq!(allocation.get("SHORT_TERM_ASSET"), Some(&Decimal::new(7500, 2)));
+        assert_eq!(allocation.get("SHORT_TERM_LIABILITY"), Some(&Decimal::new(2000, 2)));
+        assert_eq!(allocation.get("DEBT_TO_ASSET"), Some(&Decimal::new(20, 2)));
+        assert_eq!(allocation.get("savings"), Some(&Decimal::new(9375, 2)));
+        assert_eq!(allocation.get("card"), Some(&Decimal::new(-2500, 2)));
+        assert_eq!(allocation.get("external"), None);
+    }
+}
\ No newline at end of file
@@ -15,4 +15,31 @@ pub struct Value {
     pub value: Decimal,
     #[serde(with = "rust_decimal::serde::float")]
     pub value_difference: Decimal,
+    // Only present when smoothing is requested and enough previous periods exist to fill the window
+    #[serde(with = "rust_decimal::serde::float_option", skip_serializing_if = "Option::is_none", default)]
+    pub value_smoothed: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::float_option", skip_serializing_if = "Option::is_none", default)]
+    pub value_difference_smoothed: Option<Decimal>,
+    // Only present when a trend is requested, least squares fit over the whole series
+    #[serde(with = "rust_decimal::serde::float_option", skip_serializing_if = "Option::is_none", default)]
+    pub value_trend: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::float_option", skip_serializing_if = "Option::is_none", default)]
+    pub value_difference_trend: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StatisticsQuery {
+    pub smoothing: Option<String>,
+    pub window: Option<usize>,
+    pub trend: Option<bool>,
+    // Hidden accounts are included unless this is false
+    pub hidden: Option<bool>,
+    // Comma separated restrictions, an account must match every one that is provided
+    pub account_ids: Option<String>,
+    pub account_types: Option<String>,
+    pub account_group_ids: Option<String>,
+    // Only count the share of split expenses that belongs to the person marked as me
+    pub my_share: Option<bool>,
+    // Ignore refunds and reimbursements instead of taking them off the category of the original expense
+    pub gross: Option<bool>,
 }
@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use chrono::{Days, Months, NaiveDate};
+use log::{error, info};
+use crate::subscription::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+use crate::expense::db::list_expenses;
+use crate::expense::schema::Expense;
+use crate::issue::schema::{Issue, IssueType};
+use crate::subscription::schema::{Subscription, SubscriptionCadence, SubscriptionProposal};
+
+// Fewer charges than this could just be a coincidence
+const MIN_CHARGES: usize = 3;
+
+#[post("/api/subscription/")]
+pub async fn create_subscription(db: web::Data<Pool>, new_subscription: web::Json<schema::NewSubscription>) -> Result<HttpResponse, Error> {
+    let new_subscription = new_subscription.into_inner();
+    info!("HTTP create_subscription new_subscription:[{:?}]", new_subscription);
+    do_in_transaction(&db, |transaction| db::create_subscription(transaction, new_subscription))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_subscription: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/subscription/proposal/")]
+pub async fn list_subscription_proposals(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_subscription_proposals");
+    do_in_transaction(&db, |transaction| {
+        let expenses = list_expenses(transaction)?;
+        let subscriptions = db::list_subscriptions(transaction)?;
+        return Ok(detect_subscriptions(&expenses, &subscriptions));
+    })
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_subscription_proposals: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[post("/api/subscription/{id}/")]
+pub async fn update_subscription(db: web::Data<Pool>, path: web::Path<String>, updated_subscription: web::Json<schema::Subscription>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let updated_subscription = updated_subscription.into_inner();
+    info!("HTTP update_subscription id:[{}] updated_subscription:[{:?}]", id, updated_subscription);
+    let option = crate::api::compare_ids(&id, &updated_subscription.id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::update_subscription(transaction, updated_subscription))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP update_subscription: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/subscription/{id}/")]
+pub async fn delete_subscription(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_subscription id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_subscription(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_subscription: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/subscription/{id}/")]
+pub async fn get_subscription(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_subscription id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_subscription(transaction, id))
+        .await.map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_subscription: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/subscription/")]
+pub async fn list_subscriptions(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_subscriptions");
+    do_in_transaction(&db, |transaction| db::list_subscriptions(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_subscriptions: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+// Merchants charging at a steady weekly, monthly or yearly interval that aren't already subscriptions
+pub fn detect_subscriptions(expenses: &[Expense], subscriptions: &[Subscription]) -> Vec<SubscriptionProposal> {
+    let mut expenses_by_merchant_id: BTreeMap<String, Vec<&Expense>> = BTreeMap::new();
+    for expense in expenses {
+        if let Some(merchant_id) = &expense.merchant_id {
+            if subscriptions.iter().all(|subscription| subscription.merchant_id != *merchant_id) {
+                expenses_by_merchant_id.entry(merchant_id.clone()).or_default().push(expense);
+            }
+        }
+    }
+
+    let mut subscription_proposals: Vec<SubscriptionProposal> = vec![];
+    for (merchant_id, mut expenses) in expenses_by_merchant_id {
+        if expenses.len() < MIN_CHARGES {
+            continue;
+        }
+        expenses.sort_by_key(|expense| expense.date);
+        let intervals: Vec<i64> = expenses.windows(2)
+            .map(|pair| (pair[1].date - pair[0].date).num_days())
+            .collect();
+        let cadence = [SubscriptionCadence::Weekly, SubscriptionCadence::Monthly, SubscriptionCadence::Yearly].into_iter()
+            .find(|cadence| {
+                let (min, max) = interval_range(cadence);
+                return intervals.iter().all(|interval| min <= *interval && *interval <= max);
+            });
+        if let Some(cadence) = cadence {
+            let latest = expenses.last().unwrap();
+            subscription_proposals.push(SubscriptionProposal {
+                merchant_id,
+                cadence,
+                value: latest.value,
+                next_due: due_date(latest.date, &cadence, 1),
+                count: expenses.len(),
+            });
+        }
+    }
+    return subscription_proposals;
+}
+
+// Only the latest due date is checked, a charge up to the grace period either side of it counts
+pub fn calculate_subscription_issues(subscriptions: &[Subscription], expenses: &[Expense], today: NaiveDate) -> Vec<Issue> {
+    let mut issues: Vec<Issue> = vec![];
+    for subscription in subscriptions {
+        let grace = Days::new(grace_days(&subscription.cadence));
+        if today < subscription.start - grace {
+            continue;
+        }
+        let mut index = 0;
+        while due_date(subscription.start, &subscription.cadence, index + 1) - grace <= today {
+            index += 1;
+        }
+        let due = due_date(subscription.start, &subscription.cadence, index);
+        let window_start = due - grace;
+        let window_end = due_date(subscription.start, &subscription.cadence, index + 1) - grace;
+
+        let mut charges: Vec<&Expense> = expenses.iter()
+            .filter(|expense| expense.merchant_id.as_ref() == Some(&subscription.merchant_id))
+            .collect();
+        charges.sort_by_key(|expense| expense.date);
+        let previous_value = charges.iter()
+            .rev()
+            .find(|expense| expense.date < window_start)
+            .map(|expense| expense.value)
+            .unwrap_or(subscription.value);
+        let charge = charges.iter()
+            .find(|expense| window_start <= expense.date && expense.date < window_end);
+
+        let mut issue_types: Vec<IssueType> = vec![];
+        match charge {
+            None => {
+                if due + grace < today {
+                    issue_types.push(IssueType::SubscriptionMissing);
+                }
+            }
+            Some(charge) => {
+                if due + grace < charge.date {
+                    issue_types.push(IssueType::SubscriptionLate);
+                }
+                if previous_value < charge.value {
+                    issue_types.push(IssueType::SubscriptionPriceIncrease);
+                }
+            }
+        }
+        for issue_type in issue_types {
+            issues.push(Issue {
+                issue_type,
+                date: Some(due),
+                account_id: None,
+                from_account_id: None,
+                category_id: None,
+                subscription_id: Some(subscription.id.clone()),
+            });
+        }
+    }
+    return issues;
+}
+
+fn due_date(start: NaiveDate, cadence: &SubscriptionCadence, index: u32) -> NaiveDate {
+    // Always counted from the start so the day of the month doesn't drift after a short month
+    return match cadence {
+        SubscriptionCadence::Weekly => start + Days::new(7 * index as u64),
+        SubscriptionCadence::Monthly => start.checked_add_months(Months::new(index)).unwrap(),
+        SubscriptionCadence::Yearly => start.checked_add_months(Months::new(12 * index)).unwrap(),
+    };
+}
+
+fn interval_range(cadence: &SubscriptionCadence) -> (i64, i64) {
+    return match cadence {
+        SubscriptionCadence::Weekly => (6, 8),
+        SubscriptionCadence::Monthly => (27, 34),
+        SubscriptionCadence::Yearly => (358, 372),
+    };
+}
+
+fn grace_days(cadence: &SubscriptionCadence) -> u64 {
+    return match cadence {
+        SubscriptionCadence::Weekly => 2,
+        SubscriptionCadence::Monthly => 5,
+        SubscriptionCadence::Yearly => 14,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+    use super::*;
+
+    fn subscription(merchant_id: &str, value: i64, start: NaiveDate) -> Subscription {
+        Subscription {
+            id: merchant_id.to_string(),
+            merchant_id: merchant_id.to_string(),
+            cadence: SubscriptionCadence::Monthly,
+            value: Decimal::new(value, 2),
+            start,
+        }
+    }
+
+    #[test]
+    fn test_detect_subscriptions() {
+        let expenses = vec![
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(), merchant_id: Some("streaming".to_string()), ..Expense::for_test("streaming-1-31", "SUBSCRIPTIONS", Decimal::new(999, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(), merchant_id: Some("streaming".to_string()), ..Expense::for_test("streaming-2-29", "SUBSCRIPTIONS", Decimal::new(999, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(), merchant_id: Some("streaming".to_string()), ..Expense::for_test("streaming-3-31", "SUBSCRIPTIONS", Decimal::new(1199, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), merchant_id: Some("gym".to_string()), ..Expense::for_test("gym-1-1", "SUBSCRIPTIONS", Decimal::new(3000, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 8).unwrap(), merchant_id: Some("gym".to_string()), ..Expense::for_test("gym-1-8", "SUBSCRIPTIONS", Decimal::new(3000, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(), merchant_id: Some("gym".to_string()), ..Expense::for_test("gym-1-15", "SUBSCRIPTIONS", Decimal::new(3000, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(), merchant_id: Some("shop".to_string()), ..Expense::for_test("shop-1-3", "SUBSCRIPTIONS", Decimal::new(2000, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(), merchant_id: Some("shop".to_string()), ..Expense::for_test("shop-1-10", "SUBSCRIPTIONS", Decimal::new(500, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 2, 20).unwrap(), merchant_id: Some("shop".to_string()), ..Expense::for_test("shop-2-20", "SUBSCRIPTIONS", Decimal::new(4000, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(), merchant_id: Some("phone".to_string()), ..Expense::for_test("phone-1-3", "SUBSCRIPTIONS", Decimal::new(1500, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 2, 3).unwrap(), merchant_id: Some("phone".to_string()), ..Expense::for_test("phone-2-3", "SUBSCRIPTIONS", Decimal::new(1500, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 3, 3).unwrap(), merchant_id: Some("phone".to_string()), ..Expense::for_test("phone-3-3", "SUBSCRIPTIONS", Decimal::new(1500, 2)) },
+        ];
+
+        let subscription_proposals = detect_subscriptions(&expenses, &[subscription("phone", 1500, NaiveDate::from_ymd_opt(2024, 1, 3).unwrap())]);
+        assert_eq!(vec![
+            SubscriptionProposal { merchant_id: "gym".to_string(), cadence: SubscriptionCadence::Weekly, value: Decimal::new(3000, 2), next_due: NaiveDate::from_ymd_opt(2024, 1, 22).unwrap(), count: 3 },
+            SubscriptionProposal { merchant_id: "streaming".to_string(), cadence: SubscriptionCadence::Monthly, value: Decimal::new(1199, 2), next_due: NaiveDate::from_ymd_opt(2024, 4, 30).unwrap(), count: 3 },
+        ], subscription_proposals);
+    }
+
+    #[test]
+    fn test_calculate_subscription_issues() {
+        let subscriptions = vec![
+            subscription("streaming", 999, NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()),
+            subscription("phone", 1500, NaiveDate::from_ymd_opt(2024, 1, 12).unwrap()),
+            subscription("news", 500, NaiveDate::from_ymd_opt(2024, 1, 10).unwrap()),
+        ];
+        let expenses = vec![
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(), merchant_id: Some("streaming".to_string()), ..Expense::for_test("streaming-1-31", "SUBSCRIPTIONS", Decimal::new(999, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(), merchant_id: Some("streaming".to_string()), ..Expense::for_test("streaming-2-29", "SUBSCRIPTIONS", Decimal::new(1199, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(), merchant_id: Some("phone".to_string()), ..Expense::for_test("phone-1-12", "SUBSCRIPTIONS", Decimal::new(1500, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(), merchant_id: Some("news".to_string()), ..Expense::for_test("news-1-10", "SUBSCRIPTIONS", Decimal::new(500, 2)) },
+            Expense { date: NaiveDate::from_ymd_opt(2024, 2, 20).unwrap(), merchant_id: Some("news".to_string()), ..Expense::for_test("news-2-20", "SUBSCRIPTIONS", Decimal::new(500, 2)) },
+        ];
+
+        let issue_types = |today: NaiveDate| calculate_subscription_issues(&subscriptions, &expenses, today).into_iter()
+            .map(|issue| (issue.subscription_id.unwrap(), issue.issue_type, issue.date.unwrap()))
+            .collect::<Vec<_>>();
+        // The short month still counts as the 29th for February and the 31st for March
+        assert_eq!(vec![
+            ("streaming".to_string(), IssueType::SubscriptionPriceIncrease, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+            ("phone".to_string(), IssueType::SubscriptionMissing, NaiveDate::from_ymd_opt(2024, 2, 12).unwrap()),
+            ("news".to_string(), IssueType::SubscriptionLate, NaiveDate::from_ymd_opt(2024, 2, 10).unwrap()),
+        ], issue_types(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()));
+        // Streaming is still within the grace period of its next charge
+        assert_eq!(vec![
+            ("phone".to_string(), IssueType::SubscriptionMissing, NaiveDate::from_ymd_opt(2024, 3, 12).unwrap()),
+            ("news".to_string(), IssueType::SubscriptionMissing, NaiveDate::from_ymd_opt(2024, 3, 10).unwrap()),
+        ], issue_types(NaiveDate::from_ymd_opt(2024, 3, 27).unwrap()));
+    }
+}
@@ -0,0 +1,63 @@
+use const_format::formatcp;
+use rusqlite::Transaction;
+use uuid::Uuid;
+use crate::db::{list, single};
+use crate::merchant::db::verify_merchant_id_exists;
+use crate::subscription::schema::{NewSubscription, Subscription};
+
+const SUBSCRIPTION_COLUMNS: &str = "id, merchant_id, cadence, value, start";
+const SUBSCRIPTION_SELECT: &str = formatcp!("SELECT {SUBSCRIPTION_COLUMNS} FROM subscription");
+const SUBSCRIPTION_RETURNING: &str = formatcp!("RETURNING {SUBSCRIPTION_COLUMNS}");
+const SUBSCRIPTION_ORDERING: &str = "ORDER BY merchant_id, start, id ASC";
+
+pub fn create_subscription(transaction: &Transaction, new_subscription: NewSubscription) -> anyhow::Result<Option<Subscription>> {
+    verify_merchant_id_exists(transaction, new_subscription.merchant_id.clone())?;
+    return single(
+        transaction,
+        formatcp!("INSERT INTO subscription ({SUBSCRIPTION_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5) {SUBSCRIPTION_RETURNING}"),
+        [Uuid::new_v4().to_string(), new_subscription.merchant_id, new_subscription.cadence.to_string(), new_subscription.value.round_dp(2).to_string(), new_subscription.start.to_string()],
+    );
+}
+
+pub fn update_subscription(transaction: &Transaction, updated_subscription: Subscription) -> anyhow::Result<Option<Subscription>> {
+    verify_merchant_id_exists(transaction, updated_subscription.merchant_id.clone())?;
+    return single(
+        transaction,
+        formatcp!("UPDATE subscription SET merchant_id = ?1, cadence = ?2, value = ?3, start = ?4 WHERE id = ?5 {SUBSCRIPTION_RETURNING}"),
+        [updated_subscription.merchant_id, updated_subscription.cadence.to_string(), updated_subscription.value.round_dp(2).to_string(), updated_subscription.start.to_string(), updated_subscription.id],
+    );
+}
+
+pub fn delete_subscription(transaction: &Transaction, id: String) -> anyhow::Result<Option<Subscription>> {
+    return single(
+        transaction,
+        formatcp!("DELETE FROM subscription WHERE id = ?1 {SUBSCRIPTION_RETURNING}"),
+        [id],
+    );
+}
+
+pub fn get_subscription(transaction: &Transaction, id: String) -> anyhow::Result<Option<Subscription>> {
+    return single(
+        transaction,
+        formatcp!("{SUBSCRIPTION_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn list_subscriptions(transaction: &Transaction) -> anyhow::Result<Vec<Subscription>> {
+    return list(
+        transaction,
+        formatcp!("{SUBSCRIPTION_SELECT} {SUBSCRIPTION_ORDERING}"),
+        [],
+    );
+}
+
+pub fn cascade_delete_merchant(transaction: &Transaction, merchant_id: String) -> anyhow::Result<()> {
+    transaction.execute("DELETE FROM subscription WHERE merchant_id = ?1", [merchant_id])?;
+    return Ok(());
+}
+
+pub fn cascade_merge_merchant(transaction: &Transaction, merchant_id: String, into_merchant_id: String) -> anyhow::Result<()> {
+    transaction.execute("UPDATE subscription SET merchant_id = ?1 WHERE merchant_id = ?2", [into_merchant_id, merchant_id])?;
+    return Ok(());
+}
@@ -0,0 +1,82 @@
+#![allow(unreachable_patterns)]
+
+use std::str::FromStr;
+use chrono::NaiveDate;
+use rusqlite::Row;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use crate::db::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Subscription {
+    pub id: String,
+    #[serde(rename = "merchantId")]
+    pub merchant_id: String,
+    pub cadence: SubscriptionCadence,
+    // Expected amount of each charge
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    // First charge that is checked, every later due date is a whole number of cadences after it
+    pub start: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewSubscription {
+    #[serde(rename = "merchantId")]
+    pub merchant_id: String,
+    pub cadence: SubscriptionCadence,
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    pub start: NaiveDate,
+}
+
+// Confirmed by creating a subscription from it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubscriptionProposal {
+    #[serde(rename = "merchantId")]
+    pub merchant_id: String,
+    pub cadence: SubscriptionCadence,
+    // The latest charge
+    #[serde(with = "rust_decimal::serde::float")]
+    pub value: Decimal,
+    #[serde(rename = "nextDue")]
+    pub next_due: NaiveDate,
+    // Number of charges the proposal is based on
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Copy, Display, EnumString, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+pub enum SubscriptionCadence {
+    #[serde(rename = "WEEKLY" )]
+    #[strum(serialize="WEEKLY", to_string="WEEKLY")]
+    Weekly,
+    #[serde(rename = "MONTHLY" )]
+    #[strum(serialize="MONTHLY", to_string="MONTHLY")]
+    Monthly,
+    #[serde(rename = "YEARLY" )]
+    #[strum(serialize="YEARLY", to_string="YEARLY")]
+    Yearly
+}
+
+impl FromRow for Subscription {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Subscription {
+            id: row.get("id")?,
+            merchant_id: row.get("merchant_id")?,
+            cadence: row.get("cadence")?,
+            value: crate::db::get_decimal(row, "value")?,
+            start: crate::db::get_naive_date(row, "start")?,
+        })
+    }
+}
+
+impl FromSql for SubscriptionCadence {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str() {
+            Ok(value) => SubscriptionCadence::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
+            Err(err) => Err(err)
+        }
+    }
+}
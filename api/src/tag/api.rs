@@ -0,0 +1,126 @@
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use log::{error, info};
+use crate::tag::{db, schema};
+use crate::db::{do_in_transaction, Pool};
+
+#[post("/api/tag/")]
+pub async fn create_tag(db: web::Data<Pool>, new_tag: web::Json<schema::NewTag>) -> Result<HttpResponse, Error> {
+    let new_tag = new_tag.into_inner();
+    info!("HTTP create_tag new_tag:[{:?}]", new_tag);
+    do_in_transaction(&db, |transaction| db::create_tag(transaction, new_tag))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP create_tag: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+
+#[post("/api/tag/{id}/")]
+pub async fn update_tag(db: web::Data<Pool>, path: web::Path<String>, updated_tag: web::Json<schema::Tag>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let updated_tag = updated_tag.into_inner();
+    info!("HTTP update_tag id:[{}] updated_tag:[{:?}]", id, updated_tag);
+    let option = crate::api::compare_ids(&id, &updated_tag.id);
+    if option.is_some() {
+        return Err(option.unwrap());
+    }
+    do_in_transaction(&db, |transaction| db::update_tag(transaction, updated_tag))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP update_tag: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/tag/{id}/")]
+pub async fn delete_tag(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP delete_tag id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::delete_tag(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP delete_tag: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/tag/{id}/")]
+pub async fn get_tag(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_tag id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_tag(transaction, id))
+        .await.map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_tag: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/tag/")]
+pub async fn list_tags(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_tags");
+    do_in_transaction(&db, |transaction| db::list_tags(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_tags: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+#[post("/api/expense/{id}/tag/")]
+pub async fn set_expense_tags(db: web::Data<Pool>, path: web::Path<String>, tag_assignment: web::Json<schema::TagAssignment>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let tag_assignment = tag_assignment.into_inner();
+    info!("HTTP set_expense_tags id:[{}] tag_assignment:[{:?}]", id, tag_assignment);
+    do_in_transaction(&db, |transaction| db::set_expense_tags(transaction, id, tag_assignment.tag_ids))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP set_expense_tags: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/expense/{id}/tag/")]
+pub async fn get_expense_tags(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_expense_tags id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_expense_tags(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_expense_tags: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[post("/api/transaction/{id}/tag/")]
+pub async fn set_transaction_tags(db: web::Data<Pool>, path: web::Path<String>, tag_assignment: web::Json<schema::TagAssignment>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    let tag_assignment = tag_assignment.into_inner();
+    info!("HTTP set_transaction_tags id:[{}] tag_assignment:[{:?}]", id, tag_assignment);
+    do_in_transaction(&db, |transaction| db::set_transaction_tags(transaction, id, tag_assignment.tag_ids))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP set_transaction_tags: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/transaction/{id}/tag/")]
+pub async fn get_transaction_tags(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_transaction_tags id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_transaction_tags(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_transaction_tags: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+use anyhow::anyhow;
+use const_format::formatcp;
+use rusqlite::Transaction;
+use uuid::Uuid;
+use crate::db::{list, single};
+use crate::expense::db::get_expense;
+use crate::tag::schema::{NewTag, Tag, TagLink};
+use crate::transaction::db::get_transaction;
+
+const TAG_COLUMNS: &str = "id, name";
+const TAG_SELECT: &str = formatcp!("SELECT {TAG_COLUMNS} FROM tag");
+const TAG_RETURNING: &str = formatcp!("RETURNING {TAG_COLUMNS}");
+const TAG_ORDERING: &str = "ORDER BY name, id ASC";
+
+pub fn create_tag(transaction: &Transaction, new_tag: NewTag) -> anyhow::Result<Option<Tag>> {
+    return single(
+        transaction,
+        formatcp!("INSERT INTO tag ({TAG_COLUMNS}) VALUES (?1, ?2) {TAG_RETURNING}"),
+        [Uuid::new_v4().to_string(), new_tag.name],
+    );
+}
+
+pub fn update_tag(transaction: &Transaction, updated_tag: Tag) -> anyhow::Result<Option<Tag>> {
+    return single(
+        transaction,
+        formatcp!("UPDATE tag SET name = ?1 WHERE id = ?2 {TAG_RETURNING}"),
+        [updated_tag.name, updated_tag.id],
+    );
+}
+
+pub fn delete_tag(transaction: &Transaction, id: String) -> anyhow::Result<Option<Tag>> {
+    transaction.execute("DELETE FROM expense_tag WHERE tag_id = ?1", [id.clone()])?;
+    transaction.execute("DELETE FROM transaction_tag WHERE tag_id = ?1", [id.clone()])?;
+    return single(
+        transaction,
+        formatcp!("DELETE FROM tag WHERE id = ?1 {TAG_RETURNING}"),
+        [id],
+    );
+}
+
+pub fn get_tag(transaction: &Transaction, id: String) -> anyhow::Result<Option<Tag>> {
+    return single(
+        transaction,
+        formatcp!("{TAG_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn list_tags(transaction: &Transaction) -> anyhow::Result<Vec<Tag>> {
+    return list(
+        transaction,
+        formatcp!("{TAG_SELECT} {TAG_ORDERING}"),
+        [],
+    );
+}
+
+pub fn set_expense_tags(transaction: &Transaction, expense_id: String, tag_ids: Vec<String>) -> anyhow::Result<Option<Vec<Tag>>> {
+    if get_expense(transaction, expense_id.clone())?.is_none() {
+        return Ok(None);
+    }
+    verify_tag_ids_exist(transaction, &tag_ids)?;
+    transaction.execute("DELETE FROM expense_tag WHERE expense_id = ?1", [expense_id.clone()])?;
+    for tag_id in tag_ids {
+        transaction.execute("INSERT OR IGNORE INTO expense_tag (expense_id, tag_id) VALUES (?1, ?2)", [expense_id.clone(), tag_id])?;
+    }
+    return get_expense_tags(transaction, expense_id);
+}
+
+pub fn get_expense_tags(transaction: &Transaction, expense_id: String) -> anyhow::Result<Option<Vec<Tag>>> {
+    if get_expense(transaction, expense_id.clone())?.is_none() {
+        return Ok(None);
+    }
+    return list(
+        transaction,
+        formatcp!("{TAG_SELECT} WHERE id IN (SELECT tag_id FROM expense_tag WHERE expense_id = ?1) {TAG_ORDERING}"),
+        [expense_id],
+    ).map(Some);
+}
+
+pub fn set_transaction_tags(transaction: &Transaction, transaction_id: String, tag_ids: Vec<String>) -> anyhow::Result<Option<Vec<Tag>>> {
+    if get_transaction(transaction, transaction_id.clone())?.is_none() {
+        return Ok(None);
+    }
+    verify_tag_ids_exist(transaction, &tag_ids)?;
+    transaction.execute("DELETE FROM transaction_tag WHERE transaction_id = ?1", [transaction_id.clone()])?;
+    for tag_id in tag_ids {
+        transaction.execute("INSERT OR IGNORE INTO transaction_tag (transaction_id, tag_id) VALUES (?1, ?2)", [transaction_id.clone(), tag_id])?;
+    }
+    return get_transaction_tags(transaction, transaction_id);
+}
+
+pub fn get_transaction_tags(transaction: &Transaction, transaction_id: String) -> anyhow::Result<Option<Vec<Tag>>> {
+    if get_transaction(transaction, transaction_id.clone())?.is_none() {
+        return Ok(None);
+    }
+    return list(
+        transaction,
+        formatcp!("{TAG_SELECT} WHERE id IN (SELECT tag_id FROM transaction_tag WHERE transaction_id = ?1) {TAG_ORDERING}"),
+        [transaction_id],
+    ).map(Some);
+}
+
+pub fn list_expense_tag_links(transaction: &Transaction) -> anyhow::Result<Vec<TagLink>> {
+    return list(transaction, "SELECT expense_id AS item_id, tag_id FROM expense_tag", []);
+}
+
+pub fn list_transaction_tag_links(transaction: &Transaction) -> anyhow::Result<Vec<TagLink>> {
+    return list(transaction, "SELECT transaction_id AS item_id, tag_id FROM transaction_tag", []);
+}
+
+// Ids of the expenses or transactions that have at least one of the tags
+pub fn tagged_item_ids(tag_links: &[TagLink], tags: &str) -> HashSet<String> {
+    let tag_ids: HashSet<&str> = tags.split(',').map(|tag_id| tag_id.trim()).collect();
+    return tag_links.iter()
+        .filter(|tag_link| tag_ids.contains(tag_link.tag_id.as_str()))
+        .map(|tag_link| tag_link.item_id.clone())
+        .collect();
+}
+
+pub fn cascade_delete_expense(transaction: &Transaction, expense_id: String) -> anyhow::Result<()> {
+    transaction.execute("DELETE FROM expense_tag WHERE expense_id = ?1", [expense_id])?;
+    return Ok(());
+}
+
+pub fn cascade_delete_transaction(transaction: &Transaction, transaction_id: String) -> anyhow::Result<()> {
+    transaction.execute("DELETE FROM transaction_tag WHERE transaction_id = ?1", [transaction_id])?;
+    return Ok(());
+}
+
+pub fn cascade_merge_transaction(transaction: &Transaction, transaction_id: String, target_transaction_id: String) -> anyhow::Result<()> {
+    transaction.execute(
+        "INSERT OR IGNORE INTO transaction_tag (transaction_id, tag_id) SELECT ?2, tag_id FROM transaction_tag WHERE transaction_id = ?1",
+        [transaction_id.clone(), target_transaction_id],
+    )?;
+    transaction.execute("DELETE FROM transaction_tag WHERE transaction_id = ?1", [transaction_id])?;
+    return Ok(());
+}
+
+pub fn cascade_delete_account(transaction: &Transaction, account_id: String) -> anyhow::Result<()> {
+    transaction.execute(
+        "DELETE FROM transaction_tag WHERE transaction_id IN (SELECT id FROM account_transaction WHERE account_id = ?1 OR from_account_id = ?1)",
+        [account_id],
+    )?;
+    return Ok(());
+}
+
+fn verify_tag_ids_exist(transaction: &Transaction, tag_ids: &[String]) -> anyhow::Result<()> {
+    for tag_id in tag_ids {
+        if get_tag(transaction, tag_id.clone())?.is_none() {
+            return Err(anyhow!("Tag {} does not exist", tag_id));
+        }
+    }
+    return Ok(());
+}
@@ -0,0 +1,52 @@
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use crate::db::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NewTag {
+    pub name: String,
+}
+
+// Replaces every tag on an expense or transaction
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TagAssignment {
+    #[serde(rename = "tagIds")]
+    pub tag_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TagQuery {
+    // Comma separated tag ids, anything with at least one of them is kept
+    pub tags: Option<String>,
+}
+
+// Either an expense or a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagLink {
+    pub item_id: String,
+    pub tag_id: String,
+}
+
+impl FromRow for Tag {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Tag {
+            id: row.get("id")?,
+            name: row.get("name")?,
+        })
+    }
+}
+
+impl FromRow for TagLink {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(TagLink {
+            item_id: row.get("item_id")?,
+            tag_id: row.get("tag_id")?,
+        })
+    }
+}
@@ -2,6 +2,8 @@ use actix_web::{delete, Error, error, get, HttpResponse, post, web};
 use log::{error, info};
 use crate::db::{do_in_transaction, Pool};
 use crate::transaction::db;
+use crate::tag::db::{list_transaction_tag_links, tagged_item_ids};
+use crate::tag::schema::TagQuery;
 use crate::transaction::schema::{NewTransaction, Transaction};
 
 #[post("/api/transaction/")]
@@ -62,10 +64,14 @@ pub async fn get_transaction(db: web::Data<Pool>, path: web::Path<String>) -> Re
 }
 
 #[get("/api/account/{account_id}/transaction/")]
-pub async fn list_account_transactions(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+pub async fn list_account_transactions(db: web::Data<Pool>, path: web::Path<String>, query: web::Query<TagQuery>) -> Result<HttpResponse, Error> {
     let account_id = path.clone();
-    info!("HTTP list_transactions account_id:[{account_id}]");
-    do_in_transaction(&db, |transaction| db::list_account_transactions(transaction, account_id))
+    let query = query.into_inner();
+    info!("HTTP list_transactions account_id:[{account_id}] query:[{query:?}]");
+    do_in_transaction(&db, |transaction| {
+        let transactions = db::list_account_transactions(transaction, account_id)?;
+        return filter_tagged(transaction, transactions, query.tags);
+    })
         .await
         .map(|value| HttpResponse::Ok().json(value))
         .map_err(|err| {
@@ -75,13 +81,27 @@ pub async fn list_account_transactions(db: web::Data<Pool>, path: web::Path<Stri
 }
 
 #[get("/api/transaction/")]
-pub async fn list_transactions(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
-    info!("HTTP list_all_transactions");
-    do_in_transaction(&db, |transaction| db::list_transactions(transaction))
+pub async fn list_transactions(db: web::Data<Pool>, query: web::Query<TagQuery>) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    info!("HTTP list_all_transactions query:[{query:?}]");
+    do_in_transaction(&db, |transaction| {
+        let transactions = db::list_transactions(transaction)?;
+        return filter_tagged(transaction, transactions, query.tags);
+    })
         .await
         .map(|value| HttpResponse::Ok().json(value))
         .map_err(|err| {
             error!("HTTP list_all_transactions: [{err}]");
             return error::ErrorInternalServerError(err)
         })
-}
\ No newline at end of file
+}
+
+fn filter_tagged(transaction: &rusqlite::Transaction, transactions: Vec<Transaction>, tags: Option<String>) -> anyhow::Result<Vec<Transaction>> {
+    let Some(tags) = tags else {
+        return Ok(transactions);
+    };
+    let transaction_ids = tagged_item_ids(&list_transaction_tag_links(transaction)?, &tags);
+    return Ok(transactions.into_iter()
+        .filter(|transaction| transaction_ids.contains(&transaction.id))
+        .collect());
+}
@@ -6,46 +6,123 @@ use rusqlite::{params};
 use rust_decimal::Decimal;
 use uuid::Uuid;
 use crate::account::db::verify_account_id_exists;
-use crate::db::{list, single};
+use crate::account::schema::{AccountMergeConflict, AccountMergeConflictKind};
+use crate::audit::db::{record_create, record_delete, record_purge, record_restore, record_update};
+use crate::audit::schema::AuditEntityType;
+use crate::db::{format_timestamp, list, single};
 use crate::transaction::schema::{NewTransaction, Transaction, TransactionType};
+use crate::trash::db::create_trash_entry;
+use crate::trash::schema::{TrashEntityType, TrashEntry};
 
 const TRANSACTION_COLUMNS: &str = "id, description, date, value, type, account_id, from_account_id";
 const TRANSACTION_SELECT: &str = formatcp!("SELECT {TRANSACTION_COLUMNS} FROM account_transaction");
 const TRANSACTION_RETURNING: &str = formatcp!("RETURNING {TRANSACTION_COLUMNS}");
 const TRANSACTION_ORDERING: &str = "ORDER BY date, type, account_id";
+// Transactions in the trash are left out everywhere else
+const TRANSACTION_LIVE: &str = "deleted_at IS NULL";
 
 pub fn create_transaction(transaction: &rusqlite::Transaction, new_transaction: NewTransaction) -> anyhow::Result<Option<Transaction>> {
     verify(transaction, new_transaction.transaction_type.clone(), new_transaction.account_id.clone(), new_transaction.from_account_id.clone())?;
+    verify_open(transaction, new_transaction.date, new_transaction.account_id.clone(), new_transaction.from_account_id.clone())?;
     verify_unique(transaction, None, new_transaction.transaction_type.clone(), new_transaction.account_id.clone(), new_transaction.from_account_id.clone(), new_transaction.date.clone())?;
-    return single(
+    let account_transaction: Option<Transaction> = single(
         transaction,
         formatcp!("INSERT INTO account_transaction ({TRANSACTION_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) {TRANSACTION_RETURNING}"),
         params![Uuid::new_v4().to_string(), new_transaction.description, new_transaction.date.to_string(), normalize_decimal(&new_transaction.value).to_string(), new_transaction.transaction_type.to_string(), new_transaction.account_id, new_transaction.from_account_id]
-    );
+    )?;
+    if let Some(account_transaction) = &account_transaction {
+        record_create(transaction, AuditEntityType::Transaction, &account_transaction.id, account_transaction)?;
+    }
+    return Ok(account_transaction);
 }
 
 pub fn update_transaction(transaction: &rusqlite::Transaction, updated_transaction: Transaction) -> anyhow::Result<Option<Transaction>> {
     verify(transaction, updated_transaction.transaction_type.clone(), updated_transaction.account_id.clone(), updated_transaction.from_account_id.clone())?;
     verify_unique(transaction, Some(updated_transaction.id.clone()), updated_transaction.transaction_type.clone(), updated_transaction.account_id.clone(), updated_transaction.from_account_id.clone(), updated_transaction.date.clone())?;
-    return single(
+    let previous_account_transaction = get_transaction(transaction, updated_transaction.id.clone())?;
+    if let Some(previous_account_transaction) = &previous_account_transaction {
+        verify_open(transaction, previous_account_transaction.date, previous_account_transaction.account_id.clone(), previous_account_transaction.from_account_id.clone())?;
+    }
+    verify_open(transaction, updated_transaction.date, updated_transaction.account_id.clone(), updated_transaction.from_account_id.clone())?;
+    let account_transaction: Option<Transaction> = single(
         transaction,
-        formatcp!("UPDATE account_transaction SET description = ?1, date = ?2, value = ?3, type = ?4, account_id = ?5, from_account_id = ?6 WHERE id = ?7 {}", TRANSACTION_RETURNING),
+        formatcp!("UPDATE account_transaction SET description = ?1, date = ?2, value = ?3, type = ?4, account_id = ?5, from_account_id = ?6 WHERE id = ?7 AND {} {}", TRANSACTION_LIVE, TRANSACTION_RETURNING),
         params![updated_transaction.description, updated_transaction.date.to_string(), normalize_decimal(&updated_transaction.value).to_string(), updated_transaction.transaction_type.to_string(), updated_transaction.account_id, updated_transaction.from_account_id, updated_transaction.id]
-    );
+    )?;
+    if let Some(account_transaction) = &account_transaction {
+        record_update(transaction, AuditEntityType::Transaction, &account_transaction.id, previous_account_transaction.as_ref(), account_transaction)?;
+    }
+    return Ok(account_transaction);
 }
 
 pub fn delete_transaction(transaction: &rusqlite::Transaction, id: String) -> anyhow::Result<Option<Transaction>> {
-    return single(
+    let Some(previous_account_transaction) = get_transaction(transaction, id.clone())? else {
+        return Ok(None);
+    };
+    verify_open(transaction, previous_account_transaction.date, previous_account_transaction.account_id.clone(), previous_account_transaction.from_account_id.clone())?;
+    let trash_entry = create_trash_entry(transaction, TrashEntityType::Transaction, &id, &previous_account_transaction, vec![])?;
+    let account_transaction: Option<Transaction> = single(
+        transaction,
+        formatcp!("UPDATE account_transaction SET deleted_at = ?1, deletion_id = ?2 WHERE id = ?3 {TRANSACTION_RETURNING}"),
+        [format_timestamp(&trash_entry.deleted_at), trash_entry.id, id]
+    )?;
+    if let Some(account_transaction) = &account_transaction {
+        record_delete(transaction, AuditEntityType::Transaction, &account_transaction.id, account_transaction)?;
+    }
+    return Ok(account_transaction);
+}
+
+// Transactions already in the trash on their own stay in their own entry
+pub fn cascade_trash_account(transaction: &rusqlite::Transaction, account_id: String, trash_entry: &TrashEntry) -> anyhow::Result<()> {
+    let account_transactions: Vec<Transaction> = list(
+        transaction,
+        formatcp!("UPDATE account_transaction SET deleted_at = ?1, deletion_id = ?2 WHERE (account_id = ?3 OR from_account_id = ?3) AND {TRANSACTION_LIVE} {TRANSACTION_RETURNING}"),
+        [format_timestamp(&trash_entry.deleted_at), trash_entry.id.clone(), account_id]
+    )?;
+    for account_transaction in account_transactions {
+        verify_open(transaction, account_transaction.date, account_transaction.account_id.clone(), account_transaction.from_account_id.clone())?;
+        record_delete(transaction, AuditEntityType::Transaction, &account_transaction.id, &account_transaction)?;
+    }
+    return Ok(());
+}
+
+// Each transaction is checked again since its accounts or another balance on the same day may have changed since
+pub fn restore_transactions(transaction: &rusqlite::Transaction, deletion_id: String) -> anyhow::Result<()> {
+    let account_transactions: Vec<Transaction> = list(
+        transaction,
+        formatcp!("{TRANSACTION_SELECT} WHERE deletion_id = ?1 {TRANSACTION_ORDERING}"),
+        [deletion_id]
+    )?;
+    for account_transaction in account_transactions {
+        verify(transaction, account_transaction.transaction_type.clone(), account_transaction.account_id.clone(), account_transaction.from_account_id.clone())?;
+        verify_unique(transaction, Some(account_transaction.id.clone()), account_transaction.transaction_type.clone(), account_transaction.account_id.clone(), account_transaction.from_account_id.clone(), account_transaction.date)?;
+        verify_open(transaction, account_transaction.date, account_transaction.account_id.clone(), account_transaction.from_account_id.clone())?;
+        transaction.execute("UPDATE account_transaction SET deleted_at = NULL, deletion_id = NULL WHERE id = ?1", [account_transaction.id.clone()])?;
+        record_restore(transaction, AuditEntityType::Transaction, &account_transaction.id, &account_transaction)?;
+    }
+    return Ok(());
+}
+
+pub fn purge_transaction(transaction: &rusqlite::Transaction, id: String) -> anyhow::Result<()> {
+    crate::expense::db::cascade_delete_transaction(transaction, id.clone())?;
+    crate::household::db::cascade_delete_transaction(transaction, id.clone())?;
+    crate::tag::db::cascade_delete_transaction(transaction, id.clone())?;
+    crate::attachment::db::cascade_delete_transaction(transaction, id.clone())?;
+    let account_transaction: Option<Transaction> = single(
         transaction,
         formatcp!("DELETE FROM account_transaction WHERE id = ?1 {TRANSACTION_RETURNING}"),
         [id]
-    );
+    )?;
+    if let Some(account_transaction) = &account_transaction {
+        record_purge(transaction, AuditEntityType::Transaction, &account_transaction.id, account_transaction)?;
+    }
+    return Ok(());
 }
 
 pub fn get_transaction(transaction: &rusqlite::Transaction, id: String) -> anyhow::Result<Option<Transaction>> {
     return single(
         transaction,
-        formatcp!("{TRANSACTION_SELECT} WHERE id = ?1"),
+        formatcp!("{TRANSACTION_SELECT} WHERE id = ?1 AND {TRANSACTION_LIVE}"),
         [id]
     );
 }
@@ -53,7 +130,7 @@ pub fn get_transaction(transaction: &rusqlite::Transaction, id: String) -> anyho
 pub fn list_account_transactions(transaction: &rusqlite::Transaction, account_id: String) -> anyhow::Result<Vec<Transaction>> {
     return list(
         transaction,
-        formatcp!("{TRANSACTION_SELECT} WHERE account_id = ?1 OR from_account_id = ?1 {TRANSACTION_ORDERING}"),
+        formatcp!("{TRANSACTION_SELECT} WHERE (account_id = ?1 OR from_account_id = ?1) AND {TRANSACTION_LIVE} {TRANSACTION_ORDERING}"),
         [account_id]
     ).map(sort);
 }
@@ -61,16 +138,78 @@ pub fn list_account_transactions(transaction: &rusqlite::Transaction, account_id
 pub fn list_transactions(transaction: &rusqlite::Transaction) -> anyhow::Result<Vec<Transaction>> {
     return list(
         transaction,
-        formatcp!("{TRANSACTION_SELECT} {TRANSACTION_ORDERING}"),
+        formatcp!("{TRANSACTION_SELECT} WHERE {TRANSACTION_LIVE} {TRANSACTION_ORDERING}"),
         [],
     ).map(sort);
 }
 
 pub fn cascade_delete_account(transaction: &rusqlite::Transaction, account_id: String) -> anyhow::Result<()> {
-    delete_transaction_by_account(transaction, account_id)?;
+    for account_transaction in delete_transaction_by_account(transaction, account_id)? {
+        record_purge(transaction, AuditEntityType::Transaction, &account_transaction.id, &account_transaction)?;
+    }
     Ok(())
 }
 
+// Uses the same rules as verify_unique, the target account's transaction always wins
+pub fn find_merge_conflicts(transaction: &rusqlite::Transaction, account_id: String, target_account_id: String) -> anyhow::Result<(Vec<Transaction>, Vec<AccountMergeConflict>)> {
+    let target_transactions = list_account_transactions(transaction, target_account_id.clone())?;
+    let mut moved: Vec<Transaction> = vec![];
+    let mut conflicts: Vec<AccountMergeConflict> = vec![];
+    // Transactions in the trash are checked too since they get moved along with the rest
+    let account_transactions: Vec<Transaction> = list(
+        transaction,
+        formatcp!("{TRANSACTION_SELECT} WHERE account_id = ?1 OR from_account_id = ?1 {TRANSACTION_ORDERING}"),
+        [account_id.clone()]
+    ).map(sort)?;
+    for account_transaction in account_transactions {
+        verify_open(transaction, account_transaction.date, account_transaction.account_id.clone(), account_transaction.from_account_id.clone())?;
+        let merged = merge_account_ids(account_transaction.clone(), &account_id, &target_account_id);
+        verify_open(transaction, merged.date, merged.account_id.clone(), merged.from_account_id.clone())?;
+        if merged.transaction_type == TransactionType::Transfer && merged.from_account_id.as_ref() == Some(&merged.account_id) {
+            conflicts.push(AccountMergeConflict { kind: AccountMergeConflictKind::SelfTransfer, dropped: account_transaction, kept: None });
+            continue;
+        }
+        let kept = target_transactions.iter().find(|target_transaction| {
+            return target_transaction.transaction_type == merged.transaction_type
+                && target_transaction.account_id == merged.account_id
+                && target_transaction.from_account_id == merged.from_account_id
+                && target_transaction.date == merged.date;
+        });
+        match kept {
+            Some(kept) => {
+                let kind = match merged.transaction_type {
+                    TransactionType::Balance => AccountMergeConflictKind::Balance,
+                    TransactionType::Transfer => AccountMergeConflictKind::Transfer,
+                };
+                conflicts.push(AccountMergeConflict { kind, dropped: account_transaction, kept: Some(kept.clone()) });
+            }
+            None => moved.push(merged),
+        }
+    }
+    return Ok((moved, conflicts));
+}
+
+// Moves every remaining transaction including those in the trash, conflicts have to be dropped beforehand
+pub fn cascade_merge_account(transaction: &rusqlite::Transaction, account_id: String, target_account_id: String) -> anyhow::Result<()> {
+    let account_transactions: Vec<Transaction> = list(
+        transaction,
+        formatcp!("{TRANSACTION_SELECT} WHERE account_id = ?1 OR from_account_id = ?1"),
+        [account_id.clone()]
+    )?;
+    for previous_account_transaction in account_transactions {
+        let merged = merge_account_ids(previous_account_transaction.clone(), &account_id, &target_account_id);
+        let account_transaction: Option<Transaction> = single(
+            transaction,
+            formatcp!("UPDATE account_transaction SET account_id = ?1, from_account_id = ?2 WHERE id = ?3 {TRANSACTION_RETURNING}"),
+            params![merged.account_id, merged.from_account_id, merged.id]
+        )?;
+        if let Some(account_transaction) = &account_transaction {
+            record_update(transaction, AuditEntityType::Transaction, &account_transaction.id, Some(&previous_account_transaction), account_transaction)?;
+        }
+    }
+    return Ok(());
+}
+
 fn normalize_decimal(decimal: &Decimal) -> Decimal {
     let mut cloned = decimal.clone();
     cloned.rescale(2);
@@ -109,12 +248,21 @@ fn delete_transaction_by_account(transaction: &rusqlite::Transaction, account_id
     );
 }
 
+fn merge_account_ids(account_transaction: Transaction, account_id: &str, target_account_id: &str) -> Transaction {
+    let replace = |id: String| if id == account_id { target_account_id.to_string() } else { id };
+    return Transaction {
+        account_id: replace(account_transaction.account_id),
+        from_account_id: account_transaction.from_account_id.map(replace),
+        ..account_transaction
+    };
+}
+
 fn verify_unique(transaction: &rusqlite::Transaction, id: Option<String>, transaction_type: TransactionType, account_id: String, from_account_id: Option<String>, date: NaiveDate) -> anyhow::Result<()> {
     return match transaction_type {
         TransactionType::Balance => {
             let current: Vec<Transaction> = list(
                 transaction,
-                formatcp!("{TRANSACTION_SELECT} WHERE account_id = ?1 and date = ?2 and type = ?3"),
+                formatcp!("{TRANSACTION_SELECT} WHERE account_id = ?1 and date = ?2 and type = ?3 and {TRANSACTION_LIVE}"),
                 [account_id, date.to_string(), transaction_type.to_string()]
             )?;
             if !current.is_empty() && id.is_none() {
@@ -128,7 +276,7 @@ fn verify_unique(transaction: &rusqlite::Transaction, id: Option<String>, transa
         TransactionType::Transfer => {
             let current: Vec<Transaction> = list(
                 transaction,
-                formatcp!("{TRANSACTION_SELECT} WHERE account_id = ?1 and from_account_id = ?2 and date = ?3 and type = ?4"),
+                formatcp!("{TRANSACTION_SELECT} WHERE account_id = ?1 and from_account_id = ?2 and date = ?3 and type = ?4 and {TRANSACTION_LIVE}"),
                 [account_id, from_account_id.unwrap(), date.to_string(), transaction_type.to_string()]
             )?;
             if !current.is_empty() && id.is_none() {
@@ -148,6 +296,12 @@ fn verify(transaction: &rusqlite::Transaction, transaction_type: TransactionType
     Ok(())
 }
 
+// Both accounts of a transfer have to be open
+fn verify_open(transaction: &rusqlite::Transaction, date: NaiveDate, account_id: String, from_account_id: Option<String>) -> anyhow::Result<()> {
+    let account_ids: Vec<String> = [Some(account_id), from_account_id].into_iter().flatten().collect();
+    return crate::period_close::db::verify_open(transaction, date, &account_ids);
+}
+
 fn verify_from_account_id(transaction_type: TransactionType, from_account_id: Option<String>) -> anyhow::Result<()> {
     return match transaction_type {
         TransactionType::Balance => {
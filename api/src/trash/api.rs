@@ -0,0 +1,56 @@
+use actix_web::{delete, Error, error, get, HttpResponse, post, web};
+use log::{error, info};
+use crate::trash::db;
+use crate::db::{do_in_transaction, Pool};
+
+#[get("/api/trash/")]
+pub async fn list_trash_entries(db: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    info!("HTTP list_trash_entries");
+    do_in_transaction(&db, |transaction| db::list_trash_entries(transaction))
+        .await
+        .map(|value| HttpResponse::Ok().json(value))
+        .map_err(|err| {
+            error!("HTTP list_trash_entries: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[get("/api/trash/{id}/")]
+pub async fn get_trash_entry(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP get_trash_entry id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::get_trash_entry(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP get_trash_entry: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[post("/api/trash/{id}/restore/")]
+pub async fn restore_trash_entry(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP restore_trash_entry id:[{}]", id);
+    do_in_transaction(&db, |transaction| db::restore_trash_entry(transaction, id))
+        .await
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP restore_trash_entry: [{err}]");
+            return error::ErrorInternalServerError(err)
+        })
+}
+
+#[delete("/api/trash/{id}/")]
+pub async fn purge_trash_entry(db: web::Data<Pool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = path.clone();
+    info!("HTTP purge_trash_entry id:[{}]", id);
+    let result = do_in_transaction(&db, |transaction| db::purge_trash_entry(transaction, id)).await;
+    crate::attachment::api::remove_unreferenced_files(&db).await;
+    return result
+        .map(crate::api::handle_option)
+        .map_err(|err| {
+            error!("HTTP purge_trash_entry: [{err}]");
+            return error::ErrorInternalServerError(err)
+        });
+}
@@ -0,0 +1,74 @@
+use anyhow::anyhow;
+use const_format::formatcp;
+use rusqlite::{params, Transaction};
+use serde::Serialize;
+use uuid::Uuid;
+use crate::db::{list, single, timestamp};
+use crate::setting::schema::Setting;
+use crate::trash::schema::{TrashEntityType, TrashEntry};
+
+const TRASH_SELECT: &str = "SELECT id, entity_type, entity_id, entity, settings, deleted_at, (SELECT COUNT(*) FROM account_transaction WHERE deletion_id = trash.id AND id != trash.entity_id) AS cascaded FROM trash";
+const TRASH_ORDERING: &str = "ORDER BY deleted_at DESC, id ASC";
+
+// Rows deleted along with the entity are marked with the id of the returned entry
+pub fn create_trash_entry<T: Serialize>(transaction: &Transaction, entity_type: TrashEntityType, entity_id: &str, entity: &T, settings: Vec<Setting>) -> anyhow::Result<TrashEntry> {
+    let id = Uuid::new_v4().to_string();
+    transaction.execute(
+        "INSERT INTO trash (id, entity_type, entity_id, entity, settings, deleted_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![id, entity_type.to_string(), entity_id, serde_json::to_string(entity)?, serde_json::to_string(&settings)?, timestamp()],
+    )?;
+    return get_trash_entry(transaction, id.clone())?
+        .ok_or(anyhow!("Trash entry {} was not created", id));
+}
+
+pub fn get_trash_entry(transaction: &Transaction, id: String) -> anyhow::Result<Option<TrashEntry>> {
+    return single(
+        transaction,
+        formatcp!("{TRASH_SELECT} WHERE id = ?1"),
+        [id],
+    );
+}
+
+pub fn list_trash_entries(transaction: &Transaction) -> anyhow::Result<Vec<TrashEntry>> {
+    return list(
+        transaction,
+        formatcp!("{TRASH_SELECT} {TRASH_ORDERING}"),
+        [],
+    );
+}
+
+pub fn restore_trash_entry(transaction: &Transaction, id: String) -> anyhow::Result<Option<TrashEntry>> {
+    let Some(trash_entry) = get_trash_entry(transaction, id.clone())? else {
+        return Ok(None);
+    };
+    match trash_entry.entity_type {
+        TrashEntityType::Account => crate::account::db::restore_account(transaction, &trash_entry)?,
+        TrashEntityType::Transaction => crate::transaction::db::restore_transactions(transaction, trash_entry.id.clone())?,
+        TrashEntityType::Expense => crate::expense::db::restore_expense(transaction, trash_entry.id.clone())?,
+    }
+    transaction.execute("DELETE FROM trash WHERE id = ?1", [id])?;
+    return Ok(Some(trash_entry));
+}
+
+// Purging an account or merging one takes transactions that were deleted on their own beforehand with it
+pub fn delete_orphaned_entries(transaction: &Transaction) -> anyhow::Result<()> {
+    transaction.execute(
+        "DELETE FROM trash WHERE entity_type = ?1 AND entity_id NOT IN (SELECT id FROM account_transaction)",
+        [TrashEntityType::Transaction.to_string()],
+    )?;
+    return Ok(());
+}
+
+pub fn purge_trash_entry(transaction: &Transaction, id: String) -> anyhow::Result<Option<TrashEntry>> {
+    let Some(trash_entry) = get_trash_entry(transaction, id.clone())? else {
+        return Ok(None);
+    };
+    match trash_entry.entity_type {
+        TrashEntityType::Account => crate::account::db::purge_account(transaction, trash_entry.entity_id.clone())?,
+        TrashEntityType::Transaction => crate::transaction::db::purge_transaction(transaction, trash_entry.entity_id.clone())?,
+        TrashEntityType::Expense => crate::expense::db::purge_expense(transaction, trash_entry.entity_id.clone())?,
+    }
+    transaction.execute("DELETE FROM trash WHERE id = ?1", [id])?;
+    delete_orphaned_entries(transaction)?;
+    return Ok(Some(trash_entry));
+}
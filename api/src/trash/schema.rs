@@ -0,0 +1,65 @@
+#![allow(unreachable_patterns)]
+
+use std::str::FromStr;
+use chrono::{DateTime, Utc};
+use rusqlite::Row;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum_macros::{Display, EnumString};
+use crate::db::{FromRow, get_date_time, get_optional_json};
+use crate::setting::schema::Setting;
+
+// One delete, restoring or purging it also covers everything that was deleted along with it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrashEntry {
+    pub id: String,
+    #[serde(rename = "entityType")]
+    pub entity_type: TrashEntityType,
+    #[serde(rename = "entityId")]
+    pub entity_id: String,
+    // As it was when deleted
+    pub entity: Value,
+    // Settings that referred to a deleted account as they were beforehand
+    pub settings: Vec<Setting>,
+    // How many transactions went with a deleted account
+    pub cascaded: i64,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Display, EnumString, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TrashEntityType {
+    #[serde(rename = "ACCOUNT")]
+    #[strum(serialize = "ACCOUNT", to_string = "ACCOUNT")]
+    Account,
+    #[serde(rename = "TRANSACTION")]
+    #[strum(serialize = "TRANSACTION", to_string = "TRANSACTION")]
+    Transaction,
+    #[serde(rename = "EXPENSE")]
+    #[strum(serialize = "EXPENSE", to_string = "EXPENSE")]
+    Expense,
+}
+
+impl FromRow for TrashEntry {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(TrashEntry {
+            id: row.get("id")?,
+            entity_type: row.get("entity_type")?,
+            entity_id: row.get("entity_id")?,
+            entity: get_optional_json(row, "entity")?.unwrap_or(Value::Null),
+            settings: get_optional_json(row, "settings")?.unwrap_or_default(),
+            cascaded: row.get("cascaded")?,
+            deleted_at: get_date_time(row, "deleted_at")?,
+        })
+    }
+}
+
+impl FromSql for TrashEntityType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str() {
+            Ok(value) => TrashEntityType::from_str(value).map_err(|err| FromSqlError::Other(Box::new(err))),
+            Err(err) => Err(err)
+        }
+    }
+}
@@ -4,6 +4,8 @@ import { RootState } from "./store"
 export enum SettingKey {
   DEFAULT_TRANSACTION_FROM_ACCOUNT_ID = "DEFAULT_TRANSACTION_FROM_ACCOUNT_ID",
   REPEATING_TRANSFERS = "REPEATING_TRANSFERS",
+  ATTACHMENT_DIRECTORY = "ATTACHMENT_DIRECTORY",
+  CREDIT_UTILISATION_THRESHOLD = "CREDIT_UTILISATION_THRESHOLD",
 }
 
 export interface SettingState {
@@ -40,6 +40,12 @@ enum LastSType {
   MONTHS = "MONTHS",
 }
 
+interface JExpenseCategory {
+  id: string
+  name: string
+  hidden: boolean
+}
+
 interface JExpense {
   id: string
   description: string
@@ -49,6 +55,10 @@ interface JExpense {
   value: string
 }
 
+interface JExpenseSearch {
+  expenses: JExpense[]
+}
+
 function ExpenseModal({
   show,
   setShow,
@@ -60,7 +70,7 @@ function ExpenseModal({
 }: {
   show: boolean
   setShow: (value: boolean) => void
-  categories: string[]
+  categories: JExpenseCategory[]
   expense: Partial<JExpense>
   setExpense: (expense: Partial<JExpense>) => void
   saving: boolean
@@ -106,11 +116,16 @@ function ExpenseModal({
                 })
               }
             >
-              {categories.map((type) => (
-                <option key={type} value={type}>
-                  {titleCase(type)}
-                </option>
-              ))}
+              {categories
+                .filter(
+                  (category) =>
+                    !category.hidden || category.id === expense?.category,
+                )
+                .map((category) => (
+                  <option key={category.id} value={category.id}>
+                    {category.name}
+                  </option>
+                ))}
             </Form.Select>
           </Form.Group>
           <Form.Group>
@@ -158,7 +173,7 @@ export function Expenses() {
   const [expenses, setExpenses] = useState<JExpense[]>([])
   const [filteredExpenses, setFilteredExpenses] = useState<JExpense[]>([])
 
-  const [categories, setCategories] = useState<string[]>([])
+  const [categories, setCategories] = useState<JExpenseCategory[]>([])
 
   const [inlineExpense, setInlineExpense] = useState<Partial<JExpense>>({})
   const [savingInlineExpense, setSavingInlineExpense] = useState<boolean>(false)
@@ -196,12 +211,12 @@ export function Expenses() {
       })
     }
 
-    get<string[]>(server, `/api/expense-category/`)
+    get<JExpenseCategory[]>(server, `/api/expense-category/`)
       .then((categories) => {
         setCategories(categories)
-        get<JExpense[]>(server, `/api/expense/`)
-          .then((expenses) => {
-            setExpenses(sortExpenses(expenses))
+        get<JExpenseSearch>(server, `/api/expense/`)
+          .then((search) => {
+            setExpenses(sortExpenses(search.expenses))
             setInlineExpense({})
           })
           .catch((error) => err(error))
@@ -398,9 +413,9 @@ export function Expenses() {
             onChange={(e) => setSearchParams("category", e.target.value)}
           >
             <option value={"none"}></option>
-            {categories.map((type) => (
-              <option key={type} value={type}>
-                {titleCase(type)}
+            {categories.map((category) => (
+              <option key={category.id} value={category.id}>
+                {category.name}
               </option>
             ))}
           </Form.Select>
@@ -556,11 +571,17 @@ export function Expenses() {
                         })
                       }
                     >
-                      {categories.map((type) => (
-                        <option key={type} value={type}>
-                          {titleCase(type)}
-                        </option>
-                      ))}
+                      {categories
+                        .filter(
+                          (category) =>
+                            !category.hidden ||
+                            category.id === expense?.category,
+                        )
+                        .map((category) => (
+                          <option key={category.id} value={category.id}>
+                            {category.name}
+                          </option>
+                        ))}
                     </Form.Select>
                   </td>
                   <td style={cellStyle("250px")}>
@@ -49,6 +49,27 @@ enum IssueType {
   TRANSFER_WITHOUT_BALANCE = "TRANSFER_WITHOUT_BALANCE",
   NO_BALANCE = "NO_BALANCE",
   NO_TRANSFER = "NO_TRANSFER",
+  OVER_BUDGET = "OVER_BUDGET",
+  SUBSCRIPTION_MISSING = "SUBSCRIPTION_MISSING",
+  SUBSCRIPTION_LATE = "SUBSCRIPTION_LATE",
+  SUBSCRIPTION_PRICE_INCREASE = "SUBSCRIPTION_PRICE_INCREASE",
+  CREDIT_CARD_PAYMENT_MISSING = "CREDIT_CARD_PAYMENT_MISSING",
+  CREDIT_UTILISATION_HIGH = "CREDIT_UTILISATION_HIGH",
+}
+
+interface JExpenseCategory {
+  id: string
+  name: string
+}
+
+interface JSubscription {
+  id: string
+  merchantId: string
+}
+
+interface JMerchant {
+  id: string
+  name: string
 }
 
 interface JIssue {
@@ -56,6 +77,8 @@ interface JIssue {
   accountId: string
   date: string
   fromAccountId?: string
+  categoryId?: string
+  subscriptionId?: string
 }
 
 export function Issues() {
@@ -66,6 +89,9 @@ export function Issues() {
   const settings = useAppSelector(selectSettings)
 
   const [issues, setIssues] = useState<JIssue[]>([])
+  const [categories, setCategories] = useState<JExpenseCategory[]>([])
+  const [subscriptions, setSubscriptions] = useState<JSubscription[]>([])
+  const [merchants, setMerchants] = useState<JMerchant[]>([])
 
   const [pageSize, _setPageSize] = useState(DEFAULT_PAGE_SIZE)
   const [page, _setPage] = useState(0)
@@ -117,7 +143,7 @@ export function Issues() {
         if (date != 0) {
           return date
         }
-        return right.accountId.localeCompare(left.accountId)
+        return (right.accountId ?? "").localeCompare(left.accountId ?? "")
       })
     }
 
@@ -127,6 +153,15 @@ export function Issues() {
     get<JTranscation[]>(server, `/api/transaction/`)
       .then((transactions) => setTransactions(sortTransactions(transactions)))
       .catch((error) => err(error))
+    get<JExpenseCategory[]>(server, `/api/expense-category/`)
+      .then((categories) => setCategories(categories))
+      .catch((error) => err(error))
+    get<JSubscription[]>(server, `/api/subscription/`)
+      .then((subscriptions) => setSubscriptions(subscriptions))
+      .catch((error) => err(error))
+    get<JMerchant[]>(server, `/api/merchant/`)
+      .then((merchants) => setMerchants(merchants))
+      .catch((error) => err(error))
   }
 
   function issuesToDisplay() {
@@ -171,6 +206,29 @@ export function Issues() {
     )
   }
 
+  function categoryName(categoryId?: string) {
+    const category = categories.find((category) => category.id === categoryId)
+    return category?.name ?? titleCase(categoryId ?? "")
+  }
+
+  function subscriptionName(subscriptionId?: string) {
+    const subscription = subscriptions.find(
+      (subscription) => subscription.id === subscriptionId,
+    )
+    const merchant = merchants.find(
+      (merchant) => merchant.id === subscription?.merchantId,
+    )
+    return merchant?.name ?? "Subscription"
+  }
+
+  function isSubscription(type: IssueType) {
+    return (
+      type === IssueType.SUBSCRIPTION_MISSING ||
+      type === IssueType.SUBSCRIPTION_LATE ||
+      type === IssueType.SUBSCRIPTION_PRICE_INCREASE
+    )
+  }
+
   useEffect(() => refresh(), [])
 
   useEffect(() => {
@@ -208,10 +266,16 @@ export function Issues() {
                   <td style={cellStyle("100px")}>{titleCase(issue.type)}</td>
                   <td style={cellStyle("100px")}>{issue.date}</td>
                   <td style={cellStyle("200px")}>
-                    <AccountName
-                      accounts={accounts}
-                      accountId={issue.accountId}
-                    />
+                    {issue.type === IssueType.OVER_BUDGET ? (
+                      categoryName(issue.categoryId)
+                    ) : isSubscription(issue.type) ? (
+                      subscriptionName(issue.subscriptionId)
+                    ) : (
+                      <AccountName
+                        accounts={accounts}
+                        accountId={issue.accountId}
+                      />
+                    )}
                   </td>
                   <td style={cellStyle("200px")}>
                     {issue.fromAccountId === undefined ? null : (
@@ -222,7 +286,13 @@ export function Issues() {
                     )}
                   </td>
                   <td style={cellStyle("100px")}>
-                    <ButtonGroup>
+                    <ButtonGroup
+                      hidden={
+                        issue.type === IssueType.OVER_BUDGET ||
+                        issue.type === IssueType.CREDIT_UTILISATION_HIGH ||
+                        isSubscription(issue.type)
+                      }
+                    >
                       {isBalance(issue.type) ? (
                         <>
                           <Button
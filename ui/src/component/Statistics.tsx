@@ -24,6 +24,12 @@ import React from "react"
 import { IndexedAccounts, selectAccounts } from "../app/accountSlice"
 import { useSearchParams } from "react-router-dom"
 
+interface JExpenseCategory {
+  id: string
+  name: string
+  hidden: boolean
+}
+
 interface JValue {
   name: string
   value: number
@@ -128,7 +134,7 @@ function lines(
   viewType: ViewType,
   shownLines: Set<string>,
   accounts: IndexedAccounts,
-  categories: string[],
+  categories: JExpenseCategory[],
 ) {
   switch (viewType) {
     case ViewType.TOTAL_BALANCE:
@@ -249,19 +255,22 @@ function lines(
       const expensesColorPalette = generateColorPalette(categories.length + 2)
       return (
         <React.Fragment>
-          {["TOTAL", "CASH"]
-            .concat([...categories] as string[])
+          {[
+            { id: "TOTAL", name: titleCase("TOTAL") },
+            { id: "CASH", name: titleCase("CASH") },
+          ]
+            .concat(categories)
             .map((category, index) => (
               <Line
-                key={category}
+                key={category.id}
                 type="monotone"
-                dataKey={category}
+                dataKey={category.id}
                 stroke={dull(
-                  category,
+                  category.id,
                   shownLines,
                   expensesColorPalette[index + 1],
                 )}
-                name={titleCase(category)}
+                name={category.name}
               />
             ))}
         </React.Fragment>
@@ -318,7 +327,7 @@ export function Statistics() {
   const server = useAppSelector(selectServer)
   const accounts = useAppSelector(selectAccounts)
 
-  const [categories, setCategories] = useState<string[]>([])
+  const [categories, setCategories] = useState<JExpenseCategory[]>([])
 
   const [graphType, _setGraphType] = useState(DEFAULT_GRAPH_TYPE)
   const [lineStartDate, setLineStartDate] = useState("")
@@ -426,7 +435,7 @@ export function Statistics() {
   }, [searchParams])
 
   useEffect(() => {
-    get<string[]>(server, `/api/expense-category/`)
+    get<JExpenseCategory[]>(server, `/api/expense-category/`)
       .then((categories) => {
         setCategories(categories)
         get<JStatistic[]>(server, url(dateType, viewType))
@@ -489,10 +498,15 @@ export function Statistics() {
       }, {} as { [key: string]: string })
     }
     if (viewType === ViewType.EXPENSES) {
-      return ["TOTAL", "CASH"].concat(categories).reduce((acc, cur) => {
-        acc[cur] = titleCase(cur)
-        return acc
-      }, {} as { [key: string]: string })
+      return categories.reduce(
+        (acc, cur) => {
+          acc[cur.id] = cur.name
+          return acc
+        },
+        { TOTAL: titleCase("TOTAL"), CASH: titleCase("CASH") } as {
+          [key: string]: string
+        },
+      )
     }
     console.log("Unknown " + viewType)
     return {}